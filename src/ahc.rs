@@ -0,0 +1,411 @@
+use clap::ValueEnum;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+/// Whether a higher or a lower score is better.
+#[derive(ValueEnum, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Objective {
+    #[default]
+    Max,
+    Min,
+}
+
+/// How a seed ended; every status but `Ok` scores 0.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    #[serde(rename = "OK")]
+    Ok,
+    #[serde(rename = "TLE")]
+    TimeLimitExceeded,
+    #[serde(rename = "RE")]
+    RuntimeError,
+    #[serde(rename = "OLE")]
+    OutputLimitExceeded,
+    /// The scorer failed or printed no score, usually for an invalid output.
+    #[serde(rename = "SE")]
+    ScorerError,
+}
+
+impl Status {
+    pub fn code(self) -> &'static str {
+        match self {
+            Status::Ok => "OK",
+            Status::TimeLimitExceeded => "TLE",
+            Status::RuntimeError => "RE",
+            Status::OutputLimitExceeded => "OLE",
+            Status::ScorerError => "SE",
+        }
+    }
+}
+
+/// The result of a solution on one seed.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Seed {
+    /// The name of the input without its extension, such as `0042`.
+    pub seed: String,
+    pub status: Status,
+    pub score: i64,
+    pub time_ms: f64,
+}
+
+/// A run of a solution over many seeds, saved under `target/` to compare runs with.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AhcRun {
+    pub task: String,
+    /// The date of the run, such as `20240101-120000`, unless it was named.
+    pub name: String,
+    pub objective: Objective,
+    /// Hash of the source file, to tell which version of the solution was run.
+    #[serde(default)]
+    pub source_hash: String,
+    /// Seconds since the Unix epoch.
+    #[serde(default)]
+    pub timestamp: u64,
+    pub seeds: Vec<Seed>,
+}
+
+impl AhcRun {
+    /// Where the runs of `task` are kept.
+    pub fn dir(project_root: &Path, task: &str) -> PathBuf {
+        project_root.join("target/atcoder-rs/ahc").join(task)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        match fs::read_to_string(path) {
+            Ok(json) => Ok(serde_json::from_str(&json)?),
+            Err(e) if e.kind() == ErrorKind::NotFound => Err(Box::new(io::Error::new(
+                ErrorKind::NotFound,
+                format!("No run `{}`.", path.display()),
+            ))),
+            Err(e) => Err(Box::new(e)),
+        }
+    }
+
+    /// Every run saved in `dir`, oldest first; none if it does not exist.
+    pub fn load_all(dir: &Path) -> Result<Vec<Self>, Box<dyn Error>> {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(Box::new(e)),
+        };
+        let mut runs = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                runs.push(AhcRun::load(&path)?);
+            }
+        }
+        runs.sort_by(|a, b| (a.timestamp, &a.name).cmp(&(b.timestamp, &b.name)));
+        Ok(runs)
+    }
+
+    /// The run of `runs` named `reference`, or `latest` for the newest and `latest~N` for the
+    /// N-th before it; `runs` are oldest first.
+    pub fn find<'a>(runs: &'a [AhcRun], reference: &str) -> Result<&'a AhcRun, Box<dyn Error>> {
+        if let Some(run) = runs.iter().find(|run| run.name == reference) {
+            return Ok(run);
+        }
+        let back = match reference.strip_prefix("latest") {
+            Some("") => Some(0),
+            Some(back) => back.strip_prefix('~').and_then(|n| n.parse().ok()),
+            None => None,
+        };
+        back.and_then(|back: usize| runs.iter().rev().nth(back))
+            .ok_or_else(|| {
+                Box::new(io::Error::new(
+                    ErrorKind::NotFound,
+                    format!("No run `{reference}`. Runs are named after their date, or `latest`, `latest~1`, ..."),
+                )) as Box<dyn Error>
+            })
+    }
+
+    /// Writes `<name>.json` and `<name>.csv` to `dir`.
+    pub fn save(&self, dir: &Path) -> Result<(), Box<dyn Error>> {
+        fs::create_dir_all(dir)?;
+        fs::write(
+            dir.join(format!("{}.json", self.name)),
+            serde_json::to_string_pretty(self)?,
+        )?;
+        fs::write(dir.join(format!("{}.csv", self.name)), self.to_csv())?;
+        Ok(())
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("seed,status,score,time_ms\n");
+        for seed in &self.seeds {
+            let _ = writeln!(
+                csv,
+                "{},{},{},{:.1}",
+                seed.seed,
+                seed.status.code(),
+                seed.score,
+                seed.time_ms
+            );
+        }
+        csv
+    }
+
+    /// The run with only the seeds `other` has too, to compare the two on the same seeds.
+    pub fn common_with(&self, other: &AhcRun) -> AhcRun {
+        AhcRun {
+            seeds: self
+                .seeds
+                .iter()
+                .filter(|seed| other.seeds.iter().any(|s| s.seed == seed.seed))
+                .cloned()
+                .collect(),
+            ..self.clone()
+        }
+    }
+
+    pub fn total(&self) -> i64 {
+        self.seeds.iter().map(|seed| seed.score).sum()
+    }
+
+    /// The mean relative score of the seeds in percent, against the best scores of `best`.
+    pub fn relative(&self, best: &Best) -> f64 {
+        if self.seeds.is_empty() {
+            return 0.0;
+        }
+        let sum: f64 = self.seeds.iter().map(|seed| best.relative(seed)).sum();
+        sum / self.seeds.len() as f64 * 100.0
+    }
+
+    pub fn count(&self, status: Status) -> usize {
+        self.seeds
+            .iter()
+            .filter(|seed| seed.status == status)
+            .count()
+    }
+}
+
+/// The best score of every seed over several runs, as the reference of relative scores.
+#[derive(Debug, Default)]
+pub struct Best {
+    objective: Objective,
+    scores: HashMap<String, i64>,
+}
+
+impl Best {
+    pub fn of<'a>(runs: impl IntoIterator<Item = &'a AhcRun>, objective: Objective) -> Self {
+        let mut scores = HashMap::new();
+        for seed in runs.into_iter().flat_map(|run| &run.seeds) {
+            if seed.status != Status::Ok || (objective == Objective::Min && seed.score <= 0) {
+                continue;
+            }
+            scores
+                .entry(seed.seed.clone())
+                .and_modify(|best: &mut i64| {
+                    *best = match objective {
+                        Objective::Max => (*best).max(seed.score),
+                        Objective::Min => (*best).min(seed.score),
+                    }
+                })
+                .or_insert(seed.score);
+        }
+        Best { objective, scores }
+    }
+
+    /// The score of `seed` relative to the best one, from 0 to 1 as on AtCoder.
+    pub fn relative(&self, seed: &Seed) -> f64 {
+        let Some(&best) = self.scores.get(&seed.seed) else {
+            return 0.0;
+        };
+        if seed.status != Status::Ok || seed.score <= 0 || best <= 0 {
+            return 0.0;
+        }
+        match self.objective {
+            Objective::Max => seed.score as f64 / best as f64,
+            Objective::Min => best as f64 / seed.score as f64,
+        }
+    }
+}
+
+/// How the score of a seed changed from one run to another.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SeedChange {
+    pub seed: String,
+    pub before: i64,
+    pub after: i64,
+    /// Change of the relative score, in percentage points.
+    pub relative: f64,
+}
+
+impl SeedChange {
+    /// The changes of the seeds both runs have, in the order of `after`.
+    pub fn between(before: &AhcRun, after: &AhcRun, best: &Best) -> Vec<SeedChange> {
+        after
+            .seeds
+            .iter()
+            .filter_map(|seed| {
+                let old = before.seeds.iter().find(|old| old.seed == seed.seed)?;
+                Some(SeedChange {
+                    seed: seed.seed.clone(),
+                    before: old.score,
+                    after: seed.score,
+                    relative: (best.relative(seed) - best.relative(old)) * 100.0,
+                })
+            })
+            .collect()
+    }
+
+    pub fn improved(&self, objective: Objective) -> bool {
+        self.before != self.after
+            && match objective {
+                Objective::Max => self.after > self.before,
+                // A failed seed scores 0, which is never better.
+                Objective::Min => self.before == 0 || (self.after != 0 && self.after < self.before),
+            }
+    }
+
+    pub fn regressed(&self, objective: Objective) -> bool {
+        self.before != self.after && !self.improved(objective)
+    }
+}
+
+/// Stable across builds and platforms, unlike `DefaultHasher`.
+pub fn source_hash(source: &[u8]) -> String {
+    // 64-bit FNV-1a.
+    let hash = source
+        .iter()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, &byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        });
+    format!("{hash:016x}")
+}
+
+/// `timestamp` as a UTC date and time such as `20240101-120000`.
+pub fn run_name(timestamp: u64) -> String {
+    let (days, secs) = (timestamp / 86400, timestamp % 86400);
+    // Civil date from days since the epoch, after Howard Hinnant's algorithm.
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    format!(
+        "{year:04}{month:02}{day:02}-{:02}{:02}{:02}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// The last `Score = N` (or `score: N`) in what a scorer printed.
+pub fn parse_score(text: &str) -> Option<i64> {
+    let re = Regex::new(r"(?i)score\s*[=:]\s*(-?\d+)").unwrap();
+    re.captures_iter(text)
+        .last()
+        .and_then(|captures| captures[1].parse().ok())
+}
+
+#[cfg(test)]
+pub(crate) mod ahc_test {
+    use super::{
+        parse_score, run_name, source_hash, AhcRun, Best, Objective, Seed, SeedChange, Status,
+    };
+    use crate::project::project_test::TempDir;
+
+    pub(crate) fn run(name: &str, scores: &[i64]) -> AhcRun {
+        AhcRun {
+            task: "a".to_string(),
+            name: name.to_string(),
+            objective: Objective::Max,
+            source_hash: source_hash(name.as_bytes()),
+            timestamp: 0,
+            seeds: scores
+                .iter()
+                .enumerate()
+                .map(|(i, &score)| Seed {
+                    seed: format!("{i:04}"),
+                    status: if score > 0 {
+                        Status::Ok
+                    } else {
+                        Status::TimeLimitExceeded
+                    },
+                    score,
+                    time_ms: 100.0,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn parses_scores() {
+        assert_eq!(parse_score("Score = 12345\n"), Some(12345));
+        assert_eq!(parse_score("turn 3\nscore: -5\nScore = 7"), Some(7));
+        assert_eq!(parse_score("wrong answer: out of range"), None);
+    }
+
+    #[test]
+    fn relative_scores() {
+        let a = run("a", &[100, 50, 0]);
+        let b = run("b", &[50, 100, 10]);
+        let best = Best::of([&a, &b], Objective::Max);
+        assert_eq!(a.relative(&best), 50.0);
+        assert_eq!(b.relative(&best), (0.5 + 1.0 + 1.0) / 3.0 * 100.0);
+        assert_eq!(a.total(), 150);
+
+        let best = Best::of([&a, &b], Objective::Min);
+        assert_eq!(b.relative(&best), (1.0 + 0.5 + 1.0) / 3.0 * 100.0);
+    }
+
+    #[test]
+    fn save_and_load() {
+        let tmp = TempDir::new("ahc");
+        assert_eq!(AhcRun::load_all(&tmp.0.join("a")).unwrap(), vec![]);
+        let (a, b) = (run("b", &[3]), run("a", &[1, 0]));
+        a.save(&tmp.0).unwrap();
+        b.save(&tmp.0).unwrap();
+        assert_eq!(AhcRun::load_all(&tmp.0).unwrap(), vec![b, a.clone()]);
+        assert_eq!(
+            std::fs::read_to_string(tmp.0.join("b.csv")).unwrap(),
+            "seed,status,score,time_ms\n0000,OK,3,100.0\n"
+        );
+        assert_eq!(a.to_csv().lines().count(), 2);
+    }
+
+    #[test]
+    fn finds_runs() {
+        let runs = [run("first", &[1]), run("second", &[2]), run("third", &[3])];
+        assert_eq!(AhcRun::find(&runs, "second").unwrap().name, "second");
+        assert_eq!(AhcRun::find(&runs, "latest").unwrap().name, "third");
+        assert_eq!(AhcRun::find(&runs, "latest~2").unwrap().name, "first");
+        assert!(AhcRun::find(&runs, "latest~3").is_err());
+        assert!(AhcRun::find(&runs, "fourth").is_err());
+    }
+
+    #[test]
+    fn seed_changes() {
+        let (a, b) = (run("a", &[100, 50, 80]), run("b", &[50, 100, 80, 7]));
+        let best = Best::of([&a, &b], Objective::Max);
+        let changes = SeedChange::between(&a, &b, &best);
+        assert_eq!(changes.len(), 3);
+        assert_eq!(changes[0].relative, -50.0);
+        assert_eq!(changes[1].relative, 50.0);
+        assert!(changes[0].regressed(Objective::Max));
+        assert!(changes[1].improved(Objective::Max));
+        assert!(!changes[2].improved(Objective::Max) && !changes[2].regressed(Objective::Max));
+        assert!(changes[0].improved(Objective::Min));
+    }
+
+    #[test]
+    fn names_and_hashes() {
+        assert_eq!(run_name(0), "19700101-000000");
+        assert_eq!(run_name(1_709_210_096), "20240229-123456");
+        assert_eq!(source_hash(b""), "cbf29ce484222325");
+        assert_ne!(source_hash(b"a"), source_hash(b"b"));
+    }
+}
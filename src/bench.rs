@@ -0,0 +1,220 @@
+use serde::{Deserialize, Serialize};
+
+use std::error::Error;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Order statistics of timings, in milliseconds.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Stats {
+    pub min: f64,
+    pub median: f64,
+    /// Nearest-rank 95th percentile.
+    pub p95: f64,
+    pub max: f64,
+}
+
+impl Stats {
+    /// `None` without samples.
+    pub fn of(samples: &[Duration]) -> Option<Self> {
+        let mut ms: Vec<f64> = samples.iter().map(|d| d.as_secs_f64() * 1000.0).collect();
+        ms.sort_by(f64::total_cmp);
+        let n = ms.len();
+        if n == 0 {
+            return None;
+        }
+        let median = if n % 2 == 1 {
+            ms[n / 2]
+        } else {
+            (ms[n / 2 - 1] + ms[n / 2]) / 2.0
+        };
+        let p95 = ms[(n * 95).div_ceil(100) - 1];
+        Some(Stats {
+            min: ms[0],
+            median,
+            p95,
+            max: ms[n - 1],
+        })
+    }
+}
+
+/// Measurements of a solution on one input.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CaseBench {
+    pub name: String,
+    /// Wall-clock time.
+    pub wall: Stats,
+    /// User plus system CPU time.
+    pub cpu: Stats,
+    /// The largest peak resident set size of the runs.
+    pub memory_kb: Option<u64>,
+    /// Runs that did not exit with status 0.
+    pub failures: usize,
+}
+
+/// A benchmark of a task, which can be saved as a baseline under `target/`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Bench {
+    pub task: String,
+    /// Measured runs per input, after the warm-up runs.
+    pub runs: usize,
+    pub cases: Vec<CaseBench>,
+}
+
+/// Differences in median wall time below this are noise, however large relatively.
+const NOISE_MS: f64 = 1.0;
+
+impl Bench {
+    pub fn path(project_root: &Path, task: &str, name: &str) -> PathBuf {
+        project_root
+            .join("target/atcoder-rs/bench")
+            .join(task)
+            .join(format!("{name}.json"))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        match fs::read_to_string(path) {
+            Ok(json) => Ok(serde_json::from_str(&json)?),
+            Err(e) if e.kind() == ErrorKind::NotFound => Err(Box::new(io::Error::new(
+                ErrorKind::NotFound,
+                format!(
+                    "No baseline `{}`. Please save one with `--save-baseline`.",
+                    path.display()
+                ),
+            ))),
+            Err(e) => Err(Box::new(e)),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn case(&self, name: &str) -> Option<&CaseBench> {
+        self.cases.iter().find(|case| case.name == name)
+    }
+
+    /// Relative change of the median wall time of `case` from `baseline`, such as `0.25` for 25% slower.
+    pub fn change(case: &CaseBench, baseline: &Bench) -> Option<f64> {
+        let before = baseline.case(&case.name)?.wall.median;
+        (before > 0.0).then(|| case.wall.median / before - 1.0)
+    }
+
+    /// Whether `case` got slower than in `baseline` by more than `threshold`, such as `0.1` for 10%.
+    pub fn regressed(case: &CaseBench, baseline: &Bench, threshold: f64) -> bool {
+        let Some(before) = baseline.case(&case.name) else {
+            return false;
+        };
+        Bench::change(case, baseline).is_some_and(|change| change > threshold)
+            && case.wall.median - before.wall.median > NOISE_MS
+    }
+
+    /// The names of the `count` inputs with the largest median wall time, slowest first.
+    pub fn slowest(&self, count: usize) -> Vec<&str> {
+        let mut cases: Vec<&CaseBench> = self.cases.iter().collect();
+        cases.sort_by(|a, b| b.wall.median.total_cmp(&a.wall.median));
+        cases
+            .into_iter()
+            .take(count)
+            .map(|case| case.name.as_str())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod bench_test {
+    use super::{Bench, CaseBench, Stats};
+    use crate::project::project_test::TempDir;
+    use std::time::Duration;
+
+    pub(crate) fn stats(median: f64) -> Stats {
+        Stats {
+            min: median,
+            median,
+            p95: median,
+            max: median,
+        }
+    }
+
+    pub(crate) fn case(name: &str, median: f64) -> CaseBench {
+        CaseBench {
+            name: name.to_string(),
+            wall: stats(median),
+            cpu: stats(median),
+            memory_kb: Some(2048),
+            failures: 0,
+        }
+    }
+
+    #[test]
+    fn order_statistics() {
+        let ms = |ms: &[u64]| -> Vec<Duration> {
+            ms.iter().map(|&ms| Duration::from_millis(ms)).collect()
+        };
+        assert_eq!(Stats::of(&[]), None);
+        assert_eq!(
+            Stats::of(&ms(&[30, 10, 20])),
+            Some(Stats {
+                min: 10.0,
+                median: 20.0,
+                p95: 30.0,
+                max: 30.0
+            })
+        );
+        let stats = Stats::of(&ms(&(1..=100).collect::<Vec<_>>())).unwrap();
+        assert_eq!(stats.median, 50.5);
+        assert_eq!(stats.p95, 95.0);
+    }
+
+    #[test]
+    fn regressions() {
+        let baseline = Bench {
+            task: "a".to_string(),
+            runs: 10,
+            cases: vec![
+                case("1.txt", 100.0),
+                case("2.txt", 0.5),
+                case("3.txt", 50.0),
+            ],
+        };
+        let current = Bench {
+            cases: vec![
+                case("1.txt", 125.0),
+                case("2.txt", 1.0),
+                case("3.txt", 52.0),
+                case("4.txt", 900.0),
+            ],
+            ..baseline.clone()
+        };
+        let regressed: Vec<&str> = current
+            .cases
+            .iter()
+            .filter(|case| Bench::regressed(case, &baseline, 0.1))
+            .map(|case| case.name.as_str())
+            .collect();
+        assert_eq!(regressed, vec!["1.txt"]);
+        assert_eq!(Bench::change(&current.cases[0], &baseline), Some(0.25));
+        assert_eq!(Bench::change(&current.cases[3], &baseline), None);
+        assert_eq!(current.slowest(2), vec!["4.txt", "1.txt"]);
+    }
+
+    #[test]
+    fn save_and_load() {
+        let tmp = TempDir::new("bench");
+        let path = Bench::path(&tmp.0, "a", "baseline");
+        assert!(Bench::load(&path).is_err());
+        let bench = Bench {
+            task: "a".to_string(),
+            runs: 10,
+            cases: vec![case("1.txt", 12.5)],
+        };
+        bench.save(&path).unwrap();
+        assert_eq!(Bench::load(&path).unwrap(), bench);
+    }
+}
@@ -0,0 +1,159 @@
+use serde::{Deserialize, Serialize};
+
+use std::error::Error;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// A fixed benchmark of integer arithmetic, random memory access, sorting and hashing.
+///
+/// It prints the milliseconds it took and a checksum, so that it can also be run in the custom
+/// test of AtCoder to time the judge.
+pub const SUITE: &str = "\
+use std::collections::HashMap;
+use std::time::Instant;
+
+fn main() {
+    let mut x: u64 = 88172645463325252;
+    let mut next = move || {
+        x ^= x << 7;
+        x ^= x >> 9;
+        x
+    };
+    let start = Instant::now();
+    let mut check = 0u64;
+
+    // Integer arithmetic.
+    for i in 1..=30_000_000u64 {
+        check = check.wrapping_add(next() % i);
+    }
+
+    // Random memory access.
+    let n = 1 << 22;
+    let mut perm: Vec<u32> = (0..n as u32).collect();
+    for i in (1..n).rev() {
+        perm.swap(i, (next() % (i as u64 + 1)) as usize);
+    }
+    let mut j = 0;
+    for _ in 0..n {
+        j = perm[j] as usize;
+        check = check.wrapping_add(j as u64);
+    }
+
+    // Sorting.
+    let mut values: Vec<u64> = (0..3_000_000).map(|_| next()).collect();
+    values.sort_unstable();
+    check = check.wrapping_add(values[values.len() / 2]);
+
+    // Hashing.
+    let mut counts = HashMap::new();
+    for _ in 0..2_000_000 {
+        *counts.entry(next() % 1_000_000).or_insert(0u32) += 1;
+    }
+    check = check.wrapping_add(counts.len() as u64);
+
+    println!(\"{} {check}\", start.elapsed().as_millis());
+}
+";
+
+/// A rough time of the suite on the judge, used unless the measured one is given.
+pub const DEFAULT_JUDGE_MS: f64 = 700.0;
+
+/// How fast this machine runs the suite compared with the judge.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Calibration {
+    /// Median time of the suite on this machine.
+    pub local_ms: f64,
+    /// Time of the suite on the judge.
+    pub judge_ms: f64,
+}
+
+impl Calibration {
+    pub fn path(project_root: &Path) -> PathBuf {
+        project_root.join("target/atcoder-rs/calibration.json")
+    }
+
+    /// Reads the calibration, or `None` when the machine has not been calibrated yet.
+    pub fn load(path: &Path) -> Result<Option<Self>, Box<dyn Error>> {
+        match fs::read_to_string(path) {
+            Ok(json) => Ok(Some(serde_json::from_str(&json)?)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(Box::new(e)),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// How many times slower this machine is than the judge, such as `1.5`, or `0.8` when faster.
+    pub fn factor(&self) -> f64 {
+        self.local_ms / self.judge_ms
+    }
+
+    /// The time a program taking `judge` on the judge takes here.
+    pub fn local_time(&self, judge: Duration) -> Duration {
+        judge.mul_f64(self.factor())
+    }
+
+    /// The time a program taking `local` here would take on the judge.
+    pub fn judge_time(&self, local: Duration) -> Duration {
+        local.div_f64(self.factor())
+    }
+}
+
+/// The time in milliseconds printed by the suite.
+pub fn parse_suite_output(output: &str) -> Option<f64> {
+    output.split_whitespace().next()?.parse().ok()
+}
+
+#[cfg(test)]
+mod calibrate_test {
+    use super::{parse_suite_output, Calibration};
+    use crate::project::project_test::TempDir;
+    use std::time::Duration;
+
+    #[test]
+    fn scales() {
+        let calibration = Calibration {
+            local_ms: 1050.0,
+            judge_ms: 700.0,
+        };
+        assert_eq!(calibration.factor(), 1.5);
+        assert_eq!(
+            calibration.local_time(Duration::from_secs(2)),
+            Duration::from_secs(3)
+        );
+        assert_eq!(
+            calibration.judge_time(Duration::from_millis(1500)),
+            Duration::from_secs(1)
+        );
+    }
+
+    #[test]
+    fn saves_and_loads() {
+        let dir = TempDir::new("calibration");
+        let path = Calibration::path(&dir.0);
+        assert_eq!(Calibration::load(&path).unwrap(), None);
+        let calibration = Calibration {
+            local_ms: 900.0,
+            judge_ms: 700.0,
+        };
+        calibration.save(&path).unwrap();
+        assert_eq!(Calibration::load(&path).unwrap(), Some(calibration));
+    }
+
+    #[test]
+    fn parses_the_suite_output() {
+        assert_eq!(
+            parse_suite_output("1234 9231368562026434231\n"),
+            Some(1234.0)
+        );
+        assert_eq!(parse_suite_output(""), None);
+    }
+}
@@ -0,0 +1,220 @@
+mod cases_file;
+mod inline;
+mod layout;
+mod manifest;
+mod multi;
+mod selection;
+
+pub use cases_file::{format_cases_file, parse_cases_file, CASES_FILE_NAME};
+pub use inline::{embed_inline, parse_inline};
+#[cfg(test)]
+pub use layout::Skipped;
+pub use layout::{scan_dir, Layout, Scan};
+pub use manifest::{Manifest, MANIFEST_FILE_NAME};
+pub use multi::{Multi, Shape};
+pub use selection::{is_selected, natural_cmp, LastFailed, Pattern};
+
+use std::error::Error;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+/// An input and the output expected for it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Case {
+    /// Name in reports, such as `1.txt`.
+    pub name: String,
+    /// Where the input comes from, for messages.
+    pub origin: String,
+    /// Where the expected output comes from; the same as `origin` for single-file formats.
+    pub expect_origin: String,
+    pub input: String,
+    pub expect: String,
+}
+
+impl Case {
+    /// The name without its extension, as given to `--in-files`.
+    pub fn stem(&self) -> &str {
+        self.name
+            .split_once('.')
+            .map_or(self.name.as_str(), |(stem, _)| stem)
+    }
+}
+
+/// An input, and whether it has an expected output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Input {
+    pub case: Case,
+    pub answered: bool,
+}
+
+/// The cases of a task: the scan of its testcase directory, and the samples in the doc comment of
+/// its solution.
+#[derive(Debug, Default)]
+pub struct TaskCases {
+    pub scan: Scan,
+    pub inline: Vec<Case>,
+}
+
+impl TaskCases {
+    /// Every input: those of the testcase directory in natural order, with or without an expected
+    /// output, then the samples in the doc comment.
+    pub fn inputs(self) -> Vec<Input> {
+        let answered = self.scan.cases.into_iter().map(|case| Input {
+            case,
+            answered: true,
+        });
+        let unanswered = self.scan.unanswered.into_iter().map(|case| Input {
+            case,
+            answered: false,
+        });
+        let mut inputs: Vec<Input> = answered.chain(unanswered).collect();
+        inputs.sort_by(|a, b| natural_cmp(&a.case.name, &b.case.name));
+        inputs.extend(self.inline.into_iter().map(|case| Input {
+            case,
+            answered: true,
+        }));
+        inputs
+    }
+}
+
+/// Finds the cases of a task.
+///
+/// Without samples in the doc comment, the testcase directory is required.
+pub fn task_cases(
+    testcase_dir: &Path,
+    src_path: &Path,
+    layout: Option<Layout>,
+) -> Result<TaskCases, Box<dyn Error>> {
+    let inline = match fs::read_to_string(src_path) {
+        Ok(source) => parse_inline(&source, &src_path.display().to_string())?,
+        Err(_) => Vec::new(),
+    };
+    let scan = if inline.is_empty() || testcase_dir.try_exists()? {
+        check_file_existance(testcase_dir)?;
+        let layout = match layout {
+            Some(layout) => Some(layout),
+            None => Layout::from_dir(testcase_dir)?,
+        };
+        scan_dir(testcase_dir, layout)?
+    } else {
+        Scan::default()
+    };
+    Ok(TaskCases { scan, inline })
+}
+
+/// Every input of a task, as ordered by [`TaskCases::inputs`].
+pub fn task_inputs(
+    testcase_dir: &Path,
+    src_path: &Path,
+    layout: Option<Layout>,
+) -> Result<Vec<Input>, Box<dyn Error>> {
+    Ok(task_cases(testcase_dir, src_path, layout)?.inputs())
+}
+
+pub fn check_file_existance(path: &Path) -> Result<(), Box<dyn Error>> {
+    match path.try_exists() {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(Box::new(io::Error::new(
+            ErrorKind::NotFound,
+            format!("Please create `{:?}`", path),
+        ))),
+        Err(e) => Err(Box::new(e)),
+    }
+}
+
+/// Writes cases as `in/<name>` and `out/<name>`.
+///
+/// Files that already exist with the same contents are left alone; different contents are an error,
+/// checked before anything is written.
+pub fn write_dir(testcase_dir: &Path, cases: &[Case]) -> Result<(), Box<dyn Error>> {
+    for case in cases {
+        for (path, contents) in case_files(testcase_dir, case) {
+            match fs::read_to_string(&path) {
+                Ok(existing) if existing != contents => {
+                    return Err(Box::new(io::Error::new(
+                        ErrorKind::AlreadyExists,
+                        format!("`{}` already exists with other contents.", path.display()),
+                    )));
+                }
+                Err(e) if e.kind() != ErrorKind::NotFound => return Err(Box::new(e)),
+                _ => {}
+            }
+        }
+    }
+    fs::create_dir_all(testcase_dir.join("in"))?;
+    fs::create_dir_all(testcase_dir.join("out"))?;
+    for case in cases {
+        for (path, contents) in case_files(testcase_dir, case) {
+            fs::write(path, contents)?;
+        }
+    }
+    Ok(())
+}
+
+/// The input and output files of `case` with their contents.
+fn case_files<'a>(testcase_dir: &Path, case: &'a Case) -> [(PathBuf, &'a str); 2] {
+    [
+        (testcase_dir.join("in").join(&case.name), &case.input),
+        (testcase_dir.join("out").join(&case.name), &case.expect),
+    ]
+}
+
+#[cfg(test)]
+mod cases_test {
+    use super::{write_dir, Case, Layout};
+    use crate::project::project_test::TempDir;
+    use std::fs;
+
+    fn case(name: &str, input: &str, expect: &str) -> Case {
+        Case {
+            name: name.to_string(),
+            origin: String::new(),
+            expect_origin: String::new(),
+            input: input.to_string(),
+            expect: expect.to_string(),
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let tmp = TempDir::new("cases-dir");
+        let cases = vec![case("1.txt", "1\n", "2\n"), case("2.txt", "3\n", "6\n")];
+        write_dir(&tmp.0, &cases).unwrap();
+        // Writing the same cases again is fine.
+        write_dir(&tmp.0, &cases).unwrap();
+        let read: Vec<_> = Layout::InOut
+            .scan(&tmp.0)
+            .unwrap()
+            .cases
+            .into_iter()
+            .map(|c| (c.name, c.input, c.expect))
+            .collect();
+        assert_eq!(
+            read,
+            vec![
+                ("1.txt".to_string(), "1\n".to_string(), "2\n".to_string()),
+                ("2.txt".to_string(), "3\n".to_string(), "6\n".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn refuses_to_overwrite() {
+        let tmp = TempDir::new("cases-overwrite");
+        write_dir(&tmp.0, &[case("1.txt", "1\n", "2\n")]).unwrap();
+        let result = write_dir(
+            &tmp.0,
+            &[case("2.txt", "5\n", "10\n"), case("1.txt", "1\n", "3\n")],
+        );
+        assert!(result.is_err());
+        assert!(!tmp.0.join("in/2.txt").exists());
+        assert_eq!(fs::read_to_string(tmp.0.join("out/1.txt")).unwrap(), "2\n");
+    }
+
+    #[test]
+    fn stem() {
+        assert_eq!(case("1.txt", "", "").stem(), "1");
+        assert_eq!(case("inline-1", "", "").stem(), "inline-1");
+    }
+}
@@ -0,0 +1,198 @@
+use super::Case;
+
+use std::collections::HashSet;
+use std::error::Error;
+use std::io::{self, ErrorKind};
+
+/// Single file in a testcase directory holding all of its cases.
+pub const CASES_FILE_NAME: &str = "cases.txt";
+
+const MARKER: &str = "=== ";
+
+fn invalid(origin: &str, line: usize, message: &str) -> Box<dyn Error> {
+    Box::new(io::Error::new(
+        ErrorKind::InvalidData,
+        format!("{origin}:{line}: {message}"),
+    ))
+}
+
+/// Reads a cases file, made of sections starting with `=== input <name>` and `=== output <name>`.
+///
+/// Each input section must be followed by the output section of the same name.
+/// Trailing blank lines of a section are ignored, so sections can be separated by blank lines.
+pub fn parse_cases_file(text: &str, origin: &str) -> Result<Vec<Case>, Box<dyn Error>> {
+    // (line number of the marker, is input, name, contents)
+    let mut sections: Vec<(usize, bool, &str, String)> = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let marker = line.strip_prefix(MARKER).map(|rest| {
+            let (kind, name) = rest.trim().split_once(' ').unwrap_or((rest.trim(), ""));
+            (kind, name.trim())
+        });
+        match marker {
+            Some((kind @ ("input" | "output"), name)) => {
+                if name.is_empty() {
+                    return Err(invalid(origin, i + 1, "missing case name"));
+                }
+                sections.push((i + 1, kind == "input", name, String::new()));
+            }
+            Some((kind, _)) => {
+                return Err(invalid(
+                    origin,
+                    i + 1,
+                    &format!("unknown section `{kind}`, expected `input` or `output`"),
+                ));
+            }
+            None => match sections.last_mut() {
+                Some((_, _, _, contents)) => {
+                    contents.push_str(line);
+                    contents.push('\n');
+                }
+                None if line.trim().is_empty() => {}
+                None => return Err(invalid(origin, i + 1, "text before the first section")),
+            },
+        }
+    }
+
+    let mut cases = Vec::new();
+    let mut names = HashSet::new();
+    let mut sections = sections.into_iter();
+    while let Some((line, is_input, name, input)) = sections.next() {
+        if !is_input {
+            return Err(invalid(
+                origin,
+                line,
+                &format!("output `{name}` without input"),
+            ));
+        }
+        let expect = match sections.next() {
+            Some((_, false, output_name, expect)) if output_name == name => expect,
+            _ => {
+                return Err(invalid(
+                    origin,
+                    line,
+                    &format!("input `{name}` without output"),
+                ))
+            }
+        };
+        if !names.insert(name) {
+            return Err(invalid(origin, line, &format!("duplicate case `{name}`")));
+        }
+        cases.push(Case {
+            name: name.to_string(),
+            origin: format!("{origin}#{name}"),
+            expect_origin: format!("{origin}#{name}"),
+            input: trim_blank_lines(input),
+            expect: trim_blank_lines(expect),
+        });
+    }
+    Ok(cases)
+}
+
+fn trim_blank_lines(mut contents: String) -> String {
+    let len = contents.trim_end_matches(['\n', '\r']).len();
+    contents.truncate(len);
+    if len > 0 {
+        contents.push('\n');
+    }
+    contents
+}
+
+/// Writes cases in the format read by [`parse_cases_file`], named by their stems.
+///
+/// Trailing blank lines are dropped and a missing final newline is added, as they would be on reading.
+pub fn format_cases_file(cases: &[Case]) -> String {
+    let sections: Vec<String> = cases
+        .iter()
+        .map(|case| {
+            format!(
+                "{MARKER}input {name}\n{}{MARKER}output {name}\n{}",
+                trim_blank_lines(case.input.clone()),
+                trim_blank_lines(case.expect.clone()),
+                name = case.stem()
+            )
+        })
+        .collect();
+    sections.join("\n")
+}
+
+#[cfg(test)]
+mod cases_file_test {
+    use super::{format_cases_file, parse_cases_file};
+
+    const CASES: &str = "\
+=== input sample-1
+3
+1 2 3
+=== output sample-1
+6
+
+=== input max
+0
+=== output max
+0
+";
+
+    #[test]
+    fn parse() {
+        let cases = parse_cases_file(CASES, "testcase/a/cases.txt").unwrap();
+        let cases: Vec<_> = cases
+            .iter()
+            .map(|c| {
+                (
+                    c.name.as_str(),
+                    c.origin.as_str(),
+                    c.input.as_str(),
+                    c.expect.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            cases,
+            vec![
+                (
+                    "sample-1",
+                    "testcase/a/cases.txt#sample-1",
+                    "3\n1 2 3\n",
+                    "6\n"
+                ),
+                ("max", "testcase/a/cases.txt#max", "0\n", "0\n"),
+            ]
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let cases = parse_cases_file(CASES, "cases.txt").unwrap();
+        assert_eq!(format_cases_file(&cases), CASES);
+    }
+
+    #[test]
+    fn errors() {
+        let error = |text: &str| parse_cases_file(text, "cases.txt").unwrap_err().to_string();
+        assert_eq!(
+            error("1\n=== input a\n"),
+            "cases.txt:1: text before the first section"
+        );
+        assert_eq!(
+            error("=== input a\n1\n"),
+            "cases.txt:1: input `a` without output"
+        );
+        assert_eq!(
+            error("=== input a\n=== output b\n"),
+            "cases.txt:1: input `a` without output"
+        );
+        assert_eq!(
+            error("=== output a\n"),
+            "cases.txt:1: output `a` without input"
+        );
+        assert_eq!(
+            error("=== input a\n=== output a\n=== input a\n=== output a\n"),
+            "cases.txt:3: duplicate case `a`"
+        );
+        assert_eq!(
+            error("=== expected a\n"),
+            "cases.txt:1: unknown section `expected`, expected `input` or `output`"
+        );
+        assert_eq!(error("=== input\n"), "cases.txt:1: missing case name");
+    }
+}
@@ -0,0 +1,244 @@
+use super::Case;
+
+use std::error::Error;
+use std::io::{self, ErrorKind};
+
+const DOC_PREFIX: &str = "//!";
+const FENCE: &str = "```";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Block {
+    Input,
+    Output,
+}
+
+/// Text of a doc comment line without `//!` and the following space.
+fn doc_text(line: &str) -> Option<&str> {
+    let text = line.trim_start().strip_prefix(DOC_PREFIX)?;
+    Some(text.strip_prefix(' ').unwrap_or(text))
+}
+
+/// Number of lines in the leading `//!` comment of `source`.
+fn doc_len(source: &str) -> usize {
+    source
+        .lines()
+        .take_while(|line| doc_text(line).is_some())
+        .count()
+}
+
+fn invalid(origin: &str, line: usize, message: &str) -> Box<dyn Error> {
+    Box::new(io::Error::new(
+        ErrorKind::InvalidData,
+        format!("{origin}:{line}: {message}"),
+    ))
+}
+
+/// Reads the sample cases in the leading `//!` comment of `source`, named `inline-1`, `inline-2`, ...
+///
+/// Each ```` ```input ```` code block must be followed by an ```` ```output ```` code block.
+pub fn parse_inline(source: &str, origin: &str) -> Result<Vec<Case>, Box<dyn Error>> {
+    let mut cases = Vec::new();
+    let mut pending_input: Option<String> = None;
+    let mut open: Option<(Block, String)> = None;
+    for (i, line) in source.lines().take(doc_len(source)).enumerate() {
+        let text = doc_text(line).unwrap_or_default();
+        let line_number = i + 1;
+        match open.take() {
+            Some((block, contents)) if text.trim_end() == FENCE => match block {
+                Block::Input if pending_input.is_some() => {
+                    return Err(invalid(origin, line_number, "input without output"));
+                }
+                Block::Input => pending_input = Some(contents),
+                Block::Output => match pending_input.take() {
+                    Some(input) => {
+                        let name = format!("inline-{}", cases.len() + 1);
+                        cases.push(Case {
+                            origin: format!("{origin}#{name}"),
+                            expect_origin: format!("{origin}#{name}"),
+                            name,
+                            input,
+                            expect: contents,
+                        });
+                    }
+                    None => return Err(invalid(origin, line_number, "output without input")),
+                },
+            },
+            Some((block, mut contents)) => {
+                contents.push_str(text);
+                contents.push('\n');
+                open = Some((block, contents));
+            }
+            None => {
+                open = match text.trim_end().strip_prefix(FENCE).map(str::trim) {
+                    Some("input") => Some((Block::Input, String::new())),
+                    Some("output") => Some((Block::Output, String::new())),
+                    _ => None,
+                };
+            }
+        }
+    }
+    if open.is_some() {
+        return Err(invalid(origin, doc_len(source), "unclosed code block"));
+    }
+    if pending_input.is_some() {
+        return Err(invalid(origin, doc_len(source), "input without output"));
+    }
+    Ok(cases)
+}
+
+/// Replaces the sample blocks in the leading doc comment of `source` with `cases`.
+///
+/// Other lines of the doc comment are kept.
+pub fn embed_inline(source: &str, cases: &[Case]) -> String {
+    let doc_len = doc_len(source);
+    let mut doc: Vec<String> = Vec::new();
+    let mut in_block = false;
+    for line in source.lines().take(doc_len) {
+        let text = doc_text(line).unwrap_or_default().trim_end();
+        if in_block {
+            in_block = text != FENCE;
+            continue;
+        }
+        if matches!(
+            text.strip_prefix(FENCE).map(str::trim),
+            Some("input" | "output")
+        ) {
+            in_block = true;
+            continue;
+        }
+        doc.push(line.to_string());
+    }
+    while doc.last().is_some_and(|line| doc_text(line) == Some("")) {
+        doc.pop();
+    }
+
+    for case in cases {
+        if !doc.is_empty() {
+            doc.push(DOC_PREFIX.to_string());
+        }
+        for (kind, contents) in [("input", &case.input), ("output", &case.expect)] {
+            doc.push(format!("{DOC_PREFIX} {FENCE}{kind}"));
+            for line in contents.lines() {
+                if line.is_empty() {
+                    doc.push(DOC_PREFIX.to_string());
+                } else {
+                    doc.push(format!("{DOC_PREFIX} {line}"));
+                }
+            }
+            doc.push(format!("{DOC_PREFIX} {FENCE}"));
+        }
+    }
+
+    let rest: Vec<&str> = source.lines().skip(doc_len).collect();
+    let mut embedded = doc.join("\n");
+    if !embedded.is_empty() && !rest.is_empty() {
+        embedded.push('\n');
+    }
+    embedded.push_str(&rest.join("\n"));
+    if source.ends_with('\n') || source.is_empty() {
+        embedded.push('\n');
+    }
+    embedded
+}
+
+#[cfg(test)]
+mod inline_test {
+    use super::{embed_inline, parse_inline};
+    use crate::cases::Case;
+
+    const SOURCE: &str = "\
+//! Sum of a list.
+//!
+//! ```input
+//! 3
+//! 1 2 3
+//! ```
+//! ```output
+//! 6
+//! ```
+//!
+//! ```input
+//! 0
+//!
+//! ```
+//! ```output
+//! 0
+//! ```
+use proconio::*;
+
+fn main() {}
+";
+
+    #[test]
+    fn parse() {
+        let cases = parse_inline(SOURCE, "src/bin/a.rs").unwrap();
+        let cases: Vec<_> = cases
+            .iter()
+            .map(|c| {
+                (
+                    c.name.as_str(),
+                    c.origin.as_str(),
+                    c.input.as_str(),
+                    c.expect.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            cases,
+            vec![
+                ("inline-1", "src/bin/a.rs#inline-1", "3\n1 2 3\n", "6\n"),
+                ("inline-2", "src/bin/a.rs#inline-2", "0\n\n", "0\n"),
+            ]
+        );
+    }
+
+    #[test]
+    fn only_the_leading_comment() {
+        let source = "use proconio::*;\n//! ```input\n//! 1\n//! ```\n";
+        assert!(parse_inline(source, "a.rs").unwrap().is_empty());
+    }
+
+    #[test]
+    fn errors() {
+        let missing_output = "//! ```input\n//! 1\n//! ```\n";
+        assert_eq!(
+            parse_inline(missing_output, "a.rs")
+                .unwrap_err()
+                .to_string(),
+            "a.rs:3: input without output"
+        );
+        let unclosed = "//! ```input\n//! 1\nfn main() {}\n";
+        assert_eq!(
+            parse_inline(unclosed, "a.rs").unwrap_err().to_string(),
+            "a.rs:2: unclosed code block"
+        );
+    }
+
+    #[test]
+    fn embed_round_trip() {
+        let cases = parse_inline(SOURCE, "a.rs").unwrap();
+        assert_eq!(embed_inline(SOURCE, &cases), SOURCE);
+
+        let stripped = embed_inline(SOURCE, &[]);
+        assert_eq!(
+            stripped,
+            "//! Sum of a list.\nuse proconio::*;\n\nfn main() {}\n"
+        );
+        assert_eq!(embed_inline(&stripped, &cases), SOURCE);
+    }
+
+    #[test]
+    fn embed_without_doc_comment() {
+        let case = Case {
+            name: "1.txt".to_string(),
+            origin: String::new(),
+            expect_origin: String::new(),
+            input: "1\n".to_string(),
+            expect: "2\n".to_string(),
+        };
+        assert_eq!(
+            embed_inline("fn main() {}\n", &[case]),
+            "//! ```input\n//! 1\n//! ```\n//! ```output\n//! 2\n//! ```\nfn main() {}\n"
+        );
+    }
+}
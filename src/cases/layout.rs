@@ -0,0 +1,387 @@
+use super::{check_file_existance, natural_cmp, parse_cases_file, Case, CASES_FILE_NAME};
+
+use clap::ValueEnum;
+
+use std::error::Error;
+use std::ffi::OsStr;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+/// File in a testcase directory that selects its layout instead of detecting it.
+pub const LAYOUT_FILE_NAME: &str = "layout";
+
+/// How the cases of a testcase directory are stored.
+///
+/// To support another layout, add a variant and teach [`Layout::detect`] and [`Layout::scan`] about it.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layout {
+    /// `in/<name>` and `out/<name>`, either `.txt` files or files without an extension
+    /// as in AtCoder's official testcase archives.
+    InOut,
+    /// `<name>.in` and `<name>.out` side by side, as written by online-judge-tools.
+    Oj,
+    /// Named sections in `cases.txt`.
+    CasesFile,
+}
+
+/// A file that is not part of any case, and why.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Skipped {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+/// The cases found in a testcase directory and the files passed over.
+#[derive(Debug, Default)]
+pub struct Scan {
+    pub layouts: Vec<Layout>,
+    pub cases: Vec<Case>,
+    pub skipped: Vec<Skipped>,
+    /// Inputs without an expected output, with an empty `expect`; they are also in `skipped`.
+    pub unanswered: Vec<Case>,
+}
+
+impl Layout {
+    /// Every layout, in the order their cases are run.
+    pub const ALL: [Layout; 3] = [Layout::CasesFile, Layout::InOut, Layout::Oj];
+
+    /// Reads the layout of a testcase directory, if it has a `layout` file.
+    pub fn from_dir(dir: &Path) -> Result<Option<Self>, Box<dyn Error>> {
+        let path = dir.join(LAYOUT_FILE_NAME);
+        let name = match fs::read_to_string(&path) {
+            Ok(name) => name,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(Box::new(e)),
+        };
+        match Layout::from_str(name.trim(), true) {
+            Ok(layout) => Ok(Some(layout)),
+            Err(e) => Err(Box::new(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), e),
+            ))),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Layout::InOut => "in-out",
+            Layout::Oj => "oj",
+            Layout::CasesFile => "cases-file",
+        }
+    }
+
+    /// Whether `dir` looks like it uses this layout.
+    pub fn detect(self, dir: &Path) -> Result<bool, Box<dyn Error>> {
+        match self {
+            Layout::InOut => Ok(dir.join("in").is_dir()),
+            Layout::Oj => Ok(files(dir)?.iter().any(|path| has_extension(path, "in"))),
+            Layout::CasesFile => Ok(dir.join(CASES_FILE_NAME).try_exists()?),
+        }
+    }
+
+    /// Reads the cases stored in `dir` in this layout.
+    ///
+    /// Files are read in natural order; a cases file keeps its own order.
+    pub fn scan(self, dir: &Path) -> Result<Scan, Box<dyn Error>> {
+        let mut scan = Scan {
+            layouts: vec![self],
+            ..Scan::default()
+        };
+        match self {
+            Layout::InOut => {
+                scan_in_out(dir, &mut scan)?;
+                scan.cases.sort_by(|a, b| natural_cmp(&a.name, &b.name));
+                scan.unanswered
+                    .sort_by(|a, b| natural_cmp(&a.name, &b.name));
+            }
+            Layout::Oj => {
+                scan_oj(dir, &mut scan)?;
+                scan.cases.sort_by(|a, b| natural_cmp(&a.name, &b.name));
+                scan.unanswered
+                    .sort_by(|a, b| natural_cmp(&a.name, &b.name));
+            }
+            Layout::CasesFile => {
+                let path = dir.join(CASES_FILE_NAME);
+                check_file_existance(&path)?;
+                let text = fs::read_to_string(&path)?;
+                scan.cases = parse_cases_file(&text, &path.display().to_string())?;
+            }
+        }
+        Ok(scan)
+    }
+}
+
+impl Scan {
+    fn extend(&mut self, other: Scan) {
+        self.layouts.extend(other.layouts);
+        self.cases.extend(other.cases);
+        self.skipped.extend(other.skipped);
+        self.unanswered.extend(other.unanswered);
+    }
+}
+
+/// Reads `dir` in `layout`, or in every layout detected when it is `None`.
+pub fn scan_dir(dir: &Path, layout: Option<Layout>) -> Result<Scan, Box<dyn Error>> {
+    if let Some(layout) = layout {
+        return layout.scan(dir);
+    }
+    let mut scan = Scan::default();
+    for layout in Layout::ALL {
+        if layout.detect(dir)? {
+            scan.extend(layout.scan(dir)?);
+        }
+    }
+    if scan.layouts.is_empty() {
+        return Err(Box::new(io::Error::new(
+            ErrorKind::NotFound,
+            format!(
+                "No testcases in `{}`. Please create `in/` and `out/`, `*.in` and `*.out`, or `{CASES_FILE_NAME}`.",
+                dir.display()
+            ),
+        )));
+    }
+    Ok(scan)
+}
+
+/// Files directly in `dir`, sorted by name.
+fn files(dir: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut files = Vec::new();
+    for entry in dir.read_dir()? {
+        let path = entry?.path();
+        if path.is_file() {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension().and_then(OsStr::to_str) == Some(extension)
+}
+
+fn file_name(path: &Path) -> &str {
+    path.file_name().and_then(OsStr::to_str).unwrap_or_default()
+}
+
+fn skip(scan: &mut Scan, path: PathBuf, reason: impl Into<String>) {
+    scan.skipped.push(Skipped {
+        path,
+        reason: reason.into(),
+    });
+}
+
+fn read_input(name: &str, in_path: &Path) -> Result<Case, Box<dyn Error>> {
+    Ok(Case {
+        name: name.to_string(),
+        origin: in_path.display().to_string(),
+        expect_origin: String::new(),
+        input: fs::read_to_string(in_path)?,
+        expect: String::new(),
+    })
+}
+
+fn read_pair(name: &str, in_path: &Path, out_path: &Path) -> Result<Case, Box<dyn Error>> {
+    Ok(Case {
+        name: name.to_string(),
+        origin: in_path.display().to_string(),
+        expect_origin: out_path.display().to_string(),
+        input: fs::read_to_string(in_path)?,
+        expect: fs::read_to_string(out_path)?,
+    })
+}
+
+fn scan_in_out(dir: &Path, scan: &mut Scan) -> Result<(), Box<dyn Error>> {
+    let testcase_in = dir.join("in");
+    check_file_existance(&testcase_in)?;
+    let testcase_out = dir.join("out");
+    check_file_existance(&testcase_out)?;
+
+    for in_path in files(&testcase_in)? {
+        let name = file_name(&in_path).to_string();
+        let out_path = testcase_out.join(&name);
+        if name.starts_with('.') {
+            skip(scan, in_path, "hidden file");
+        } else if in_path.extension().is_some() && !has_extension(&in_path, "txt") {
+            skip(
+                scan,
+                in_path,
+                "not a .txt file or a file without an extension",
+            );
+        } else if !out_path.is_file() {
+            scan.unanswered.push(read_input(&name, &in_path)?);
+            let reason = format!("no expected output `{}`", out_path.display());
+            skip(scan, in_path, reason);
+        } else {
+            scan.cases.push(read_pair(&name, &in_path, &out_path)?);
+        }
+    }
+    for out_path in files(&testcase_out)? {
+        let name = file_name(&out_path);
+        if !name.starts_with('.') && !testcase_in.join(name).is_file() {
+            let reason = format!("no input `{}`", testcase_in.join(name).display());
+            skip(scan, out_path, reason);
+        }
+    }
+    Ok(())
+}
+
+fn scan_oj(dir: &Path, scan: &mut Scan) -> Result<(), Box<dyn Error>> {
+    for path in files(dir)? {
+        if has_extension(&path, "in") {
+            let out_path = path.with_extension("out");
+            let name = path.file_stem().and_then(OsStr::to_str).unwrap_or_default();
+            if out_path.is_file() {
+                scan.cases.push(read_pair(name, &path, &out_path)?);
+            } else {
+                scan.unanswered.push(read_input(name, &path)?);
+                let reason = format!("no expected output `{}`", out_path.display());
+                skip(scan, path, reason);
+            }
+        } else if has_extension(&path, "out") && !path.with_extension("in").is_file() {
+            let reason = format!("no input `{}`", path.with_extension("in").display());
+            skip(scan, path, reason);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod layout_test {
+    use super::{scan_dir, Layout, Scan};
+    use crate::project::project_test::TempDir;
+    use std::fs;
+    use std::path::Path;
+
+    fn write(dir: &Path, files: &[(&str, &str)]) {
+        for (name, contents) in files {
+            let path = dir.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+    }
+
+    fn names(scan: &Scan) -> Vec<&str> {
+        scan.cases.iter().map(|c| c.name.as_str()).collect()
+    }
+
+    fn skipped(scan: &Scan, dir: &Path) -> Vec<(String, String)> {
+        scan.skipped
+            .iter()
+            .map(|s| {
+                let path = s.path.strip_prefix(dir).unwrap().display().to_string();
+                (path, s.reason.replace(&dir.display().to_string(), "DIR"))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn in_out() {
+        let tmp = TempDir::new("layout-in-out");
+        write(
+            &tmp.0,
+            &[
+                ("in/1.txt", "1\n"),
+                ("out/1.txt", "2\n"),
+                ("in/10.txt", "10\n"),
+                ("out/10.txt", "20\n"),
+                ("in/9.txt", "9\n"),
+                ("out/9.txt", "18\n"),
+                ("in/sample_01", "3\n"),
+                ("out/sample_01", "6\n"),
+                ("in/2.txt", "4\n"),
+                ("out/3.txt", "8\n"),
+                ("in/notes.md", ""),
+                ("in/.DS_Store", ""),
+            ],
+        );
+        let scan = scan_dir(&tmp.0, None).unwrap();
+        assert_eq!(scan.layouts, vec![Layout::InOut]);
+        assert_eq!(names(&scan), vec!["1.txt", "9.txt", "10.txt", "sample_01"]);
+        assert_eq!(scan.cases[3].expect, "6\n");
+        assert_eq!(scan.unanswered.len(), 1);
+        assert_eq!(scan.unanswered[0].name, "2.txt");
+        assert_eq!(scan.unanswered[0].input, "4\n");
+        assert_eq!(
+            skipped(&scan, &tmp.0),
+            vec![
+                ("in/.DS_Store".to_string(), "hidden file".to_string()),
+                (
+                    "in/2.txt".to_string(),
+                    "no expected output `DIR/out/2.txt`".to_string()
+                ),
+                (
+                    "in/notes.md".to_string(),
+                    "not a .txt file or a file without an extension".to_string()
+                ),
+                (
+                    "out/3.txt".to_string(),
+                    "no input `DIR/in/3.txt`".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn oj() {
+        let tmp = TempDir::new("layout-oj");
+        write(
+            &tmp.0,
+            &[
+                ("sample-1.in", "1\n"),
+                ("sample-1.out", "2\n"),
+                ("sample-2.in", "3\n"),
+                ("compare", "tokens"),
+            ],
+        );
+        let scan = scan_dir(&tmp.0, None).unwrap();
+        assert_eq!(scan.layouts, vec![Layout::Oj]);
+        assert_eq!(names(&scan), vec!["sample-1"]);
+        assert_eq!(scan.unanswered[0].name, "sample-2");
+        assert_eq!(
+            skipped(&scan, &tmp.0),
+            vec![(
+                "sample-2.in".to_string(),
+                "no expected output `DIR/sample-2.out`".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn detects_every_layout() {
+        let tmp = TempDir::new("layout-all");
+        write(
+            &tmp.0,
+            &[
+                ("cases.txt", "=== input x\n1\n=== output x\n2\n"),
+                ("in/1.txt", "1\n"),
+                ("out/1.txt", "2\n"),
+                ("a.in", "1\n"),
+                ("a.out", "2\n"),
+            ],
+        );
+        let scan = scan_dir(&tmp.0, None).unwrap();
+        assert_eq!(scan.layouts, Layout::ALL.to_vec());
+        assert_eq!(names(&scan), vec!["x", "1.txt", "a"]);
+
+        let scan = scan_dir(&tmp.0, Some(Layout::Oj)).unwrap();
+        assert_eq!(names(&scan), vec!["a"]);
+    }
+
+    #[test]
+    fn layout_file() {
+        let tmp = TempDir::new("layout-file");
+        assert_eq!(Layout::from_dir(&tmp.0).unwrap(), None);
+        write(&tmp.0, &[("layout", "oj\n")]);
+        assert_eq!(Layout::from_dir(&tmp.0).unwrap(), Some(Layout::Oj));
+        write(&tmp.0, &[("layout", "zip\n")]);
+        assert!(Layout::from_dir(&tmp.0).is_err());
+    }
+
+    #[test]
+    fn nothing_found() {
+        let tmp = TempDir::new("layout-empty");
+        assert!(scan_dir(&tmp.0, None).is_err());
+    }
+}
@@ -0,0 +1,266 @@
+use super::{Case, Multi, Shape};
+use crate::checker::CompareMode;
+use crate::report::Verdict;
+use crate::runner::OUTPUT_LIMIT;
+
+use serde::Deserialize;
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::Path;
+use std::time::Duration;
+
+/// Optional file in a testcase directory with settings for its cases.
+pub const MANIFEST_FILE_NAME: &str = "cases.toml";
+
+/// Settings of a case; unset fields fall back to the directory defaults.
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct CaseOptions {
+    /// In seconds.
+    pub time_limit: Option<f64>,
+    /// In MiB.
+    pub output_limit: Option<f64>,
+    pub checker: Option<CompareMode>,
+    pub float_tolerance: Option<f64>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub skip: Option<bool>,
+    pub expect_verdict: Option<Verdict>,
+    /// Shape of each case of a multi-testcase input.
+    pub multi: Option<Shape>,
+    /// Shape of the output of each case, one line by default.
+    pub multi_output: Option<Shape>,
+}
+
+impl CaseOptions {
+    /// `self` with the fields set in `other` replaced, and the tags of both.
+    fn overlay(&self, other: &CaseOptions) -> CaseOptions {
+        let mut tags = self.tags.clone();
+        tags.extend(
+            other
+                .tags
+                .iter()
+                .filter(|t| !self.tags.contains(t))
+                .cloned(),
+        );
+        CaseOptions {
+            time_limit: other.time_limit.or(self.time_limit),
+            output_limit: other.output_limit.or(self.output_limit),
+            checker: other.checker.or(self.checker),
+            float_tolerance: other.float_tolerance.or(self.float_tolerance),
+            tags,
+            skip: other.skip.or(self.skip),
+            expect_verdict: other.expect_verdict.or(self.expect_verdict),
+            multi: other.multi.clone().or_else(|| self.multi.clone()),
+            multi_output: other
+                .multi_output
+                .clone()
+                .or_else(|| self.multi_output.clone()),
+        }
+    }
+
+    pub fn time_limit(&self) -> Option<Duration> {
+        self.time_limit.map(Duration::from_secs_f64)
+    }
+
+    /// In bytes; the given limit in MiB takes precedence, and the default is [`OUTPUT_LIMIT`].
+    pub fn output_limit(&self, mib: Option<f64>) -> usize {
+        mib.or(self.output_limit)
+            .map_or(OUTPUT_LIMIT, |mib| (mib * (1 << 20) as f64) as usize)
+    }
+
+    pub fn skip(&self) -> bool {
+        self.skip.unwrap_or(false)
+    }
+
+    /// How the input is split into cases, if it holds several; the given shapes take precedence.
+    pub fn multi(&self, input: Option<&Shape>, output: Option<&Shape>) -> Option<Multi> {
+        let input = input.or(self.multi.as_ref())?.clone();
+        let output = output.or(self.multi_output.as_ref()).cloned();
+        Some(Multi::new(input, output))
+    }
+}
+
+/// Contents of `cases.toml`: defaults at the top level and overrides under `[cases.<name>]`.
+///
+/// ```toml
+/// time-limit = 2.0
+/// checker = "tokens"
+///
+/// [cases.max]
+/// time-limit = 10.0
+/// expect-verdict = "TLE"
+/// tags = ["max-case"]
+/// ```
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Manifest {
+    time_limit: Option<f64>,
+    output_limit: Option<f64>,
+    checker: Option<CompareMode>,
+    float_tolerance: Option<f64>,
+    #[serde(default)]
+    tags: Vec<String>,
+    skip: Option<bool>,
+    expect_verdict: Option<Verdict>,
+    multi: Option<Shape>,
+    multi_output: Option<Shape>,
+    /// Overrides by case name, with or without its extension.
+    #[serde(default)]
+    cases: BTreeMap<String, CaseOptions>,
+}
+
+impl Manifest {
+    /// Reads the manifest of a testcase directory, if it has one.
+    pub fn from_dir(dir: &Path) -> Result<Option<Self>, Box<dyn Error>> {
+        let path = dir.join(MANIFEST_FILE_NAME);
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(Box::new(e)),
+        };
+        Self::parse(&text).map(Some).map_err(|e| {
+            Box::new(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), e),
+            )) as Box<dyn Error>
+        })
+    }
+
+    fn parse(text: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(text)
+    }
+
+    fn defaults(&self) -> CaseOptions {
+        CaseOptions {
+            time_limit: self.time_limit,
+            output_limit: self.output_limit,
+            checker: self.checker,
+            float_tolerance: self.float_tolerance,
+            tags: self.tags.clone(),
+            skip: self.skip,
+            expect_verdict: self.expect_verdict,
+            multi: self.multi.clone(),
+            multi_output: self.multi_output.clone(),
+        }
+    }
+
+    /// The settings of `case`.
+    pub fn options(&self, case: &Case) -> CaseOptions {
+        let defaults = self.defaults();
+        match self
+            .cases
+            .get(&case.name)
+            .or_else(|| self.cases.get(case.stem()))
+        {
+            Some(options) => defaults.overlay(options),
+            None => defaults,
+        }
+    }
+
+    /// Names under `[cases]` that match none of `cases`, most likely typos.
+    pub fn unknown_cases(&self, cases: &[Case]) -> Vec<&str> {
+        self.cases
+            .keys()
+            .filter(|key| !cases.iter().any(|c| c.name == **key || c.stem() == *key))
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod manifest_test {
+    use super::{CaseOptions, Manifest};
+    use crate::cases::{Case, Multi, Shape};
+    use crate::checker::CompareMode;
+    use crate::report::Verdict;
+    use std::time::Duration;
+
+    fn case(name: &str) -> Case {
+        Case {
+            name: name.to_string(),
+            origin: String::new(),
+            expect_origin: String::new(),
+            input: String::new(),
+            expect: String::new(),
+        }
+    }
+
+    const MANIFEST: &str = r#"
+time-limit = 2.0
+checker = "case-insensitive"
+tags = ["all"]
+
+[cases.max]
+time-limit = 10.5
+output-limit = 0.5
+expect-verdict = "TLE"
+tags = ["max-case"]
+
+[cases."float.txt"]
+float-tolerance = 1e-6
+
+[cases.broken]
+skip = true
+
+[cases.all]
+multi = "n; [n]"
+"#;
+
+    #[test]
+    fn overrides() {
+        let manifest = Manifest::parse(MANIFEST).unwrap();
+        let defaults = manifest.options(&case("1.txt"));
+        assert_eq!(
+            defaults,
+            CaseOptions {
+                time_limit: Some(2.0),
+                checker: Some(CompareMode::CaseInsensitive),
+                tags: vec!["all".to_string()],
+                ..CaseOptions::default()
+            }
+        );
+
+        let max = manifest.options(&case("max.txt"));
+        assert_eq!(max.time_limit(), Some(Duration::from_millis(10500)));
+        assert_eq!(max.output_limit(None), 512 << 10);
+        assert_eq!(max.output_limit(Some(2.0)), 2 << 20);
+        assert_eq!(defaults.output_limit(None), 64 << 20);
+        assert_eq!(max.expect_verdict, Some(Verdict::TimeLimitExceeded));
+        assert_eq!(max.tags, vec!["all", "max-case"]);
+        assert_eq!(max.checker, Some(CompareMode::CaseInsensitive));
+
+        let float = manifest.options(&case("float.txt"));
+        assert_eq!(float.float_tolerance, Some(1e-6));
+        assert!(!float.skip());
+        assert!(manifest.options(&case("broken")).skip());
+        assert_eq!(float.multi(None, None), None);
+        let all = manifest.options(&case("all.txt"));
+        let shape: Shape = "n m; [m]".parse().unwrap();
+        assert_eq!(
+            all.multi(None, None),
+            Some(Multi::new("n; [n]".parse().unwrap(), None))
+        );
+        assert_eq!(
+            all.multi(Some(&shape), Some(&shape)),
+            Some(Multi::new(shape.clone(), Some(shape)))
+        );
+
+        assert_eq!(
+            manifest.unknown_cases(&[case("max.txt"), case("float.txt")]),
+            vec!["all", "broken"]
+        );
+    }
+
+    #[test]
+    fn unknown_keys() {
+        assert!(Manifest::parse("time-limt = 2.0\n").is_err());
+        assert!(Manifest::parse("[cases.a]\nskipped = true\n").is_err());
+        assert!(Manifest::parse("checker = \"fuzzy\"\n").is_err());
+        assert!(Manifest::parse("expect-verdict = \"MLE\"\n").is_err());
+        assert!(Manifest::parse("multi = \"n; [n\"\n").is_err());
+    }
+}
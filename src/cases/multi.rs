@@ -0,0 +1,472 @@
+use super::Case;
+
+use serde::Deserialize;
+
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+use std::iter::Peekable;
+use std::ops::Range;
+use std::str::FromStr;
+
+/// The lines of one case of a multi-testcase input.
+///
+/// A shape is a `;`-separated list of line groups, read in order:
+///
+/// - names such as `n m`: one line whose tokens are bound to the names, `_` for a token that is not needed
+/// - `[expr]`: `expr` lines, where `expr` is made of integers, bound names, `+`, `-`, `*` and parentheses
+///
+/// `n m; [m]` is a case with `n` and `m` on its first line, followed by `m` lines.
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(try_from = "String")]
+pub struct Shape {
+    source: String,
+    groups: Vec<Group>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Group {
+    Bind(Vec<String>),
+    Lines(Expr),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Expr {
+    Num(i64),
+    Var(String),
+    Op(Box<Expr>, char, Box<Expr>),
+}
+
+impl FromStr for Shape {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let groups = s
+            .split(';')
+            .map(str::trim)
+            .filter(|group| !group.is_empty())
+            .map(parse_group)
+            .collect::<Result<Vec<_>, _>>()?;
+        if groups.is_empty() {
+            return Err("empty shape".to_string());
+        }
+        Ok(Shape {
+            source: s.trim().to_string(),
+            groups,
+        })
+    }
+}
+
+impl TryFrom<String> for Shape {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+fn is_name(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn parse_group(group: &str) -> Result<Group, String> {
+    if let Some(expr) = group.strip_prefix('[') {
+        let expr = expr
+            .strip_suffix(']')
+            .ok_or_else(|| format!("`{group}`: missing `]`"))?;
+        let tokens = tokenize(expr).map_err(|e| format!("`{group}`: {e}"))?;
+        let mut tokens = tokens.iter().map(String::as_str).peekable();
+        let parsed = parse_sum(&mut tokens).map_err(|e| format!("`{group}`: {e}"))?;
+        if let Some(token) = tokens.next() {
+            return Err(format!("`{group}`: unexpected `{token}`"));
+        }
+        return Ok(Group::Lines(parsed));
+    }
+    let names: Vec<String> = group.split_whitespace().map(str::to_string).collect();
+    match names.iter().find(|name| !is_name(name)) {
+        Some(name) => Err(format!(
+            "`{name}` is not a name; write a number of lines as `[{name}]`"
+        )),
+        None => Ok(Group::Bind(names)),
+    }
+}
+
+fn tokenize(expr: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = expr.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if "+-*()".contains(c) {
+            tokens.push(c.to_string());
+            chars.next();
+        } else if c.is_ascii_alphanumeric() || c == '_' {
+            let mut token = String::new();
+            while let Some(&c) = chars
+                .peek()
+                .filter(|c| c.is_ascii_alphanumeric() || **c == '_')
+            {
+                token.push(c);
+                chars.next();
+            }
+            tokens.push(token);
+        } else {
+            return Err(format!("unexpected `{c}`"));
+        }
+    }
+    Ok(tokens)
+}
+
+fn parse_sum<'a, I: Iterator<Item = &'a str>>(tokens: &mut Peekable<I>) -> Result<Expr, String> {
+    let mut lhs = parse_product(tokens)?;
+    while let Some(op @ ("+" | "-")) = tokens.peek().copied() {
+        tokens.next();
+        let rhs = parse_product(tokens)?;
+        lhs = Expr::Op(Box::new(lhs), op.chars().next().unwrap(), Box::new(rhs));
+    }
+    Ok(lhs)
+}
+
+fn parse_product<'a, I: Iterator<Item = &'a str>>(
+    tokens: &mut Peekable<I>,
+) -> Result<Expr, String> {
+    let mut lhs = parse_atom(tokens)?;
+    while tokens.peek() == Some(&"*") {
+        tokens.next();
+        let rhs = parse_atom(tokens)?;
+        lhs = Expr::Op(Box::new(lhs), '*', Box::new(rhs));
+    }
+    Ok(lhs)
+}
+
+fn parse_atom<'a, I: Iterator<Item = &'a str>>(tokens: &mut Peekable<I>) -> Result<Expr, String> {
+    match tokens.next() {
+        Some("(") => {
+            let expr = parse_sum(tokens)?;
+            match tokens.next() {
+                Some(")") => Ok(expr),
+                _ => Err("missing `)`".to_string()),
+            }
+        }
+        Some(token) if token.starts_with(|c: char| c.is_ascii_digit()) => token
+            .parse()
+            .map(Expr::Num)
+            .map_err(|_| format!("`{token}` is not a number")),
+        Some(token) if is_name(token) => Ok(Expr::Var(token.to_string())),
+        Some(token) => Err(format!("unexpected `{token}`")),
+        None => Err("missing a number or a name".to_string()),
+    }
+}
+
+impl Expr {
+    fn eval(&self, env: &HashMap<String, i64>) -> Result<i64, String> {
+        match self {
+            Expr::Num(n) => Ok(*n),
+            Expr::Var(name) => env
+                .get(name)
+                .copied()
+                .ok_or_else(|| format!("`{name}` is not bound")),
+            Expr::Op(lhs, op, rhs) => {
+                let (lhs, rhs) = (lhs.eval(env)?, rhs.eval(env)?);
+                let value = match op {
+                    '+' => lhs.checked_add(rhs),
+                    '-' => lhs.checked_sub(rhs),
+                    _ => lhs.checked_mul(rhs),
+                };
+                value.ok_or_else(|| "overflow".to_string())
+            }
+        }
+    }
+}
+
+impl Display for Shape {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl Display for Group {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Group::Bind(names) => write!(f, "{}", names.join(" ")),
+            Group::Lines(expr) => write!(f, "[{expr}]"),
+        }
+    }
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Num(n) => write!(f, "{n}"),
+            Expr::Var(name) => write!(f, "{name}"),
+            Expr::Op(lhs, op, rhs) => write!(f, "({lhs} {op} {rhs})"),
+        }
+    }
+}
+
+impl Shape {
+    /// Reads one case from `lines[start..]`, binding names in `env`, and returns the line after it.
+    fn read(
+        &self,
+        lines: &[&str],
+        start: usize,
+        env: &mut HashMap<String, i64>,
+    ) -> Result<usize, String> {
+        let mut line = start;
+        for group in &self.groups {
+            match group {
+                Group::Bind(names) => {
+                    let text = lines
+                        .get(line)
+                        .ok_or_else(|| format!("ends before `{group}`"))?;
+                    let tokens: Vec<&str> = text.split_whitespace().collect();
+                    for (i, name) in names.iter().enumerate() {
+                        let token = tokens
+                            .get(i)
+                            .ok_or_else(|| format!("line {}: no token for `{name}`", line + 1))?;
+                        if name == "_" {
+                            continue;
+                        }
+                        let value = token.parse().map_err(|_| {
+                            format!(
+                                "line {}: `{token}` for `{name}` is not an integer",
+                                line + 1
+                            )
+                        })?;
+                        env.insert(name.clone(), value);
+                    }
+                    line += 1;
+                }
+                Group::Lines(expr) => {
+                    let count = expr.eval(env)?;
+                    let count =
+                        usize::try_from(count).map_err(|_| format!("{group} is {count} lines"))?;
+                    if line + count > lines.len() {
+                        return Err(format!(
+                            "line {}: ends before the {count} lines of {group}",
+                            lines.len()
+                        ));
+                    }
+                    line += count;
+                }
+            }
+        }
+        Ok(line)
+    }
+}
+
+/// How an input holding several cases is split, with the shape of the output of each case.
+///
+/// The first line of the input is the number of cases.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Multi {
+    pub input: Shape,
+    pub output: Shape,
+}
+
+/// One case of a multi-testcase input, as 0-based line ranges.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SubCase {
+    pub input: Range<usize>,
+    pub output: Range<usize>,
+}
+
+/// Lines of `text`, without the blank lines at its end.
+fn lines(text: &str) -> Vec<&str> {
+    let mut lines: Vec<&str> = text.lines().collect();
+    while lines.last().is_some_and(|line| line.trim().is_empty()) {
+        lines.pop();
+    }
+    lines
+}
+
+impl Multi {
+    /// An output shape of one line per case, the most common one.
+    pub fn new(input: Shape, output: Option<Shape>) -> Self {
+        let output = output.unwrap_or_else(|| "[1]".parse().expect("a valid shape"));
+        Multi { input, output }
+    }
+
+    /// Splits an input and its expected output into cases.
+    pub fn split(&self, input: &str, output: &str) -> Result<Vec<SubCase>, String> {
+        let (input, output) = (lines(input), lines(output));
+        let count: usize = input
+            .first()
+            .and_then(|line| line.split_whitespace().next())
+            .and_then(|token| token.parse().ok())
+            .ok_or("line 1: expected the number of cases")?;
+        let (mut in_line, mut out_line) = (1, 0);
+        let mut cases = Vec::with_capacity(count);
+        for i in 0..count {
+            let mut env = HashMap::new();
+            let in_end = self
+                .input
+                .read(&input, in_line, &mut env)
+                .map_err(|e| format!("input of case {} of {count}: {e}", i + 1))?;
+            let out_end = self
+                .output
+                .read(&output, out_line, &mut env)
+                .map_err(|e| format!("output of case {} of {count}: {e}", i + 1))?;
+            cases.push(SubCase {
+                input: in_line..in_end,
+                output: out_line..out_end,
+            });
+            (in_line, out_line) = (in_end, out_end);
+        }
+        if in_line < input.len() {
+            return Err(format!(
+                "{} input lines after the last of {count} cases",
+                input.len() - in_line
+            ));
+        }
+        if out_line < output.len() {
+            return Err(format!(
+                "{} output lines after the last of {count} cases",
+                output.len() - out_line
+            ));
+        }
+        Ok(cases)
+    }
+
+    /// The 0-based index of the case that the 1-based `output_line` belongs to.
+    pub fn locate(cases: &[SubCase], output_line: usize) -> Option<usize> {
+        cases
+            .iter()
+            .position(|case| case.output.contains(&(output_line - 1)))
+    }
+
+    /// The case at 0-based `index` of `case`, as a case of its own with `T = 1`.
+    pub fn extract(&self, case: &Case, index: usize, name: &str) -> Result<Case, String> {
+        let cases = self.split(&case.input, &case.expect)?;
+        let sub_case = cases.get(index).ok_or_else(|| {
+            format!(
+                "`{}` has {} cases, not {}",
+                case.name,
+                cases.len(),
+                index + 1
+            )
+        })?;
+        let join = |text: &str, range: &Range<usize>| {
+            let mut joined: String = lines(text)[range.clone()]
+                .iter()
+                .flat_map(|line| [*line, "\n"])
+                .collect();
+            if joined.is_empty() {
+                joined.push('\n');
+            }
+            joined
+        };
+        Ok(Case {
+            name: name.to_string(),
+            origin: format!("{}#{}", case.origin, index + 1),
+            expect_origin: format!("{}#{}", case.expect_origin, index + 1),
+            input: format!("1\n{}", join(&case.input, &sub_case.input)),
+            expect: join(&case.expect, &sub_case.output),
+        })
+    }
+}
+
+#[cfg(test)]
+mod multi_test {
+    use super::{Multi, Shape, SubCase};
+    use crate::cases::Case;
+
+    const INPUT: &str = "3\n2 1\n1 2\n3 1\n1 3\n4 2\n1 2\n3 4\n";
+    const OUTPUT: &str = "Yes\nNo\nYes\n";
+
+    fn multi(input: &str, output: &str) -> Multi {
+        Multi::new(input.parse().unwrap(), output.parse().ok())
+    }
+
+    #[test]
+    fn shapes() {
+        let shape: Shape = "n m; [m]; [ 2*(n - 1) + 1 ]".parse().unwrap();
+        assert_eq!(shape.to_string(), "n m; [m]; [ 2*(n - 1) + 1 ]");
+        assert_eq!(shape.groups.len(), 3);
+        assert_eq!(shape.groups[2].to_string(), "[((2 * (n - 1)) + 1)]");
+        assert!("".parse::<Shape>().is_err());
+        assert!("n; m".parse::<Shape>().is_ok());
+        assert!("n; 2".parse::<Shape>().is_err());
+        assert!("n; [n".parse::<Shape>().is_err());
+        assert!("n; [n +]".parse::<Shape>().is_err());
+        assert!("n; [n m]".parse::<Shape>().is_err());
+    }
+
+    #[test]
+    fn split() {
+        let cases = multi("n m; [m]", "").split(INPUT, OUTPUT).unwrap();
+        assert_eq!(
+            cases,
+            vec![
+                SubCase {
+                    input: 1..3,
+                    output: 0..1
+                },
+                SubCase {
+                    input: 3..5,
+                    output: 1..2
+                },
+                SubCase {
+                    input: 5..8,
+                    output: 2..3
+                },
+            ]
+        );
+        assert_eq!(Multi::locate(&cases, 2), Some(1));
+        assert_eq!(Multi::locate(&cases, 4), None);
+
+        let by_answer = multi("_ m; [m]", "k; [k]");
+        assert_eq!(
+            by_answer
+                .split("2\n1 1\nx\n1 2\ny\nz\n", "2\na\nb\n0\n")
+                .unwrap()[1]
+                .output,
+            3..4
+        );
+    }
+
+    #[test]
+    fn split_errors() {
+        let shape = multi("n m; [m]", "");
+        assert_eq!(
+            shape.split("x\n", "").unwrap_err(),
+            "line 1: expected the number of cases"
+        );
+        assert_eq!(
+            shape.split("2\n1 1\n1 2\n1 5\n1 2\n", OUTPUT).unwrap_err(),
+            "input of case 2 of 2: line 5: ends before the 5 lines of [m]"
+        );
+        assert_eq!(
+            shape.split(INPUT, "Yes\nNo\n").unwrap_err(),
+            "output of case 3 of 3: line 2: ends before the 1 lines of [1]"
+        );
+        assert_eq!(
+            shape.split("1\n1 1\n1 2\n1 1\n", "Yes\n").unwrap_err(),
+            "1 input lines after the last of 1 cases"
+        );
+        assert_eq!(
+            multi("n; [n]", "").split("1\nx\n", "Yes\n").unwrap_err(),
+            "input of case 1 of 1: line 2: `x` for `n` is not an integer"
+        );
+    }
+
+    #[test]
+    fn extract() {
+        let case = Case {
+            name: "1.txt".to_string(),
+            origin: "in/1.txt".to_string(),
+            expect_origin: "out/1.txt".to_string(),
+            input: INPUT.to_string(),
+            expect: OUTPUT.to_string(),
+        };
+        let multi = multi("n m; [m]", "");
+        let third = multi.extract(&case, 2, "1-3.txt").unwrap();
+        assert_eq!(third.input, "1\n4 2\n1 2\n3 4\n");
+        assert_eq!(third.expect, "Yes\n");
+        assert_eq!(third.origin, "in/1.txt#3");
+        assert_eq!(
+            multi.extract(&case, 3, "1-4.txt").unwrap_err(),
+            "`1.txt` has 3 cases, not 4"
+        );
+    }
+}
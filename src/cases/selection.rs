@@ -0,0 +1,217 @@
+use super::Case;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Prefix that makes a `--filter` or `--exclude` pattern a regular expression instead of a glob.
+const REGEX_PREFIX: &str = "re:";
+
+/// A glob such as `sample-*`, or a regular expression such as `re:^\d+$`, matched against case names.
+#[derive(Clone, Debug)]
+pub enum Pattern {
+    Glob(glob::Pattern),
+    Regex(Regex),
+}
+
+impl FromStr for Pattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix(REGEX_PREFIX) {
+            Some(regex) => Regex::new(regex)
+                .map(Pattern::Regex)
+                .map_err(|e| e.to_string()),
+            None => glob::Pattern::new(s)
+                .map(Pattern::Glob)
+                .map_err(|e| e.to_string()),
+        }
+    }
+}
+
+impl Pattern {
+    /// Whether the name of `case`, with or without its extension, matches.
+    pub fn matches(&self, case: &Case) -> bool {
+        [case.name.as_str(), case.stem()]
+            .iter()
+            .any(|name| match self {
+                Pattern::Glob(glob) => glob.matches(name),
+                Pattern::Regex(regex) => regex.is_match(name),
+            })
+    }
+}
+
+/// Whether `case` matches one of `filter`, or `filter` is empty, and none of `exclude`.
+pub fn is_selected(case: &Case, filter: &[Pattern], exclude: &[Pattern]) -> bool {
+    (filter.is_empty() || filter.iter().any(|p| p.matches(case)))
+        && !exclude.iter().any(|p| p.matches(case))
+}
+
+/// Orders names so that the numbers in them compare by value: `2.txt` comes before `10.txt`.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a_chunks, mut b_chunks) = (chunks(a), chunks(b));
+    loop {
+        match (a_chunks.next(), b_chunks.next()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) => {
+                let is_number = |s: &str| s.starts_with(|c: char| c.is_ascii_digit());
+                let ordering = if is_number(x) && is_number(y) {
+                    let (x, y) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+                    x.len().cmp(&y.len()).then_with(|| x.cmp(y))
+                } else {
+                    x.cmp(y)
+                };
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+        }
+    }
+}
+
+/// Splits `s` into runs of ASCII digits and runs of anything else.
+fn chunks(s: &str) -> impl Iterator<Item = &str> {
+    let mut rest = s;
+    std::iter::from_fn(move || {
+        let first = rest.chars().next()?;
+        let digit = first.is_ascii_digit();
+        let end = rest
+            .find(|c: char| c.is_ascii_digit() != digit)
+            .unwrap_or(rest.len());
+        let (chunk, tail) = rest.split_at(end);
+        rest = tail;
+        Some(chunk)
+    })
+}
+
+/// Names of the cases that failed in earlier runs of a task, kept under `target/` of the project.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+pub struct LastFailed {
+    pub failed: BTreeSet<String>,
+}
+
+impl LastFailed {
+    pub fn path(project_root: &Path, task: &str) -> PathBuf {
+        project_root
+            .join("target/atcoder-rs/last-failed")
+            .join(format!("{task}.json"))
+    }
+
+    /// Reads the cache, or `None` when the task has not been run yet.
+    pub fn load(path: &Path) -> Result<Option<Self>, Box<dyn Error>> {
+        match fs::read_to_string(path) {
+            Ok(json) => Ok(Some(serde_json::from_str(&json)?)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(Box::new(e)),
+        }
+    }
+
+    /// Records the outcome of the cases that ran; the others keep their previous state.
+    pub fn update<'a>(&mut self, results: impl IntoIterator<Item = (&'a str, bool)>) {
+        for (name, accepted) in results {
+            if accepted {
+                self.failed.remove(name);
+            } else {
+                self.failed.insert(name.to_string());
+            }
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod selection_test {
+    use super::{is_selected, natural_cmp, LastFailed, Pattern};
+    use crate::cases::Case;
+    use crate::project::project_test::TempDir;
+
+    fn case(name: &str) -> Case {
+        Case {
+            name: name.to_string(),
+            origin: String::new(),
+            expect_origin: String::new(),
+            input: String::new(),
+            expect: String::new(),
+        }
+    }
+
+    #[test]
+    fn natural_order() {
+        let mut names = vec![
+            "10.txt",
+            "2.txt",
+            "1.txt",
+            "sample-10",
+            "sample-2",
+            "a",
+            "02.txt",
+        ];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(
+            names,
+            vec![
+                "1.txt",
+                "02.txt",
+                "2.txt",
+                "10.txt",
+                "a",
+                "sample-2",
+                "sample-10"
+            ]
+        );
+    }
+
+    #[test]
+    fn patterns() {
+        let glob: Pattern = "sample-*".parse().unwrap();
+        assert!(glob.matches(&case("sample-1")));
+        assert!(!glob.matches(&case("1.txt")));
+        let stem: Pattern = "1?".parse().unwrap();
+        assert!(stem.matches(&case("12.txt")));
+        let regex: Pattern = r"re:^\d+$".parse().unwrap();
+        assert!(regex.matches(&case("12.txt")));
+        assert!(!regex.matches(&case("sample-1")));
+        assert!("re:(".parse::<Pattern>().is_err());
+
+        let (filter, exclude) = (vec![glob], vec!["*-2".parse().unwrap()]);
+        assert!(is_selected(&case("sample-1"), &filter, &exclude));
+        assert!(!is_selected(&case("sample-2"), &filter, &exclude));
+        assert!(!is_selected(&case("1.txt"), &filter, &exclude));
+        assert!(is_selected(&case("1.txt"), &[], &exclude));
+    }
+
+    #[test]
+    fn last_failed() {
+        let tmp = TempDir::new("last-failed");
+        let path = LastFailed::path(&tmp.0, "a");
+        assert_eq!(LastFailed::load(&path).unwrap(), None);
+
+        let mut last = LastFailed::default();
+        last.update([("1.txt", true), ("2.txt", false), ("3.txt", false)]);
+        last.save(&path).unwrap();
+        let mut last = LastFailed::load(&path).unwrap().unwrap();
+        assert_eq!(
+            last.failed.iter().collect::<Vec<_>>(),
+            vec!["2.txt", "3.txt"]
+        );
+
+        last.update([("2.txt", true)]);
+        assert_eq!(last.failed.iter().collect::<Vec<_>>(), vec!["3.txt"]);
+    }
+}
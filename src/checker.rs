@@ -0,0 +1,425 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+use unicode_segmentation::UnicodeSegmentation;
+
+use std::error::Error;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::Path;
+
+/// File in a testcase directory that selects its comparison mode.
+pub const COMPARE_FILE_NAME: &str = "compare";
+
+/// How the program output is compared with the expected output.
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum CompareMode {
+    /// Byte-for-byte, including whitespace and line endings.
+    Exact,
+    /// Line by line, ignoring leading and trailing whitespace, CRLF and trailing blank lines.
+    #[default]
+    Lines,
+    /// Whitespace separated tokens, regardless of how they are split into lines.
+    Tokens,
+    /// Like `lines`, ignoring letter case.
+    CaseInsensitive,
+}
+
+/// 1-based line and 0-based grapheme column in an output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Where the program output and the expected output first differ.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Difference {
+    pub actual: Position,
+    pub expect: Position,
+}
+
+impl CompareMode {
+    /// Reads the mode of a testcase directory, if it has a `compare` file.
+    pub fn from_dir(dir: &Path) -> Result<Option<Self>, Box<dyn Error>> {
+        let path = dir.join(COMPARE_FILE_NAME);
+        let name = match fs::read_to_string(&path) {
+            Ok(name) => name,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(Box::new(e)),
+        };
+        match CompareMode::from_str(name.trim(), true) {
+            Ok(mode) => Ok(Some(mode)),
+            Err(e) => Err(Box::new(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), e),
+            ))),
+        }
+    }
+
+    /// Compares whole outputs and returns the first difference, taking numbers within `tolerance`
+    /// of each other as equal.
+    fn compare_within(
+        self,
+        actual: &str,
+        expect: &str,
+        tolerance: Option<f64>,
+    ) -> Option<Difference> {
+        match self {
+            CompareMode::Exact => compare_lines(actual, expect, true, false, tolerance),
+            CompareMode::Lines => compare_lines(actual, expect, false, false, tolerance),
+            CompareMode::CaseInsensitive => compare_lines(actual, expect, false, true, tolerance),
+            CompareMode::Tokens => compare_tokens(actual, expect, tolerance),
+        }
+    }
+
+    /// Whether two lines are the same under this mode, used to align lines in diffs.
+    pub fn lines_equal(self, a: &str, b: &str) -> bool {
+        match self {
+            CompareMode::Exact => a == b,
+            CompareMode::Lines => a.trim() == b.trim(),
+            CompareMode::CaseInsensitive => a.trim().to_lowercase() == b.trim().to_lowercase(),
+            CompareMode::Tokens => a.split_whitespace().eq(b.split_whitespace()),
+        }
+    }
+}
+
+/// A comparison mode, optionally accepting numbers within a tolerance.
+///
+/// The tolerance applies within the mode: under `lines` a line must still have the same tokens
+/// and spacing, only its numbers may differ a little.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Checker {
+    pub mode: CompareMode,
+    /// Maximum absolute or relative error of numeric tokens.
+    pub float_tolerance: Option<f64>,
+}
+
+impl From<CompareMode> for Checker {
+    fn from(mode: CompareMode) -> Self {
+        Self {
+            mode,
+            float_tolerance: None,
+        }
+    }
+}
+
+impl Checker {
+    /// A checker with the chosen `mode`, if any; with only a tolerance it compares tokens, as
+    /// numbers are rarely laid out the same way.
+    pub fn new(mode: Option<CompareMode>, float_tolerance: Option<f64>) -> Self {
+        let default = match float_tolerance {
+            Some(_) => CompareMode::Tokens,
+            None => CompareMode::default(),
+        };
+        Self {
+            mode: mode.unwrap_or(default),
+            float_tolerance,
+        }
+    }
+
+    pub fn compare(&self, actual: &str, expect: &str) -> Option<Difference> {
+        self.mode
+            .compare_within(actual, expect, self.float_tolerance)
+    }
+}
+
+/// Whether `actual` is within `tolerance` of `expect`, in absolute or relative error.
+fn within_tolerance(actual: &str, expect: &str, tolerance: f64) -> bool {
+    match (actual.parse::<f64>(), expect.parse::<f64>()) {
+        (Ok(a), Ok(e)) if a.is_finite() && e.is_finite() => {
+            let error = (a - e).abs();
+            error <= tolerance || error <= tolerance * e.abs()
+        }
+        _ => false,
+    }
+}
+
+/// Whether two lines differ only in numbers within `tolerance`, with the same tokens and spacing
+/// otherwise.
+fn lines_within_tolerance(actual: &str, expect: &str, tolerance: f64) -> bool {
+    let (actual, expect) = (pieces(actual), pieces(expect));
+    actual.len() == expect.len()
+        && actual.iter().zip(&expect).all(|(a, e)| {
+            a == e || !a.starts_with(char::is_whitespace) && within_tolerance(a, e, tolerance)
+        })
+}
+
+/// Splits `line` into alternating runs of whitespace and of other characters.
+fn pieces(line: &str) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    let mut blank = None;
+    for (offset, c) in line.char_indices() {
+        let is_blank = c.is_whitespace();
+        if blank.is_some_and(|b| b != is_blank) {
+            pieces.push(&line[start..offset]);
+            start = offset;
+        }
+        blank = Some(is_blank);
+    }
+    if start < line.len() {
+        pieces.push(&line[start..]);
+    }
+    pieces
+}
+
+/// Index of the first grapheme cluster at which `lhs` and `rhs` differ.
+pub fn diff_position(lhs: &str, rhs: &str) -> Option<usize> {
+    let mut g1 = lhs.graphemes(true);
+    let mut g2 = rhs.graphemes(true);
+    let mut pos = 0;
+    loop {
+        match (g1.next(), g2.next()) {
+            (None, None) => return None,
+            (c1, c2) if c1 != c2 => return Some(pos),
+            _ => pos += 1,
+        }
+    }
+}
+
+/// Compares line by line.
+///
+/// Unless `exact`, lines are trimmed and missing lines are treated as empty ones,
+/// so that CRLF and trailing blank lines do not matter.
+fn compare_lines(
+    actual: &str,
+    expect: &str,
+    exact: bool,
+    fold_case: bool,
+    tolerance: Option<f64>,
+) -> Option<Difference> {
+    let normalize = |line: &str| {
+        let line = if exact { line } else { line.trim() };
+        if fold_case {
+            line.to_lowercase()
+        } else {
+            line.to_string()
+        }
+    };
+    let (actual_lines, expect_lines) = (split_lines(actual, exact), split_lines(expect, exact));
+    for i in 0..actual_lines.len().max(expect_lines.len()) {
+        let a = actual_lines.get(i).copied().unwrap_or("");
+        let e = expect_lines.get(i).copied().unwrap_or("");
+        let (normal_a, normal_e) = (normalize(a), normalize(e));
+        if let Some(pos) = diff_position(&normal_a, &normal_e) {
+            if tolerance.is_some_and(|t| lines_within_tolerance(&normal_a, &normal_e, t)) {
+                continue;
+            }
+            // The column counts the skipped leading whitespace.
+            let column = |line: &str| {
+                let skipped = if exact { 0 } else { indent(line) };
+                line[..skipped].graphemes(true).count() + pos
+            };
+            return Some(Difference {
+                actual: Position {
+                    line: i + 1,
+                    column: column(a),
+                },
+                expect: Position {
+                    line: i + 1,
+                    column: column(e),
+                },
+            });
+        }
+    }
+    None
+}
+
+/// Splits `s` into lines; `exact` keeps the line terminators.
+fn split_lines(s: &str, exact: bool) -> Vec<&str> {
+    if exact {
+        s.split_inclusive('\n').collect()
+    } else {
+        s.lines().collect()
+    }
+}
+
+/// Byte length of the leading whitespace of `line`.
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+struct Token<'a> {
+    text: &'a str,
+    position: Position,
+    /// Number of graphemes in `text`.
+    len: usize,
+}
+
+fn tokenize(s: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    for (i, line) in s.lines().enumerate() {
+        let mut start: Option<(usize, usize)> = None;
+        let mut column = 0;
+        for (offset, g) in line.grapheme_indices(true) {
+            let blank = g.chars().all(char::is_whitespace);
+            match (blank, start) {
+                (false, None) => start = Some((offset, column)),
+                (true, Some((begin, begin_column))) => {
+                    tokens.push(Token {
+                        text: &line[begin..offset],
+                        position: Position {
+                            line: i + 1,
+                            column: begin_column,
+                        },
+                        len: column - begin_column,
+                    });
+                    start = None;
+                }
+                _ => {}
+            }
+            column += 1;
+        }
+        if let Some((begin, begin_column)) = start {
+            tokens.push(Token {
+                text: &line[begin..],
+                position: Position {
+                    line: i + 1,
+                    column: begin_column,
+                },
+                len: column - begin_column,
+            });
+        }
+    }
+    tokens
+}
+
+fn compare_tokens(actual: &str, expect: &str, tolerance: Option<f64>) -> Option<Difference> {
+    let actual_tokens = tokenize(actual);
+    let expect_tokens = tokenize(expect);
+    // Where a missing token would be: right after the last one.
+    let end = |tokens: &[Token]| {
+        tokens
+            .last()
+            .map_or(Position { line: 1, column: 0 }, |t| Position {
+                line: t.position.line,
+                column: t.position.column + t.len,
+            })
+    };
+    for i in 0..actual_tokens.len().max(expect_tokens.len()) {
+        let (a, e) = (actual_tokens.get(i), expect_tokens.get(i));
+        if a.map(|t| t.text) == e.map(|t| t.text) {
+            continue;
+        }
+        if let (Some(a), Some(e), Some(tolerance)) = (a, e, tolerance) {
+            if within_tolerance(a.text, e.text, tolerance) {
+                continue;
+            }
+        }
+        let offset = match (a, e) {
+            (Some(a), Some(e)) => diff_position(a.text, e.text).unwrap_or(0),
+            _ => 0,
+        };
+        let position = |t: Option<&Token>, tokens: &[Token]| {
+            t.map_or(end(tokens), |t| Position {
+                line: t.position.line,
+                column: t.position.column + offset,
+            })
+        };
+        return Some(Difference {
+            actual: position(a, &actual_tokens),
+            expect: position(e, &expect_tokens),
+        });
+    }
+    None
+}
+
+#[cfg(test)]
+mod checker_test {
+    use super::{diff_position, Checker, CompareMode, Difference, Position};
+
+    fn at(actual: (usize, usize), expect: (usize, usize)) -> Option<Difference> {
+        Some(Difference {
+            actual: Position {
+                line: actual.0,
+                column: actual.1,
+            },
+            expect: Position {
+                line: expect.0,
+                column: expect.1,
+            },
+        })
+    }
+
+    #[test]
+    fn diff_position_graphemes() {
+        assert_eq!(diff_position("abc", "abc"), None);
+        assert_eq!(diff_position("abc", "abcd"), Some(3));
+        assert_eq!(diff_position("こんにちは", "こんばんは"), Some(2));
+        // "e" followed by a combining acute accent is a single grapheme.
+        assert_eq!(diff_position("cafe\u{301}", "cafe"), Some(3));
+    }
+
+    #[test]
+    fn exact() {
+        let checker = Checker::from(CompareMode::Exact);
+        assert_eq!(checker.compare("1\n2\n", "1\n2\n"), None);
+        assert_eq!(checker.compare("1 \n", "1\n"), at((1, 1), (1, 1)));
+        assert_eq!(checker.compare("1\r\n", "1\n"), at((1, 1), (1, 1)));
+        assert_eq!(checker.compare("1\n\n", "1\n"), at((2, 0), (2, 0)));
+    }
+
+    #[test]
+    fn lines() {
+        let checker = Checker::from(CompareMode::Lines);
+        assert_eq!(checker.compare(" 1 \r\n2\n\n", "1\n2\n"), None);
+        assert_eq!(checker.compare("1\n\n2\n", "1\n2\n"), at((2, 0), (2, 0)));
+        assert_eq!(checker.compare("  ab\n", "ac\n"), at((1, 3), (1, 1)));
+        assert_eq!(checker.compare("Yes\n", "yes\n"), at((1, 0), (1, 0)));
+    }
+
+    #[test]
+    fn case_insensitive() {
+        let checker = Checker::from(CompareMode::CaseInsensitive);
+        assert_eq!(checker.compare("YES\nno\n", "Yes\nNo\n"), None);
+        assert_eq!(checker.compare("YES\n", "No\n"), at((1, 0), (1, 0)));
+    }
+
+    #[test]
+    fn tokens() {
+        let checker = Checker::from(CompareMode::Tokens);
+        assert_eq!(checker.compare("1  2\n3\n", "1 2 3\n"), None);
+        assert_eq!(checker.compare("1 2\n4\n", "1 2 3\n"), at((2, 0), (1, 4)));
+        assert_eq!(checker.compare("1 22\n", "1 23\n"), at((1, 3), (1, 3)));
+        assert_eq!(checker.compare("1 2\n", "1 2 3\n"), at((1, 3), (1, 4)));
+    }
+
+    #[test]
+    fn float_tolerance() {
+        let checker = Checker::new(None, Some(1e-6));
+        assert_eq!(checker.mode, CompareMode::Tokens);
+        assert_eq!(checker.compare("0.5 1.0\n", "0.5\n1.0\n"), None);
+        assert_eq!(checker.compare("0.3333333\n", "0.333333333\n"), None);
+        assert_eq!(checker.compare("1000000.5\n", "1000000.0\n"), None);
+        assert_eq!(checker.compare("1 0.34\n", "1 0.33\n"), at((1, 5), (1, 5)));
+        assert_eq!(checker.compare("Yes\n", "yes\n"), at((1, 0), (1, 0)));
+        assert_eq!(checker.compare("nan\n", "nan\n"), None);
+        assert_eq!(checker.compare("nan\n", "0\n"), at((1, 0), (1, 0)));
+    }
+
+    #[test]
+    fn float_tolerance_within_mode() {
+        let lines = Checker::new(Some(CompareMode::Lines), Some(1e-6));
+        assert_eq!(lines.compare("x 0.3333333 \n", "x 0.333333333\n"), None);
+        assert_eq!(lines.compare("0.5 1.0\n", "0.5\n1.0\n"), at((1, 3), (1, 3)));
+        assert_eq!(lines.compare("x  0.5\n", "x 0.5\n"), at((1, 2), (1, 2)));
+
+        let case_insensitive = Checker::new(Some(CompareMode::CaseInsensitive), Some(1e-6));
+        assert_eq!(
+            case_insensitive.compare("YES 0.3333333\n", "yes 0.333333333\n"),
+            None
+        );
+        assert_eq!(
+            case_insensitive.compare("Yes 0.34\n", "yes 0.33\n"),
+            at((1, 7), (1, 7))
+        );
+
+        let exact = Checker::new(Some(CompareMode::Exact), Some(1e-6));
+        assert_eq!(exact.compare("0.3333333\n", "0.333333333\n"), None);
+        assert_eq!(
+            exact.compare("0.3333333\r\n", "0.333333333\n"),
+            at((1, 9), (1, 9))
+        );
+    }
+}
@@ -1,8 +1,22 @@
+mod add;
+mod ahc;
+mod bench;
+mod calibrate;
+mod compare;
 mod new;
+mod samples;
+mod scale;
 mod testcase;
 
+use add::Add;
+use ahc::Ahc;
+use bench::Bench;
+use calibrate::Calibrate;
 use clap::Parser;
+use compare::Compare;
 use new::New;
+use samples::Samples;
+use scale::Scale;
 use std::error::Error;
 use testcase::Testcase;
 
@@ -15,7 +29,14 @@ pub trait Run {
 #[command(propagate_version = true)]
 pub enum Cmd {
     New(New),
+    Add(Add),
     Testcase(Testcase),
+    Compare(Compare),
+    Bench(Bench),
+    Scale(Scale),
+    Ahc(Ahc),
+    Calibrate(Calibrate),
+    Samples(Samples),
 }
 
 impl Cmd {
@@ -25,7 +46,14 @@ impl Cmd {
     pub fn run(&self) -> Result<(), Box<dyn Error>> {
         match self {
             Cmd::New(cmd) => cmd.run(),
+            Cmd::Add(cmd) => cmd.run(),
             Cmd::Testcase(cmd) => cmd.run(),
+            Cmd::Compare(cmd) => cmd.run(),
+            Cmd::Bench(cmd) => cmd.run(),
+            Cmd::Scale(cmd) => cmd.run(),
+            Cmd::Ahc(cmd) => cmd.run(),
+            Cmd::Calibrate(cmd) => cmd.run(),
+            Cmd::Samples(cmd) => cmd.run(),
         }
     }
 }
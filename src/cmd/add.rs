@@ -0,0 +1,198 @@
+use crate::cmd::Run;
+use crate::config::{TEMPLATE, TESTCASE_DIR_NAME};
+use crate::fetch::{self, Sample};
+use crate::project;
+
+use clap::Parser;
+
+use std::env;
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+/// Adds tasks to the project in the current directory.
+#[derive(Parser, Debug)]
+pub struct Add {
+    /// Task names, such as `ex` or `a2` for a second attempt at `a`.
+    #[arg(value_name = "TASK", required = true)]
+    pub tasks: Vec<String>,
+    /// Download the sample cases from AtCoder.
+    #[arg(long)]
+    pub fetch: bool,
+    /// Contest to download the samples from; defaults to the project directory name.
+    #[arg(long, value_name = "ID")]
+    pub contest: Option<String>,
+}
+
+#[derive(Debug)]
+struct SamplesNotFetched {
+    tasks: Vec<String>,
+}
+
+impl Display for SamplesNotFetched {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The samples of {} could not be fetched",
+            self.tasks.join(", ")
+        )
+    }
+}
+
+impl Error for SamplesNotFetched {}
+
+impl Run for Add {
+    fn run(&self) -> Result<(), Box<dyn Error>> {
+        let root = project::find_root(&env::current_dir()?)?;
+        // Every URL is made before any file is written, so that a bad name changes nothing.
+        let urls = if self.fetch {
+            let contest = match &self.contest {
+                Some(contest) => contest.clone(),
+                None => root
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+            };
+            self.tasks
+                .iter()
+                .map(|task| fetch::task_url(&contest, task))
+                .collect::<Result<Vec<_>, _>>()?
+        } else {
+            Vec::new()
+        };
+        for path in add_tasks(&root, &self.tasks)? {
+            println!("created {}", path.display());
+        }
+        // A task whose samples cannot be fetched is still added, and the others are fetched.
+        let mut failed = Vec::new();
+        for (task, url) in self.tasks.iter().zip(&urls) {
+            let testcase_dir = root.join(TESTCASE_DIR_NAME).join(task);
+            let saved = fetch::fetch_samples(url, fetch::curl(url)).and_then(|samples| {
+                let written = write_samples(&testcase_dir, &samples)?;
+                Ok((written, samples.len()))
+            });
+            match saved {
+                Ok((written, total)) => println!("{task}: {written} of {total} samples saved"),
+                Err(e) => {
+                    eprintln!("{task}: {e}");
+                    failed.push(task.clone());
+                }
+            }
+        }
+        if !failed.is_empty() {
+            return Err(Box::new(SamplesNotFetched { tasks: failed }));
+        }
+        Ok(())
+    }
+}
+
+/// Creates the solution and the testcase directories of each task, returning the created paths.
+///
+/// Nothing is created if the solution of any task already exists.
+fn add_tasks(root: &Path, tasks: &[String]) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let bin_dir = root.join("src/bin");
+    for task in tasks {
+        let src = bin_dir.join(format!("{task}.rs"));
+        if src.try_exists()? {
+            return Err(Box::new(io::Error::new(
+                ErrorKind::AlreadyExists,
+                format!("`{}` already exists.", src.display()),
+            )));
+        }
+    }
+    let mut created = Vec::new();
+    fs::create_dir_all(&bin_dir)?;
+    for task in tasks {
+        let src = bin_dir.join(format!("{task}.rs"));
+        fs::write(&src, TEMPLATE)?;
+        created.push(src);
+        let testcase_dir = root.join(TESTCASE_DIR_NAME).join(task);
+        for sub in ["in", "out"] {
+            let dir = testcase_dir.join(sub);
+            if !dir.try_exists()? {
+                fs::create_dir_all(&dir)?;
+                created.push(dir);
+            }
+        }
+    }
+    Ok(created)
+}
+
+/// Saves samples as `in/N.txt` and `out/N.txt`, keeping existing cases. Returns how many were saved.
+fn write_samples(testcase_dir: &Path, samples: &[Sample]) -> Result<usize, Box<dyn Error>> {
+    let mut written = 0;
+    for (i, sample) in samples.iter().enumerate() {
+        let name = format!("{}.txt", i + 1);
+        let in_path = testcase_dir.join("in").join(&name);
+        let out_path = testcase_dir.join("out").join(&name);
+        if in_path.try_exists()? || out_path.try_exists()? {
+            continue;
+        }
+        fs::write(in_path, &sample.input)?;
+        fs::write(out_path, &sample.output)?;
+        written += 1;
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod add_test {
+    use super::{add_tasks, write_samples};
+    use crate::config::TEMPLATE;
+    use crate::fetch::Sample;
+    use crate::project::project_test::TempDir;
+    use std::fs;
+
+    fn tasks(names: &[&str]) -> Vec<String> {
+        names.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn adds_tasks() {
+        let tmp = TempDir::new("add-tasks");
+        let created = add_tasks(&tmp.0, &tasks(&["ex", "a2"])).unwrap();
+        assert_eq!(created.len(), 6);
+        assert_eq!(
+            fs::read_to_string(tmp.0.join("src/bin/ex.rs")).unwrap(),
+            TEMPLATE
+        );
+        assert!(tmp.0.join("testcase/a2/in").is_dir());
+        assert!(tmp.0.join("testcase/a2/out").is_dir());
+    }
+
+    #[test]
+    fn refuses_to_overwrite() {
+        let tmp = TempDir::new("add-overwrite");
+        fs::create_dir_all(tmp.0.join("src/bin")).unwrap();
+        fs::write(tmp.0.join("src/bin/a.rs"), "// solved\n").unwrap();
+        assert!(add_tasks(&tmp.0, &tasks(&["b", "a"])).is_err());
+        assert_eq!(
+            fs::read_to_string(tmp.0.join("src/bin/a.rs")).unwrap(),
+            "// solved\n"
+        );
+        assert!(!tmp.0.join("src/bin/b.rs").exists());
+    }
+
+    #[test]
+    fn keeps_existing_cases() {
+        let tmp = TempDir::new("add-samples");
+        add_tasks(&tmp.0, &tasks(&["a"])).unwrap();
+        let dir = tmp.0.join("testcase/a");
+        fs::write(dir.join("in/1.txt"), "mine\n").unwrap();
+        let samples = vec![
+            Sample {
+                input: "1\n".to_string(),
+                output: "2\n".to_string(),
+            },
+            Sample {
+                input: "3\n".to_string(),
+                output: "6\n".to_string(),
+            },
+        ];
+        assert_eq!(write_samples(&dir, &samples).unwrap(), 1);
+        assert_eq!(fs::read_to_string(dir.join("in/1.txt")).unwrap(), "mine\n");
+        assert_eq!(fs::read_to_string(dir.join("out/2.txt")).unwrap(), "6\n");
+    }
+}
@@ -0,0 +1,596 @@
+use crate::ahc::{self, AhcRun, Best, Objective, Seed, SeedChange, Status};
+use crate::cases;
+use crate::cmd::Run;
+use crate::color::{ColorChoice, Palette, Style};
+use crate::project;
+use crate::runner::{self, Profile};
+use crate::sandbox::Sandbox;
+use crate::syscommand::{SysChild, SysCommand};
+
+use clap::{Args, Parser, Subcommand};
+
+use std::env;
+use std::error::Error;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Runs and scores solutions of heuristic contests (AHC) over many seeds.
+#[derive(Parser, Debug)]
+pub struct Ahc {
+    #[command(subcommand)]
+    action: Action,
+}
+
+#[derive(Subcommand, Debug)]
+enum Action {
+    /// Runs a solution on every seed, scores the outputs and compares the run with the saved ones.
+    Run(RunArgs),
+    /// Compares the scores of two runs seed by seed.
+    Diff {
+        /// The earlier run: its name, `latest` or `latest~N` for the N-th run before the latest.
+        #[arg(default_value = "latest~1")]
+        before: String,
+        /// The later run.
+        #[arg(default_value = "latest")]
+        after: String,
+        /// The task the runs are of, if several tasks have runs.
+        #[arg(short, long)]
+        task: Option<String>,
+        /// Number of most regressed seeds to highlight.
+        #[arg(long, default_value_t = 5)]
+        worst: usize,
+        /// When to use colors.
+        #[arg(long, value_enum, default_value_t = ColorChoice::default())]
+        color: ColorChoice,
+    },
+}
+
+#[derive(Args, Debug)]
+struct RunArgs {
+    /// The source file name to execute.
+    src_name: String,
+    /// The directory of the inputs, one per seed.
+    #[arg(long, value_name = "DIR", default_value = "tools/in")]
+    inputs: PathBuf,
+    /// Run only the first COUNT seeds.
+    #[arg(long, value_name = "COUNT")]
+    seeds: Option<usize>,
+    /// The command printing `Score = N` for an output, run from the project root.
+    /// `{input}` and `{output}` are replaced with the paths of the files.
+    #[arg(
+        long,
+        value_name = "COMMAND",
+        default_value = "cargo run -q -r --manifest-path tools/Cargo.toml --bin vis {input} {output}"
+    )]
+    scorer: String,
+    /// Whether a higher or a lower score is better.
+    #[arg(long, value_enum, default_value_t = Objective::default())]
+    objective: Objective,
+    /// Seeds run at the same time; the number of CPUs by default.
+    #[arg(short, long)]
+    jobs: Option<usize>,
+    /// Seconds after which a seed is killed and scores 0.
+    #[arg(long, default_value_t = 2.0)]
+    time_limit: f64,
+    /// The name the run is saved under, instead of its date.
+    #[arg(long)]
+    name: Option<String>,
+    /// Build with the dev profile instead of the release profile used by the judge.
+    #[arg(long)]
+    debug: bool,
+    /// When to use colors.
+    #[arg(long, value_enum, default_value_t = ColorChoice::default())]
+    color: ColorChoice,
+    /// Run the solution without network, writing only to a temp dir, with limited processes and CPU time.
+    /// The scorer is trusted and runs as usual.
+    #[arg(long)]
+    sandbox: bool,
+}
+
+impl Run for Ahc {
+    fn run(&self) -> Result<(), Box<dyn Error>> {
+        let root = project::find_root(&env::current_dir()?)?;
+        match &self.action {
+            Action::Run(args) => args.run(&root),
+            Action::Diff {
+                before,
+                after,
+                task,
+                worst,
+                color,
+            } => {
+                let task = match task {
+                    Some(task) => task.clone(),
+                    None => only_task(&root)?,
+                };
+                let runs = AhcRun::load_all(&AhcRun::dir(&root, &task))?;
+                let (before, after) = (AhcRun::find(&runs, before)?, AhcRun::find(&runs, after)?);
+                print!("{}", diff(&runs, before, after, *worst, color.palette()));
+                Ok(())
+            }
+        }
+    }
+}
+
+impl RunArgs {
+    fn run(&self, root: &Path) -> Result<(), Box<dyn Error>> {
+        let palette = self.color.palette();
+        let mut inputs = seed_inputs(&root.join(&self.inputs))?;
+        if let Some(count) = self.seeds {
+            inputs.truncate(count);
+        }
+        let profile = if self.debug {
+            Profile::Dev
+        } else {
+            Profile::Release
+        };
+        let source = fs::read(root.join(format!("src/bin/{}.rs", self.src_name)))?;
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        let name = self
+            .name
+            .clone()
+            .unwrap_or_else(|| ahc::run_name(timestamp));
+        runner::build(std::slice::from_ref(&self.src_name), profile)?;
+        let binary = runner::binary_path(root, &self.src_name, profile);
+        let dir = AhcRun::dir(root, &self.src_name);
+        let output_dir = dir.join(&name);
+        fs::create_dir_all(&output_dir)?;
+        let sandbox = self.sandbox.then(|| Sandbox::new(palette)).transpose()?;
+
+        let jobs = self
+            .jobs
+            .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()))
+            .max(1);
+        let time_limit = Duration::from_secs_f64(self.time_limit);
+        let next = AtomicUsize::new(0);
+        let results = Mutex::new(vec![None; inputs.len()]);
+        thread::scope(|scope| {
+            for _ in 0..jobs.min(inputs.len()) {
+                scope.spawn(|| loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(input) = inputs.get(i) else {
+                        break;
+                    };
+                    let seed = run_seed(
+                        root,
+                        &binary,
+                        input,
+                        &output_dir,
+                        &self.scorer,
+                        time_limit,
+                        sandbox.as_ref(),
+                    );
+                    if let Ok(seed) = &seed {
+                        eprintln!("{}", palette.paint(Style::Dim, seed_line(seed)));
+                    }
+                    results.lock().unwrap()[i] = Some(seed.map_err(|e| e.to_string()));
+                });
+            }
+        });
+        let mut seeds = Vec::with_capacity(inputs.len());
+        for seed in results.into_inner().unwrap().into_iter().flatten() {
+            seeds.push(seed.map_err(io::Error::other)?);
+        }
+
+        let run = AhcRun {
+            task: self.src_name.clone(),
+            name: name.clone(),
+            objective: self.objective,
+            source_hash: ahc::source_hash(&source),
+            timestamp,
+            seeds,
+        };
+        run.save(&dir)?;
+        let mut runs: Vec<AhcRun> = AhcRun::load_all(&dir)?;
+        runs.retain(|saved| saved.name != run.name);
+        runs.push(run);
+        print!("{}", summary(&runs, &name, self.objective, palette));
+        println!(
+            "Results saved to {}",
+            dir.join(format!("{name}.csv")).display()
+        );
+        Ok(())
+    }
+}
+
+/// The inputs of `dir` in natural order.
+fn seed_inputs(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut inputs = Vec::new();
+    for entry in fs::read_dir(dir).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("Cannot read the inputs `{}`: {e}", dir.display()),
+        )
+    })? {
+        let path = entry?.path();
+        if path.is_file() {
+            inputs.push(path);
+        }
+    }
+    inputs.sort_by(|a, b| cases::natural_cmp(&a.to_string_lossy(), &b.to_string_lossy()));
+    Ok(inputs)
+}
+
+/// Runs the solution on one input, writes its output next to the run and scores it.
+fn run_seed(
+    root: &Path,
+    binary: &Path,
+    input: &Path,
+    output_dir: &Path,
+    scorer: &str,
+    time_limit: Duration,
+    sandbox: Option<&Sandbox>,
+) -> io::Result<Seed> {
+    let name = input
+        .file_stem()
+        .map_or(String::new(), |stem| stem.to_string_lossy().into_owned());
+    let mut command = runner::binary(binary);
+    command.stderr(Stdio::null());
+    if let Some(sandbox) = sandbox {
+        sandbox.apply(&mut command, Some(time_limit));
+    }
+    let start = Instant::now();
+    let mut child = SysCommand::spawn(&mut command)?;
+    child.set_time_limit(time_limit);
+    let execution = runner::execute(&mut child, &fs::read(input)?, runner::OUTPUT_LIMIT, start);
+    if execution.is_err() {
+        let _ = child.kill();
+    }
+    let execution = execution?;
+    let output = output_dir.join(format!("{name}.txt"));
+    fs::write(&output, &execution.stdout)?;
+    let time_ms = execution.time.as_secs_f64() * 1000.0;
+    let failed = |status| Seed {
+        seed: name.clone(),
+        status,
+        score: 0,
+        time_ms,
+    };
+    if execution.timed_out {
+        return Ok(failed(Status::TimeLimitExceeded));
+    }
+    if execution.output_limit_exceeded {
+        return Ok(failed(Status::OutputLimitExceeded));
+    }
+    if !execution.succeeded() {
+        return Ok(failed(Status::RuntimeError));
+    }
+
+    let mut command = scorer_command(scorer, input, &output);
+    command.current_dir(root);
+    let mut child = SysCommand::spawn(&mut command)?;
+    let scored = runner::execute(&mut child, b"", runner::OUTPUT_LIMIT, Instant::now())?;
+    let score = ahc::parse_score(&scored.stdout).or_else(|| ahc::parse_score(&scored.stderr));
+    Ok(match score {
+        Some(score) if scored.succeeded() => Seed {
+            score,
+            ..failed(Status::Ok)
+        },
+        _ => failed(Status::ScorerError),
+    })
+}
+
+/// `scorer` split on whitespace, with `{input}` and `{output}` replaced.
+fn scorer_command(scorer: &str, input: &Path, output: &Path) -> Command {
+    let mut words = scorer.split_whitespace().map(|word| {
+        word.replace("{input}", &input.to_string_lossy())
+            .replace("{output}", &output.to_string_lossy())
+    });
+    let mut command = Command::new(words.next().unwrap_or_default());
+    command
+        .args(words)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    command
+}
+
+fn seed_line(seed: &Seed) -> String {
+    format!(
+        "{} {} {} ({:.0} ms)",
+        seed.seed,
+        seed.status.code(),
+        seed.score,
+        seed.time_ms
+    )
+}
+
+/// The task with saved runs, when there is only one.
+fn only_task(root: &Path) -> Result<String, Box<dyn Error>> {
+    let dir = root.join("target/atcoder-rs/ahc");
+    let mut tasks = Vec::new();
+    if let Ok(entries) = fs::read_dir(&dir) {
+        for entry in entries {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                tasks.push(entry.file_name().to_string_lossy().into_owned());
+            }
+        }
+    }
+    tasks.sort();
+    match tasks.len() {
+        1 => Ok(tasks.remove(0)),
+        0 => Err(Box::new(io::Error::new(
+            io::ErrorKind::NotFound,
+            "No runs yet. Please run `atcoder ahc run <task>` first.",
+        ))),
+        _ => Err(Box::new(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "Several tasks have runs: {}. Please choose one with `--task`.",
+                tasks.join(", ")
+            ),
+        ))),
+    }
+}
+
+/// Runs shown in the summary; older ones still count for the relative scores.
+const SUMMARY_RUNS: usize = 10;
+
+/// A table of the latest saved runs with their total and relative scores, `current`
+/// highlighted, followed by the seeds `current` failed.
+fn summary(runs: &[AhcRun], current: &str, objective: Objective, palette: Palette) -> String {
+    let best = Best::of(runs, objective);
+    let older = runs.len().saturating_sub(SUMMARY_RUNS);
+    let (older, runs) = (older, &runs[older..]);
+    let width = runs
+        .iter()
+        .map(|run| run.name.len())
+        .chain(std::iter::once("run".len()))
+        .max()
+        .unwrap_or(0);
+    let mut rendered = String::new();
+    let _ = writeln!(
+        rendered,
+        "{}",
+        palette.paint(
+            Style::Header,
+            format!(
+                "  {:<width$}  {:<8}  {:>6}  {:>14}  {:>12}  {:>9}  {:>4}  {:>4}  {:>4}  {:>4}",
+                "run", "source", "seeds", "total", "average", "relative", "TLE", "RE", "OLE", "SE"
+            )
+        )
+    );
+    if older > 0 {
+        let _ = writeln!(
+            rendered,
+            "{}",
+            palette.paint(Style::Dim, format!("  ({older} older runs)"))
+        );
+    }
+    for run in runs {
+        let average = if run.seeds.is_empty() {
+            0.0
+        } else {
+            run.total() as f64 / run.seeds.len() as f64
+        };
+        let line = format!(
+            "  {:<width$}  {:<8}  {:>6}  {:>14}  {:>12.1}  {:>8.3}%  {:>4}  {:>4}  {:>4}  {:>4}",
+            run.name,
+            short_hash(run),
+            run.seeds.len(),
+            run.total(),
+            average,
+            run.relative(&best),
+            run.count(Status::TimeLimitExceeded),
+            run.count(Status::RuntimeError),
+            run.count(Status::OutputLimitExceeded),
+            run.count(Status::ScorerError)
+        );
+        if run.name == current {
+            let _ = writeln!(rendered, "{}", palette.paint(Style::Highlight, line));
+        } else {
+            let _ = writeln!(rendered, "{line}");
+        }
+    }
+    let failed: Vec<&str> = runs
+        .iter()
+        .filter(|run| run.name == current)
+        .flat_map(|run| &run.seeds)
+        .filter(|seed| seed.status != Status::Ok)
+        .map(|seed| seed.seed.as_str())
+        .collect();
+    if !failed.is_empty() {
+        let _ = writeln!(
+            rendered,
+            "{} {}",
+            palette.paint(Style::Error, "Failed seeds:"),
+            failed.join(", ")
+        );
+    }
+    rendered
+}
+
+fn short_hash(run: &AhcRun) -> &str {
+    run.source_hash.get(..8).unwrap_or(&run.source_hash)
+}
+
+/// The seed by seed changes from `before` to `after`, with the `worst` most regressed seeds
+/// highlighted, and the totals over the seeds both have. Relative scores are against the best
+/// scores of `runs`.
+fn diff(
+    runs: &[AhcRun],
+    before: &AhcRun,
+    after: &AhcRun,
+    worst: usize,
+    palette: Palette,
+) -> String {
+    let objective = after.objective;
+    let best = Best::of(runs, objective);
+    let changes = SeedChange::between(before, after, &best);
+    let mut regressions: Vec<&SeedChange> = changes
+        .iter()
+        .filter(|change| change.regressed(objective))
+        .collect();
+    regressions.sort_by(|a, b| a.relative.total_cmp(&b.relative));
+    regressions.truncate(worst);
+
+    let mut rendered = String::new();
+    let same = if before.source_hash == after.source_hash {
+        ", same source"
+    } else {
+        ""
+    };
+    let _ = writeln!(
+        rendered,
+        "{} (source {}) -> {} (source {}){same}",
+        before.name,
+        short_hash(before),
+        after.name,
+        short_hash(after)
+    );
+    let _ = writeln!(
+        rendered,
+        "{}",
+        palette.paint(
+            Style::Header,
+            format!(
+                "  {:<8}  {:>12}  {:>12}  {:>12}  {:>9}",
+                "seed", "before", "after", "delta", "relative"
+            )
+        )
+    );
+    for change in &changes {
+        let line = format!(
+            "  {:<8}  {:>12}  {:>12}  {:>+12}  {:>+9.3}",
+            change.seed,
+            change.before,
+            change.after,
+            change.after - change.before,
+            change.relative
+        );
+        if regressions.iter().any(|worst| worst.seed == change.seed) {
+            let _ = writeln!(rendered, "{}", palette.paint(Style::Error, line));
+        } else {
+            let _ = writeln!(rendered, "{line}");
+        }
+    }
+    let wins = changes.iter().filter(|c| c.improved(objective)).count();
+    let losses = changes.iter().filter(|c| c.regressed(objective)).count();
+    let _ = writeln!(
+        rendered,
+        "{} {wins}, {} {losses}, ties {} of {} seeds",
+        palette.paint(Style::Ok, "Wins"),
+        palette.paint(Style::Error, "losses"),
+        changes.len() - wins - losses,
+        changes.len()
+    );
+    // Seeds only one run has would skew the totals, so they are left out and counted.
+    let (common_before, common_after) = (before.common_with(after), after.common_with(before));
+    let (only_before, only_after) = (
+        before.seeds.len() - common_before.seeds.len(),
+        after.seeds.len() - common_after.seeds.len(),
+    );
+    if only_before > 0 || only_after > 0 {
+        let _ = writeln!(
+            rendered,
+            "Left out of the totals: {only_before} seeds only in {}, {only_after} only in {}",
+            before.name, after.name
+        );
+    }
+    let (total_before, total_after) = (common_before.total(), common_after.total());
+    let _ = writeln!(
+        rendered,
+        "Total score {total_before} -> {total_after} ({:+})",
+        total_after - total_before
+    );
+    let (relative_before, relative_after) =
+        (common_before.relative(&best), common_after.relative(&best));
+    let _ = writeln!(
+        rendered,
+        "Relative score {relative_before:.3}% -> {relative_after:.3}% ({:+.3})",
+        relative_after - relative_before
+    );
+    if !regressions.is_empty() {
+        let regressions: Vec<String> = regressions
+            .iter()
+            .map(|change| format!("{} ({:+.3})", change.seed, change.relative))
+            .collect();
+        let _ = writeln!(
+            rendered,
+            "{} {}",
+            palette.paint(Style::Error, "Most regressed:"),
+            regressions.join(", ")
+        );
+    }
+    rendered
+}
+
+#[cfg(test)]
+mod ahc_cmd_test {
+    use super::{diff, scorer_command, seed_inputs, summary};
+    use crate::ahc::ahc_test::run;
+    use crate::ahc::Objective;
+    use crate::color::Palette;
+    use crate::project::project_test::TempDir;
+    use std::fs;
+    use std::path::Path;
+
+    #[test]
+    fn lists_seeds_in_order() {
+        let tmp = TempDir::new("ahc-inputs");
+        for name in ["10.txt", "2.txt", "0001.txt"] {
+            fs::write(tmp.0.join(name), "").unwrap();
+        }
+        let inputs = seed_inputs(&tmp.0).unwrap();
+        let names: Vec<_> = inputs.iter().map(|p| p.file_name().unwrap()).collect();
+        assert_eq!(names, ["0001.txt", "2.txt", "10.txt"]);
+    }
+
+    #[test]
+    fn substitutes_paths() {
+        let command = scorer_command(
+            "vis {input} {output}",
+            Path::new("in/0.txt"),
+            Path::new("out/0.txt"),
+        );
+        assert_eq!(command.get_program(), "vis");
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(args, ["in/0.txt", "out/0.txt"]);
+    }
+
+    #[test]
+    fn summarizes() {
+        let runs = [run("before", &[100, 50, 20]), run("latest", &[80, 100, 0])];
+        assert_eq!(
+            summary(&runs, "latest", Objective::Max, Palette::PLAIN),
+            "  run     source     seeds           total       average   relative   TLE    RE   OLE    SE
+  before  d89aa062       3             170          56.7    83.333%     0     0     0     0
+  latest  376e3463       3             180          60.0    60.000%     1     0     0     0
+Failed seeds: 0002
+"
+        );
+    }
+
+    #[test]
+    fn diffs() {
+        let runs = [
+            run("before", &[100, 50, 20, 10]),
+            run("after", &[80, 100, 20, 0, 5]),
+        ];
+        assert_eq!(
+            diff(&runs, &runs[0], &runs[1], 1, Palette::PLAIN),
+            "before (source d89aa062) -> after (source bf82010f)
+  seed            before         after         delta   relative
+  0000               100            80           -20    -20.000
+  0001                50           100           +50    +50.000
+  0002                20            20            +0     +0.000
+  0003                10             0           -10   -100.000
+Wins 1, losses 2, ties 1 of 4 seeds
+Left out of the totals: 0 seeds only in before, 1 only in after
+Total score 180 -> 200 (+20)
+Relative score 87.500% -> 70.000% (-17.500)
+Most regressed: 0003 (-100.000)
+"
+        );
+    }
+}
@@ -0,0 +1,358 @@
+use crate::bench::{self, CaseBench, Stats};
+use crate::cases::{self, Layout, Manifest, Pattern};
+use crate::cmd::Run;
+use crate::color::{ColorChoice, Palette, Style};
+use crate::config::TESTCASE_DIR_NAME;
+use crate::project;
+use crate::runner::{self, Execution, Profile};
+use crate::sandbox::{Sandbox, DEFAULT_CPU_LIMIT};
+use crate::syscommand::{SysChild, SysCommand};
+
+use clap::Parser;
+
+use std::env;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter, Write as _};
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+
+#[derive(Parser, Debug)]
+/// Time a solution on every input several times.
+pub struct Bench {
+    /// The source file name to execute.
+    src_name: String,
+    /// If the directory containing the inputs differs from the source file name.
+    #[arg(short, long)]
+    dir_name: Option<String>,
+    /// Measured runs per input.
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..))]
+    runs: u32,
+    /// Runs per input before measuring, to warm up caches.
+    #[arg(long, default_value_t = 2)]
+    warmup: u32,
+    /// Run only the inputs whose name matches a glob, or a regex prefixed with `re:`.
+    #[arg(long, value_name = "PATTERN", num_args = 1..)]
+    filter: Vec<Pattern>,
+    /// Skip the inputs whose name matches a glob, or a regex prefixed with `re:`.
+    #[arg(long, value_name = "PATTERN", num_args = 1..)]
+    exclude: Vec<Pattern>,
+    /// How the testcase directory is laid out, overriding its `layout` file and detection.
+    #[arg(long, value_enum)]
+    layout: Option<Layout>,
+    /// Number of slowest inputs to highlight.
+    #[arg(long, default_value_t = 3)]
+    slowest: usize,
+    /// Save the results as a baseline to compare later runs with.
+    #[arg(long, value_name = "NAME", num_args = 0..=1, default_missing_value = "baseline")]
+    save_baseline: Option<String>,
+    /// Compare with a saved baseline, failing if an input got slower than the threshold.
+    #[arg(long, value_name = "NAME", num_args = 0..=1, default_missing_value = "baseline")]
+    baseline: Option<String>,
+    /// Slowdown of the median wall time, in percent, that counts as a regression.
+    #[arg(long, default_value_t = 10.0)]
+    threshold: f64,
+    /// Build with the dev profile instead of the release profile used by the judge.
+    #[arg(long)]
+    debug: bool,
+    /// When to use colors.
+    #[arg(long, value_enum, default_value_t = ColorChoice::default())]
+    color: ColorChoice,
+    /// Stack size of the main thread in MiB, for deep recursion; the inherited limit by default.
+    #[arg(long, value_name = "MIB")]
+    stack_size: Option<u64>,
+    /// Run the solution without network, writing only to a temp dir, with limited processes and CPU time.
+    #[arg(long)]
+    sandbox: bool,
+}
+
+#[derive(Debug)]
+struct Regression {
+    count: usize,
+    threshold: f64,
+}
+
+impl Display for Regression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} inputs got more than {}% slower than the baseline",
+            self.count, self.threshold
+        )
+    }
+}
+
+impl Error for Regression {}
+
+impl Run for Bench {
+    fn run(&self) -> Result<(), Box<dyn Error>> {
+        let palette = self.color.palette();
+        let root = project::find_root(&env::current_dir()?)?;
+        // Baselines belong to the task, so that a rewrite run with `-d` compares with the original.
+        let task = self.dir_name.as_ref().unwrap_or(&self.src_name);
+        let testcase_dir = root.join(TESTCASE_DIR_NAME).join(task);
+        let src_path = root.join(format!("src/bin/{}.rs", self.src_name));
+        let inputs = cases::task_inputs(&testcase_dir, &src_path, self.layout)?;
+        let baseline = match &self.baseline {
+            Some(name) => Some(bench::Bench::load(&bench::Bench::path(&root, task, name))?),
+            None => None,
+        };
+
+        let profile = if self.debug {
+            Profile::Dev
+        } else {
+            Profile::Release
+        };
+        runner::build(std::slice::from_ref(&self.src_name), profile)?;
+        let binary = runner::binary_path(&root, &self.src_name, profile);
+        let sandbox = self.sandbox.then(|| Sandbox::new(palette)).transpose()?;
+        let manifest = Manifest::from_dir(&testcase_dir)?.unwrap_or_default();
+        let mut result = bench::Bench {
+            task: self.src_name.clone(),
+            runs: self.runs as usize,
+            cases: Vec::new(),
+        };
+        for input in &inputs {
+            let case = &input.case;
+            if !cases::is_selected(case, &self.filter, &self.exclude) {
+                continue;
+            }
+            eprintln!("{} {}", palette.paint(Style::Dim, "Running"), case.name);
+            let time_limit = manifest.options(case).time_limit();
+            let mut executions = Vec::with_capacity(self.runs as usize);
+            for run in 0..self.warmup + self.runs {
+                let mut command = runner::binary(&binary);
+                command.stderr(Stdio::null());
+                if let Some(sandbox) = &sandbox {
+                    sandbox.apply(&mut command, Some(time_limit.unwrap_or(DEFAULT_CPU_LIMIT)));
+                }
+                if let Some(mib) = self.stack_size {
+                    runner::set_stack_size(&mut command, mib);
+                }
+                let start = Instant::now();
+                let mut child = SysCommand::spawn(&mut command)?;
+                if let Some(time_limit) = time_limit {
+                    child.set_time_limit(time_limit);
+                }
+                let execution = runner::execute(
+                    &mut child,
+                    case.input.as_bytes(),
+                    runner::OUTPUT_LIMIT,
+                    start,
+                );
+                if execution.is_err() {
+                    let _ = child.kill();
+                }
+                if run >= self.warmup {
+                    executions.push(execution?);
+                }
+            }
+            result.cases.push(measure(&case.name, &executions));
+        }
+
+        let threshold = self.threshold / 100.0;
+        print!(
+            "{}",
+            render(&result, baseline.as_ref(), threshold, self.slowest, palette)
+        );
+        if let Some(name) = &self.save_baseline {
+            let path = bench::Bench::path(&root, task, name);
+            result.save(&path)?;
+            println!("Baseline saved to {}", path.display());
+        }
+        if let Some(baseline) = &baseline {
+            let count = result
+                .cases
+                .iter()
+                .filter(|case| bench::Bench::regressed(case, baseline, threshold))
+                .count();
+            if count > 0 {
+                return Err(Box::new(Regression {
+                    count,
+                    threshold: self.threshold,
+                }));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn measure(name: &str, executions: &[Execution]) -> CaseBench {
+    let wall: Vec<Duration> = executions.iter().map(|e| e.time).collect();
+    let cpu: Vec<Duration> = executions
+        .iter()
+        .filter_map(|e| e.usage)
+        .map(|usage| usage.cpu_time())
+        .collect();
+    let zero = Stats {
+        min: 0.0,
+        median: 0.0,
+        p95: 0.0,
+        max: 0.0,
+    };
+    CaseBench {
+        name: name.to_string(),
+        wall: Stats::of(&wall).unwrap_or(zero),
+        cpu: Stats::of(&cpu).unwrap_or(zero),
+        memory_kb: executions
+            .iter()
+            .filter_map(|e| e.usage)
+            .map(|u| u.max_rss_kb)
+            .max(),
+        failures: executions.iter().filter(|e| !e.succeeded()).count(),
+    }
+}
+
+fn render(
+    result: &bench::Bench,
+    baseline: Option<&bench::Bench>,
+    threshold: f64,
+    slowest: usize,
+    palette: Palette,
+) -> String {
+    let slowest = result.slowest(slowest);
+    let width = result
+        .cases
+        .iter()
+        .map(|case| case.name.len())
+        .chain(std::iter::once("input".len()))
+        .max()
+        .unwrap_or(0);
+    let mut rendered = String::new();
+    let _ = write!(
+        rendered,
+        "{}",
+        palette.paint(
+            Style::Header,
+            format!(
+                "  {:<width$}  {:>29}  {:>29}  {:>10}",
+                "input", "wall ms min/median/p95/max", "cpu ms min/median/p95/max", "memory"
+            )
+        )
+    );
+    if baseline.is_some() {
+        let _ = write!(
+            rendered,
+            "  {}",
+            palette.paint(Style::Header, "vs baseline")
+        );
+    }
+    rendered.push('\n');
+    let stats = |stats: &Stats| {
+        format!(
+            "{:.1}/{:.1}/{:.1}/{:.1}",
+            stats.min, stats.median, stats.p95, stats.max
+        )
+    };
+    for case in &result.cases {
+        let name = format!("{:<width$}", case.name);
+        let name = if slowest.contains(&case.name.as_str()) {
+            palette.paint(Style::Highlight, name)
+        } else {
+            name
+        };
+        let memory = case
+            .memory_kb
+            .map_or("-".to_string(), |kb| format!("{kb} KiB"));
+        let _ = write!(
+            rendered,
+            "  {name}  {:>29}  {:>29}  {memory:>10}",
+            stats(&case.wall),
+            stats(&case.cpu)
+        );
+        if let Some(baseline) = baseline {
+            let change = match bench::Bench::change(case, baseline) {
+                Some(change) => {
+                    let text = format!("{:+.1}%", change * 100.0);
+                    if bench::Bench::regressed(case, baseline, threshold) {
+                        palette.paint(Style::Error, format!("{text} regression"))
+                    } else {
+                        palette.paint(Style::Dim, text)
+                    }
+                }
+                None => palette.paint(Style::Dim, "new"),
+            };
+            let _ = write!(rendered, "  {change}");
+        }
+        if case.failures > 0 {
+            let _ = write!(
+                rendered,
+                "  {}",
+                palette.paint(
+                    Style::Error,
+                    format!("{} of {} runs failed", case.failures, result.runs)
+                )
+            );
+        }
+        rendered.push('\n');
+    }
+    if !slowest.is_empty() {
+        let slowest: Vec<String> = slowest
+            .iter()
+            .filter_map(|name| result.case(name))
+            .map(|case| format!("{} ({:.1} ms)", case.name, case.wall.median))
+            .collect();
+        let _ = writeln!(
+            rendered,
+            "{} {}",
+            palette.paint(Style::Header, "Slowest:"),
+            slowest.join(", ")
+        );
+    }
+    rendered
+}
+
+#[cfg(test)]
+mod bench_cmd_test {
+    use super::{measure, render};
+    use crate::bench::bench_test::case;
+    use crate::bench::Bench;
+    use crate::color::Palette;
+    use crate::runner::Execution;
+    use crate::syscommand::Usage;
+    use std::time::Duration;
+
+    #[test]
+    fn measures() {
+        let run = |ms: u64, exit_code: i32| Execution {
+            exit_code: Some(exit_code),
+            time: Duration::from_millis(ms),
+            usage: Some(Usage {
+                max_rss_kb: ms,
+                user_time: Duration::from_millis(ms / 2),
+                sys_time: Duration::from_millis(1),
+            }),
+            ..Execution::default()
+        };
+        let case = measure("1.txt", &[run(30, 0), run(10, 0), run(20, 1)]);
+        assert_eq!(case.wall.median, 20.0);
+        assert_eq!(case.cpu.min, 6.0);
+        assert_eq!(case.cpu.max, 16.0);
+        assert_eq!(case.memory_kb, Some(30));
+        assert_eq!(case.failures, 1);
+    }
+
+    #[test]
+    fn renders() {
+        let baseline = Bench {
+            task: "a".to_string(),
+            runs: 10,
+            cases: vec![case("1.txt", 100.0), case("2.txt", 10.0)],
+        };
+        let mut slow = case("1.txt", 125.0);
+        slow.failures = 2;
+        let result = Bench {
+            cases: vec![slow, case("2.txt", 10.5), case("max.txt", 300.0)],
+            ..baseline.clone()
+        };
+        let rendered = render(&result, Some(&baseline), 0.1, 1, Palette::PLAIN);
+        assert_eq!(
+            rendered,
+            "  input       wall ms min/median/p95/max      cpu ms min/median/p95/max      memory  vs baseline
+  1.txt          125.0/125.0/125.0/125.0        125.0/125.0/125.0/125.0    2048 KiB  +25.0% regression  2 of 10 runs failed
+  2.txt              10.5/10.5/10.5/10.5            10.5/10.5/10.5/10.5    2048 KiB  +5.0%
+  max.txt        300.0/300.0/300.0/300.0        300.0/300.0/300.0/300.0    2048 KiB  new
+Slowest: max.txt (300.0 ms)
+"
+        );
+    }
+}
@@ -0,0 +1,153 @@
+use crate::calibrate::{self, Calibration, DEFAULT_JUDGE_MS, SUITE};
+use crate::cmd::Run;
+use crate::color::{ColorChoice, Palette, Style};
+use crate::project;
+use crate::runner::{self, Execution};
+use crate::syscommand::SysCommand;
+
+use clap::Parser;
+
+use std::env;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::process::{Command, Stdio};
+use std::time::Instant;
+
+#[derive(Parser, Debug)]
+/// Time a fixed benchmark suite and save how fast this machine is compared with the judge.
+pub struct Calibrate {
+    /// The milliseconds the suite printed in the custom test of AtCoder, instead of a rough
+    /// estimate.
+    #[arg(long, value_name = "MS")]
+    judge_ms: Option<f64>,
+    /// Runs of the suite, of which the median time is taken.
+    #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(u32).range(1..))]
+    runs: u32,
+    /// Print the suite, to run it in the custom test of AtCoder, instead of running it.
+    #[arg(long)]
+    print_suite: bool,
+    /// When to use colors.
+    #[arg(long, value_enum, default_value_t = ColorChoice::default())]
+    color: ColorChoice,
+}
+
+#[derive(Debug)]
+enum CalibrateError {
+    JudgeMs(f64),
+    Compile(i32),
+    Suite(Execution),
+}
+
+impl Display for CalibrateError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            CalibrateError::JudgeMs(ms) => {
+                write!(f, "The time of the suite must be positive, not {ms}")
+            }
+            CalibrateError::Compile(code) => write!(f, "`rustc` exited with {code}"),
+            CalibrateError::Suite(execution) => {
+                write!(f, "The suite did not print its time\n{}", execution.stderr)
+            }
+        }
+    }
+}
+
+impl Error for CalibrateError {}
+
+impl Run for Calibrate {
+    fn run(&self) -> Result<(), Box<dyn Error>> {
+        if self.print_suite {
+            print!("{SUITE}");
+            return Ok(());
+        }
+        let judge_ms = self.judge_ms.unwrap_or(DEFAULT_JUDGE_MS);
+        if judge_ms.is_nan() || judge_ms <= 0.0 {
+            return Err(Box::new(CalibrateError::JudgeMs(judge_ms)));
+        }
+        let palette = self.color.palette();
+        let root = project::find_root(&env::current_dir()?)?;
+        let dir = root.join("target/atcoder-rs/calibrate");
+        fs::create_dir_all(&dir)?;
+        let source = dir.join("suite.rs");
+        let binary = dir.join(format!("suite{}", env::consts::EXE_SUFFIX));
+        fs::write(&source, SUITE)?;
+        let mut rustc = Command::new("rustc");
+        rustc
+            .args(["--edition", "2021", "-C", "opt-level=3", "-o"])
+            .arg(&binary)
+            .arg(&source);
+        match SysCommand::status(&mut rustc)? {
+            0 => {}
+            code => return Err(Box::new(CalibrateError::Compile(code))),
+        }
+
+        let mut times = Vec::with_capacity(self.runs as usize);
+        for run in 1..=self.runs {
+            eprintln!(
+                "{} {run}/{}",
+                palette.paint(Style::Dim, "Running the suite"),
+                self.runs
+            );
+            let mut command = runner::binary(&binary);
+            command.stderr(Stdio::piped());
+            let mut child = SysCommand::spawn(&mut command)?;
+            let execution = runner::execute(&mut child, b"", runner::OUTPUT_LIMIT, Instant::now())?;
+            match calibrate::parse_suite_output(&execution.stdout) {
+                Some(ms) if execution.succeeded() => times.push(ms),
+                _ => return Err(Box::new(CalibrateError::Suite(execution))),
+            }
+        }
+        times.sort_by(f64::total_cmp);
+        let calibration = Calibration {
+            local_ms: times[times.len() / 2],
+            judge_ms,
+        };
+        calibration.save(&Calibration::path(&root))?;
+        print!("{}", render(&calibration, self.judge_ms.is_some(), palette));
+        Ok(())
+    }
+}
+
+fn render(calibration: &Calibration, measured: bool, palette: Palette) -> String {
+    let judge = if measured {
+        "on the judge"
+    } else {
+        "estimated on the judge"
+    };
+    let factor = calibration.factor();
+    let comparison = if factor >= 1.0 {
+        palette.paint(Style::Error, format!("{factor:.2}× slower than"))
+    } else {
+        palette.paint(Style::Ok, format!("{:.2}× faster than", 1.0 / factor))
+    };
+    format!(
+        "Suite: {:.0} ms here, {:.0} ms {judge}\nThis machine is {comparison} the judge; `atcoder testcase --calibrated` scales times by it.\n",
+        calibration.local_ms, calibration.judge_ms
+    )
+}
+
+#[cfg(test)]
+mod calibrate_cmd_test {
+    use super::render;
+    use crate::calibrate::Calibration;
+    use crate::color::Palette;
+
+    #[test]
+    fn renders() {
+        let slower = Calibration {
+            local_ms: 1050.0,
+            judge_ms: 700.0,
+        };
+        assert_eq!(
+            render(&slower, false, Palette::PLAIN),
+            "Suite: 1050 ms here, 700 ms estimated on the judge\nThis machine is 1.50× slower than the judge; `atcoder testcase --calibrated` scales times by it.\n"
+        );
+        let faster = Calibration {
+            local_ms: 560.0,
+            judge_ms: 700.0,
+        };
+        assert!(render(&faster, true, Palette::PLAIN)
+            .contains("700 ms on the judge\nThis machine is 1.25× faster than the judge"));
+    }
+}
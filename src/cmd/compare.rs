@@ -0,0 +1,486 @@
+use crate::cases::{self, Layout, Manifest, Pattern};
+use crate::checker::{Checker, CompareMode, Difference};
+use crate::cmd::Run;
+use crate::color::{ColorChoice, Palette, Style};
+use crate::config::TESTCASE_DIR_NAME;
+use crate::diff::{DiffOptions, Mismatch};
+use crate::project;
+use crate::runner::{self, Execution, Profile};
+use crate::sandbox::{self, Sandbox};
+use crate::syscommand::{SysChild, SysCommand};
+
+use clap::Parser;
+
+use std::env;
+use std::error::Error;
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+#[derive(Parser, Debug)]
+/// Run several solutions of a task on the same inputs and compare their outputs.
+pub struct Compare {
+    /// The source file names; the first one is the reference the others are compared with.
+    #[arg(required = true, num_args = 2..)]
+    src_names: Vec<String>,
+    /// If the directory containing the inputs differs from the first source file name.
+    #[arg(short, long)]
+    dir_name: Option<String>,
+    /// Run only the inputs whose name matches a glob, or a regex prefixed with `re:`.
+    #[arg(long, value_name = "PATTERN", num_args = 1..)]
+    filter: Vec<Pattern>,
+    /// Skip the inputs whose name matches a glob, or a regex prefixed with `re:`.
+    #[arg(long, value_name = "PATTERN", num_args = 1..)]
+    exclude: Vec<Pattern>,
+    /// How the testcase directory is laid out, overriding its `layout` file and detection.
+    #[arg(long, value_enum)]
+    layout: Option<Layout>,
+    /// How to compare the outputs, overriding the `compare` file of the testcase directory.
+    #[arg(long, value_enum)]
+    compare: Option<CompareMode>,
+    #[command(flatten)]
+    diff: DiffOptions,
+    /// When to use colors.
+    #[arg(long, value_enum, default_value_t = ColorChoice::default())]
+    color: ColorChoice,
+    /// Stack size of the main thread in MiB, for deep recursion; the inherited limit by default.
+    #[arg(long, value_name = "MIB")]
+    stack_size: Option<u64>,
+    /// Run the solutions without network, writing only to a temp dir, with limited processes and CPU time.
+    #[arg(long)]
+    sandbox: bool,
+}
+
+impl Run for Compare {
+    fn run(&self) -> Result<(), Box<dyn Error>> {
+        let palette = self.color.palette();
+        let reference = &self.src_names[0];
+        let testcase_dir =
+            PathBuf::from(TESTCASE_DIR_NAME).join(self.dir_name.as_ref().unwrap_or(reference));
+        let src_path = PathBuf::from(format!("src/bin/{reference}.rs"));
+        let inputs = cases::task_inputs(&testcase_dir, &src_path, self.layout)?;
+        let selected = inputs
+            .iter()
+            .filter(|input| cases::is_selected(&input.case, &self.filter, &self.exclude));
+
+        // Build every solution first and run the binaries, so that the times and the memory are
+        // those of the solutions, without the compilation or cargo.
+        runner::build(&self.src_names, Profile::Dev)?;
+        let sandbox = self.sandbox.then(|| Sandbox::new(palette)).transpose()?;
+        let root = project::find_root(&env::current_dir()?).unwrap_or_else(|_| PathBuf::from("."));
+        let dir_mode = CompareMode::from_dir(&testcase_dir)?;
+        let manifest = Manifest::from_dir(&testcase_dir)?.unwrap_or_default();
+        let mut totals = vec![Total::default(); self.src_names.len()];
+        for input in selected {
+            let case = &input.case;
+            let options = manifest.options(case);
+            let checker = Checker::new(
+                self.compare.or(options.checker).or(dir_mode),
+                options.float_tolerance,
+            );
+            let mut executions = Vec::with_capacity(self.src_names.len());
+            for src_name in &self.src_names {
+                let mut command =
+                    runner::binary(&runner::binary_path(&root, src_name, Profile::Dev));
+                if let Some(sandbox) = &sandbox {
+                    let cpu_limit = options.time_limit().unwrap_or(sandbox::DEFAULT_CPU_LIMIT);
+                    sandbox.apply(&mut command, Some(cpu_limit));
+                }
+                if let Some(mib) = self.stack_size {
+                    runner::set_stack_size(&mut command, mib);
+                }
+                let execution = run_solution(
+                    command,
+                    case.input.as_bytes(),
+                    options.time_limit(),
+                    options.output_limit(None),
+                );
+                if let Some(error) = &execution.error {
+                    eprintln!(
+                        "{} `{src_name}` could not be run on {}: {error}",
+                        palette.paint(Style::Error, "Error:"),
+                        case.name
+                    );
+                }
+                executions.push(execution);
+            }
+
+            let expect = input.answered.then_some(case.expect.as_str());
+            let rows = rows(&self.src_names, &executions, expect, checker);
+            let title = if input.answered {
+                case.name.clone()
+            } else {
+                format!("{} (no expected output)", case.name)
+            };
+            println!("{}", palette.paint(Style::Header, title));
+            print!("{}", render_rows(&rows, palette));
+            for (row, execution) in rows.iter().zip(&executions).skip(1) {
+                if let Some(Disagreement::Output(difference)) = &row.disagreement {
+                    println!(
+                        "{}",
+                        palette.paint(
+                            Style::Header,
+                            format!("`{}` (program output) vs `{reference}` (expect):", row.name)
+                        )
+                    );
+                    let mismatch = Mismatch::new(
+                        &execution.stdout,
+                        &executions[0].stdout,
+                        *difference,
+                        checker.mode,
+                        &self.diff,
+                    );
+                    println!("{}", mismatch.render(&self.diff, palette));
+                }
+            }
+            println!();
+            for ((total, row), execution) in totals.iter_mut().zip(&rows).zip(&executions) {
+                total.add(row, execution);
+            }
+        }
+        print!("{}", render_totals(&self.src_names, &totals, palette));
+        Ok(())
+    }
+}
+
+/// Runs a solution on `input`, recording it as not run when it cannot be started or waited for,
+/// so that the other solutions are still compared.
+fn run_solution<P, C>(
+    mut command: P,
+    input: &[u8],
+    time_limit: Option<Duration>,
+    output_limit: usize,
+) -> Execution
+where
+    P: SysCommand<C>,
+    C: SysChild,
+{
+    let start = Instant::now();
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(e) => return Execution::not_run(e),
+    };
+    if let Some(time_limit) = time_limit {
+        child.set_time_limit(time_limit);
+    }
+    match runner::execute(&mut child, input, output_limit, start) {
+        Ok(execution) => execution,
+        Err(e) => {
+            let _ = child.kill();
+            Execution::not_run(e)
+        }
+    }
+}
+
+/// How a solution ended, for comparing solutions that did not exit normally.
+fn ending(execution: &Execution) -> String {
+    match execution.exit_code {
+        _ if execution.error.is_some() => "could not be run".to_string(),
+        _ if execution.timed_out => "timed out".to_string(),
+        _ if execution.output_limit_exceeded => "exceeded the output limit".to_string(),
+        Some(code) => format!("exited with {code}"),
+        None => "was killed by a signal".to_string(),
+    }
+}
+
+/// How a solution differs from the reference on an input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Disagreement {
+    /// They ended differently, as described.
+    Ending(String),
+    /// Both succeeded with different outputs.
+    Output(Difference),
+}
+
+fn disagreement(
+    reference: &Execution,
+    other: &Execution,
+    checker: Checker,
+) -> Option<Disagreement> {
+    if ending(other) != ending(reference) {
+        return Some(Disagreement::Ending(format!(
+            "{} while the reference {}",
+            ending(other),
+            ending(reference)
+        )));
+    }
+    if !reference.succeeded() {
+        return None;
+    }
+    checker
+        .compare(&other.stdout, &reference.stdout)
+        .map(Disagreement::Output)
+}
+
+/// One solution on one input.
+#[derive(Debug)]
+struct Row<'a> {
+    name: &'a str,
+    /// `AC`, `WA`, `RE` or `TLE`, or `OK` for a successful run without an expected output.
+    status: &'static str,
+    time: Duration,
+    memory_kb: Option<u64>,
+    /// `None` for the reference.
+    disagreement: Option<Disagreement>,
+}
+
+fn rows<'a>(
+    names: &'a [String],
+    executions: &[Execution],
+    expect: Option<&str>,
+    checker: Checker,
+) -> Vec<Row<'a>> {
+    names
+        .iter()
+        .zip(executions)
+        .enumerate()
+        .map(|(i, (name, execution))| Row {
+            name,
+            status: match expect {
+                _ if execution.timed_out => "TLE",
+                _ if execution.output_limit_exceeded => "OLE",
+                _ if !execution.succeeded() => "RE",
+                Some(expect) if checker.compare(&execution.stdout, expect).is_some() => "WA",
+                Some(_) => "AC",
+                None => "OK",
+            },
+            time: execution.time,
+            memory_kb: execution.usage.map(|u| u.max_rss_kb),
+            disagreement: if i == 0 {
+                None
+            } else {
+                disagreement(&executions[0], execution, checker)
+            },
+        })
+        .collect()
+}
+
+fn name_width<'a>(names: impl Iterator<Item = &'a str>) -> usize {
+    names.map(|name| name.len()).max().unwrap_or(0)
+}
+
+fn memory(memory_kb: Option<u64>) -> String {
+    memory_kb.map_or("-".to_string(), |kb| format!("{kb} KiB"))
+}
+
+fn render_rows(rows: &[Row], palette: Palette) -> String {
+    let width = name_width(rows.iter().map(|row| row.name));
+    let mut rendered = String::new();
+    for (i, row) in rows.iter().enumerate() {
+        let status_style = match row.status {
+            "AC" | "OK" => Style::Ok,
+            _ => Style::Error,
+        };
+        let note = match &row.disagreement {
+            _ if i == 0 => palette.paint(Style::Dim, "reference"),
+            None => palette.paint(Style::Ok, "same"),
+            Some(Disagreement::Output(_)) => palette.paint(Style::Error, "differs"),
+            Some(Disagreement::Ending(ending)) => {
+                palette.paint(Style::Error, format!("differs: {ending}"))
+            }
+        };
+        let _ = writeln!(
+            rendered,
+            "  {:<width$}  {}  {:>6} ms  {:>10}  {note}",
+            row.name,
+            palette.paint(status_style, format!("{:<3}", row.status)),
+            row.time.as_millis(),
+            memory(row.memory_kb),
+        );
+    }
+    rendered
+}
+
+/// Totals of a solution over every input.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct Total {
+    inputs: usize,
+    agreed: usize,
+    time: Duration,
+    max_memory_kb: Option<u64>,
+}
+
+impl Total {
+    fn add(&mut self, row: &Row, execution: &Execution) {
+        self.inputs += 1;
+        if row.disagreement.is_none() {
+            self.agreed += 1;
+        }
+        self.time += execution.time;
+        self.max_memory_kb = self.max_memory_kb.max(row.memory_kb);
+    }
+}
+
+fn render_totals(names: &[String], totals: &[Total], palette: Palette) -> String {
+    let width = name_width(names.iter().map(String::as_str));
+    let mut rendered = palette.paint(Style::Header, "Summary:");
+    rendered.push('\n');
+    for (i, (name, total)) in names.iter().zip(totals).enumerate() {
+        let agreement = if i == 0 {
+            palette.paint(Style::Dim, "reference")
+        } else {
+            let style = if total.agreed == total.inputs {
+                Style::Ok
+            } else {
+                Style::Error
+            };
+            palette.paint(
+                style,
+                format!("agrees on {} of {}", total.agreed, total.inputs),
+            )
+        };
+        let _ = writeln!(
+            rendered,
+            "  {name:<width$}  {:>6} ms  {:>10}  {agreement}",
+            total.time.as_millis(),
+            memory(total.max_memory_kb),
+        );
+    }
+    rendered
+}
+
+#[cfg(test)]
+mod compare_test {
+    use super::{render_rows, render_totals, rows, run_solution, Disagreement, Total};
+    use crate::checker::CompareMode;
+    use crate::color::Palette;
+    use crate::runner::{Execution, OUTPUT_LIMIT};
+    use crate::syscommand::syscommand_test::{DummyCommand, DummyEffectCommand};
+    use crate::syscommand::Usage;
+    use std::io;
+    use std::time::Duration;
+
+    fn execution(stdout: &str, exit_code: Option<i32>, time_ms: u64) -> Execution {
+        Execution {
+            stdout: stdout.to_string(),
+            exit_code,
+            time: Duration::from_millis(time_ms),
+            usage: Some(Usage {
+                max_rss_kb: 2048,
+                ..Usage::default()
+            }),
+            ..Execution::default()
+        }
+    }
+
+    fn names() -> Vec<String> {
+        ["a", "a2", "a_slow"].map(str::to_string).to_vec()
+    }
+
+    #[test]
+    fn classifies() {
+        let names = names();
+        let executions = [
+            execution("6\n", Some(0), 120),
+            execution("6 \n", Some(0), 8),
+            execution("7\n", Some(0), 900),
+        ];
+        let table = rows(&names, &executions, Some("6\n"), CompareMode::Lines.into());
+        assert_eq!(
+            table.iter().map(|row| row.status).collect::<Vec<_>>(),
+            vec!["AC", "AC", "WA"]
+        );
+        assert_eq!(table[1].disagreement, None);
+        assert!(matches!(
+            table[2].disagreement,
+            Some(Disagreement::Output(_))
+        ));
+
+        let executions = [
+            execution("6\n", Some(0), 120),
+            execution("", Some(101), 8),
+            Execution {
+                timed_out: true,
+                ..execution("", None, 2000)
+            },
+        ];
+        let table = rows(&names, &executions, None, CompareMode::Lines.into());
+        assert_eq!(
+            table.iter().map(|row| row.status).collect::<Vec<_>>(),
+            vec!["OK", "RE", "TLE"]
+        );
+        assert_eq!(
+            table[1].disagreement,
+            Some(Disagreement::Ending(
+                "exited with 101 while the reference exited with 0".to_string()
+            ))
+        );
+        assert_eq!(
+            table[2].disagreement,
+            Some(Disagreement::Ending(
+                "timed out while the reference exited with 0".to_string()
+            ))
+        );
+
+        let both_failed = [execution("1\n", Some(1), 1), execution("2\n", Some(1), 1)];
+        let table = super::rows(&names[..2], &both_failed, None, CompareMode::Lines.into());
+        assert_eq!(table[1].disagreement, None);
+    }
+
+    #[test]
+    fn detects_disagreement() {
+        let run = |stdout: &str| {
+            let command = DummyCommand {
+                exit_code: 0,
+                stdout: stdout.to_string(),
+            };
+            run_solution(command, b"3\n", None, OUTPUT_LIMIT)
+        };
+        let broken = DummyEffectCommand {
+            exit_code: 0,
+            effect: || Err(io::Error::other("no such file")),
+        };
+        let executions = [
+            run("6\n"),
+            run("6\n"),
+            run("7\n"),
+            run_solution(broken, b"3\n", None, OUTPUT_LIMIT),
+        ];
+        let names = ["a", "a2", "a3", "a4"].map(str::to_string);
+        let table = rows(&names, &executions, None, CompareMode::Lines.into());
+        assert_eq!(table[1].disagreement, None);
+        assert!(matches!(
+            table[2].disagreement,
+            Some(Disagreement::Output(_))
+        ));
+        assert_eq!(table[3].status, "RE");
+        assert_eq!(
+            table[3].disagreement,
+            Some(Disagreement::Ending(
+                "could not be run while the reference exited with 0".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn side_by_side() {
+        let names = names();
+        let executions = [
+            execution("6\n", Some(0), 120),
+            execution("6\n", Some(0), 8),
+            execution("", Some(101), 900),
+        ];
+        let table = rows(&names, &executions, Some("6\n"), CompareMode::Lines.into());
+        assert_eq!(
+            render_rows(&table, Palette::PLAIN),
+            "  a       AC      120 ms    2048 KiB  reference
+  a2      AC        8 ms    2048 KiB  same
+  a_slow  RE      900 ms    2048 KiB  differs: exited with 101 while the reference exited with 0
+"
+        );
+
+        let mut totals = vec![Total::default(); 3];
+        for ((total, row), execution) in totals.iter_mut().zip(&table).zip(&executions) {
+            total.add(row, execution);
+        }
+        assert_eq!(
+            render_totals(&names, &totals, Palette::PLAIN),
+            "\
+Summary:
+  a          120 ms    2048 KiB  reference
+  a2           8 ms    2048 KiB  agrees on 1 of 1
+  a_slow     900 ms    2048 KiB  agrees on 0 of 1
+"
+        );
+    }
+}
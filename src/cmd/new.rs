@@ -1,11 +1,14 @@
 use crate::cmd::Run;
-use crate::config::{TEMPLATE, TESTCASE_DIR_NAME};
+use crate::config::{Template, TESTCASE_DIR_NAME};
+use crate::syscommand::{SysChild, SysCommand};
 
 use clap::Parser;
 
-use std::env;
 use std::error::Error;
+use std::fmt::{self, Display};
 use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
 ///  Creates new cargo project
@@ -17,60 +20,426 @@ pub struct New {
     /// files
     #[arg(value_name = "TEXT", default_values = ["a", "b", "c", "d"])]
     pub files: Vec<String>,
+    /// Directory in which the project is created.
+    #[arg(long, value_name = "DIR", default_value = ".")]
+    pub path: PathBuf,
+    /// Use an existing project directory and overwrite its source files with the template.
+    #[arg(long)]
+    pub force: bool,
+    /// Print what would be done without doing it.
+    #[arg(long)]
+    pub dry_run: bool,
+    /// The source file each task starts from.
+    #[arg(long, value_enum, default_value_t = Template::default())]
+    pub template: Template,
 }
 
 impl Run for New {
     fn run(&self) -> Result<(), Box<dyn Error>> {
-        let New { name, files } = self;
+        let project_dir = self.path.join(&self.name);
+        let steps = plan(&project_dir, &self.files, self.force)?;
+        if self.dry_run {
+            for step in &steps {
+                println!("{step}");
+            }
+            return Ok(());
+        }
 
         // $ cargo new name
         let mut cargo_new = Command::new("cargo");
-        cargo_new.arg("new").arg(name);
+        cargo_new.arg("new").arg(&project_dir);
 
         // $ cargo add proconio
         let mut cargo_add = Command::new("cargo");
-        cargo_add.arg("add").arg("proconio");
+        cargo_add
+            .arg("add")
+            .arg("proconio")
+            .current_dir(&project_dir);
 
-        make_cargo_project(name, cargo_new, cargo_add)?;
+        make_cargo_project(&steps, cargo_new, cargo_add, self.template.source())
+    }
+}
 
-        // Remove main.rs
-        fs::remove_file("src/main.rs")?;
+/// A change to the file system made while creating a project.
+#[derive(Debug, PartialEq, Eq)]
+enum Step {
+    CargoNew(PathBuf),
+    CargoAdd(PathBuf),
+    RemoveFile(PathBuf),
+    CreateDir(PathBuf),
+    WriteTemplate(PathBuf),
+}
 
-        // Create bin folder
-        fs::create_dir("src/bin")?;
+impl Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Step::CargoNew(dir) => write!(f, "$ cargo new {}", dir.display()),
+            Step::CargoAdd(dir) => write!(f, "$ cargo add proconio (in {})", dir.display()),
+            Step::RemoveFile(path) => write!(f, "remove {}", path.display()),
+            Step::CreateDir(path) => write!(f, "create {}", path.display()),
+            Step::WriteTemplate(path) => write!(f, "write {}", path.display()),
+        }
+    }
+}
 
-        // Create source files
-        for fname in files {
-            fs::File::create(format!("src/bin/{fname}.rs")).unwrap();
-            fs::write(format!("src/bin/{fname}.rs"), TEMPLATE).unwrap();
+/// Decides the steps to create the project in `project_dir`.
+fn plan(project_dir: &Path, files: &[String], force: bool) -> Result<Vec<Step>, Box<dyn Error>> {
+    let manifest = project_dir.join("Cargo.toml");
+    let mut steps = Vec::new();
+    if project_dir.try_exists()? {
+        if !force {
+            return Err(Box::new(io::Error::new(
+                ErrorKind::AlreadyExists,
+                format!(
+                    "`{}` already exists. Use --force to reuse it.",
+                    project_dir.display()
+                ),
+            )));
         }
+        if !manifest.try_exists()? {
+            return Err(Box::new(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("`{}` is not a cargo project.", project_dir.display()),
+            )));
+        }
+        if !fs::read_to_string(&manifest)?.contains("proconio") {
+            steps.push(Step::CargoAdd(project_dir.to_path_buf()));
+        }
+    } else {
+        steps.push(Step::CargoNew(project_dir.to_path_buf()));
+        steps.push(Step::CargoAdd(project_dir.to_path_buf()));
+    }
+
+    let main_rs = project_dir.join("src/main.rs");
+    if steps.contains(&Step::CargoNew(project_dir.to_path_buf())) || main_rs.try_exists()? {
+        steps.push(Step::RemoveFile(main_rs));
+    }
+    let bin_dir = project_dir.join("src/bin");
+    if !bin_dir.try_exists()? {
+        steps.push(Step::CreateDir(bin_dir.clone()));
+    }
+    for fname in files {
+        steps.push(Step::WriteTemplate(bin_dir.join(format!("{fname}.rs"))));
+    }
+    let testcase_dir = project_dir.join(TESTCASE_DIR_NAME);
+    if !testcase_dir.try_exists()? {
+        steps.push(Step::CreateDir(testcase_dir));
+    }
+    Ok(steps)
+}
 
-        // Create testcase folder
-        fs::create_dir(TESTCASE_DIR_NAME)?;
+#[derive(Debug)]
+struct CommandFailed {
+    command: &'static str,
+    exit_code: i32,
+}
 
-        Ok(())
+impl Display for CommandFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}` exited with {}", self.command, self.exit_code)
     }
 }
 
-use crate::syscommand::{SysChild, SysCommand};
+impl Error for CommandFailed {}
+
+/// How to undo a change to the file system.
+enum Undo {
+    RemoveDir(PathBuf),
+    RemoveFile(PathBuf),
+    Restore(PathBuf, Vec<u8>),
+}
+
+/// Changes made so far, undone in reverse order when a later step fails.
+#[derive(Default)]
+struct Journal {
+    undo: Vec<Undo>,
+}
+
+impl Journal {
+    /// Records the current state of `path` before it is written or removed.
+    fn snapshot(&mut self, path: &Path) -> io::Result<()> {
+        match fs::read(path) {
+            Ok(contents) => self.undo.push(Undo::Restore(path.to_path_buf(), contents)),
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                self.undo.push(Undo::RemoveFile(path.to_path_buf()))
+            }
+            Err(e) => return Err(e),
+        }
+        Ok(())
+    }
+
+    fn rollback(self) {
+        for undo in self.undo.into_iter().rev() {
+            let _ = match undo {
+                Undo::RemoveDir(path) => fs::remove_dir_all(path),
+                Undo::RemoveFile(path) => fs::remove_file(path),
+                Undo::Restore(path, contents) => fs::write(path, contents),
+            };
+        }
+    }
+}
 
-fn make_cargo_project<S, C>(
-    name: &str,
-    mut cargo_new: S,
-    mut cargo_add: S,
+fn make_cargo_project<N, A, C>(
+    steps: &[Step],
+    mut cargo_new: N,
+    mut cargo_add: A,
+    template: &str,
 ) -> Result<(), Box<dyn Error>>
 where
-    S: SysCommand<C>,
+    N: SysCommand<C>,
+    A: SysCommand<C>,
     C: SysChild,
 {
-    // cargo new `name`
-    cargo_new.status()?;
+    let mut journal = Journal::default();
+    let result = steps
+        .iter()
+        .try_for_each(|step| -> Result<(), Box<dyn Error>> {
+            match step {
+                Step::CargoNew(dir) => {
+                    journal.undo.push(Undo::RemoveDir(dir.clone()));
+                    check_status("cargo new", cargo_new.status()?)
+                }
+                Step::CargoAdd(dir) => {
+                    journal.snapshot(&dir.join("Cargo.toml"))?;
+                    journal.snapshot(&dir.join("Cargo.lock"))?;
+                    check_status("cargo add proconio", cargo_add.status()?)
+                }
+                Step::RemoveFile(path) => {
+                    journal.snapshot(path)?;
+                    match fs::remove_file(path) {
+                        Err(e) if e.kind() != ErrorKind::NotFound => Err(Box::new(e)),
+                        _ => Ok(()),
+                    }
+                }
+                Step::CreateDir(path) => {
+                    journal.undo.push(Undo::RemoveDir(path.clone()));
+                    Ok(fs::create_dir(path)?)
+                }
+                Step::WriteTemplate(path) => {
+                    journal.snapshot(path)?;
+                    Ok(fs::write(path, template)?)
+                }
+            }
+        });
+    if result.is_err() {
+        journal.rollback();
+    }
+    result
+}
+
+fn check_status(command: &'static str, exit_code: i32) -> Result<(), Box<dyn Error>> {
+    if exit_code == 0 {
+        Ok(())
+    } else {
+        Err(Box::new(CommandFailed { command, exit_code }))
+    }
+}
+
+#[cfg(test)]
+mod new_test {
+    use super::{make_cargo_project, plan, Step};
+    use crate::config::{Template, TEMPLATE};
+    use crate::project::project_test::TempDir;
+    use crate::syscommand::syscommand_test::DummyEffectCommand;
+    use std::env;
+    use std::fs;
+    use std::io::Result;
+    use std::path::Path;
+
+    /// Creates what `cargo new` creates.
+    fn cargo_new(dir: &Path, exit_code: i32) -> DummyEffectCommand<impl FnMut() -> Result<()>> {
+        let dir = dir.to_path_buf();
+        DummyEffectCommand {
+            exit_code,
+            effect: move || {
+                fs::create_dir_all(dir.join("src"))?;
+                fs::write(dir.join("Cargo.toml"), "[dependencies]\n")?;
+                fs::write(dir.join("src/main.rs"), "fn main() {}\n")
+            },
+        }
+    }
+
+    /// Adds proconio to the manifest like `cargo add proconio` does.
+    fn cargo_add(dir: &Path, exit_code: i32) -> DummyEffectCommand<impl FnMut() -> Result<()>> {
+        let manifest = dir.join("Cargo.toml");
+        DummyEffectCommand {
+            exit_code,
+            effect: move || fs::write(&manifest, "[dependencies]\nproconio = \"0.4\"\n"),
+        }
+    }
+
+    fn files() -> Vec<String> {
+        vec!["a".to_string(), "b".to_string()]
+    }
 
-    // cd name
-    env::set_current_dir(name).unwrap();
+    #[test]
+    fn creates_project() {
+        let tmp = TempDir::new("new-creates");
+        let cwd = env::current_dir().unwrap();
+        let project = tmp.0.join("abc001");
+        let steps = plan(&project, &files(), false).unwrap();
+        make_cargo_project(
+            &steps,
+            cargo_new(&project, 0),
+            cargo_add(&project, 0),
+            TEMPLATE,
+        )
+        .unwrap();
 
-    // cargo add proconio
-    cargo_add.status()?;
+        assert_eq!(env::current_dir().unwrap(), cwd);
+        assert!(!project.join("src/main.rs").exists());
+        assert_eq!(
+            fs::read_to_string(project.join("src/bin/a.rs")).unwrap(),
+            TEMPLATE
+        );
+        assert_eq!(
+            fs::read_to_string(project.join("src/bin/b.rs")).unwrap(),
+            TEMPLATE
+        );
+        assert!(project.join("testcase").is_dir());
+    }
 
-    Ok(())
+    #[test]
+    fn creates_project_from_template() {
+        let tmp = TempDir::new("new-template");
+        let project = tmp.0.join("abc001");
+        let steps = plan(&project, &files(), false).unwrap();
+        let template = Template::LargeStack.source();
+        make_cargo_project(
+            &steps,
+            cargo_new(&project, 0),
+            cargo_add(&project, 0),
+            template,
+        )
+        .unwrap();
+        let source = fs::read_to_string(project.join("src/bin/a.rs")).unwrap();
+        assert!(source.contains(".stack_size(1 << 30)"));
+        assert!(source.contains("fn solve() {\n    input! {"));
+    }
+
+    #[test]
+    fn refuses_existing_directory() {
+        let tmp = TempDir::new("new-existing");
+        let project = tmp.0.join("abc001");
+        fs::create_dir(&project).unwrap();
+        assert!(plan(&project, &files(), false).is_err());
+    }
+
+    #[test]
+    fn rolls_back_when_cargo_add_fails() {
+        let tmp = TempDir::new("new-rollback-add");
+        let project = tmp.0.join("abc001");
+        let steps = plan(&project, &files(), false).unwrap();
+        let result = make_cargo_project(
+            &steps,
+            cargo_new(&project, 0),
+            cargo_add(&project, 101),
+            TEMPLATE,
+        );
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "`cargo add proconio` exited with 101"
+        );
+        assert!(!project.exists());
+    }
+
+    #[test]
+    fn rolls_back_when_cargo_new_fails() {
+        let tmp = TempDir::new("new-rollback-new");
+        let project = tmp.0.join("abc001");
+        let steps = plan(&project, &files(), false).unwrap();
+        let result = make_cargo_project(
+            &steps,
+            cargo_new(&project, 101),
+            cargo_add(&project, 0),
+            TEMPLATE,
+        );
+        assert!(result.is_err());
+        assert!(!project.exists());
+    }
+
+    #[test]
+    fn force_reuses_project() {
+        let tmp = TempDir::new("new-force");
+        let project = tmp.0.join("abc001");
+        fs::create_dir_all(project.join("src/bin")).unwrap();
+        fs::write(
+            project.join("Cargo.toml"),
+            "[dependencies]\nproconio = \"0.4\"\n",
+        )
+        .unwrap();
+        fs::write(project.join("src/bin/a.rs"), "// solved\n").unwrap();
+
+        let steps = plan(&project, &files(), true).unwrap();
+        assert_eq!(
+            steps,
+            vec![
+                Step::WriteTemplate(project.join("src/bin/a.rs")),
+                Step::WriteTemplate(project.join("src/bin/b.rs")),
+                Step::CreateDir(project.join("testcase")),
+            ]
+        );
+        make_cargo_project(
+            &steps,
+            cargo_new(&project, 0),
+            cargo_add(&project, 0),
+            TEMPLATE,
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(project.join("src/bin/a.rs")).unwrap(),
+            TEMPLATE
+        );
+        assert!(project.join("testcase").is_dir());
+    }
+
+    #[test]
+    fn force_restores_files_on_failure() {
+        let tmp = TempDir::new("new-force-rollback");
+        let project = tmp.0.join("abc001");
+        fs::create_dir_all(project.join("src/bin")).unwrap();
+        fs::write(project.join("Cargo.toml"), "[dependencies]\n").unwrap();
+        fs::write(project.join("src/bin/a.rs"), "// solved\n").unwrap();
+
+        // `cargo add` fails after the source files would have been written.
+        let steps = plan(&project, &files(), true).unwrap();
+        assert_eq!(steps[0], Step::CargoAdd(project.clone()));
+        let result = make_cargo_project(
+            &steps,
+            cargo_new(&project, 0),
+            cargo_add(&project, 1),
+            TEMPLATE,
+        );
+        assert!(result.is_err());
+        assert_eq!(
+            fs::read_to_string(project.join("src/bin/a.rs")).unwrap(),
+            "// solved\n"
+        );
+        assert_eq!(
+            fs::read_to_string(project.join("Cargo.toml")).unwrap(),
+            "[dependencies]\n"
+        );
+    }
+
+    #[test]
+    fn dry_run_plan() {
+        let tmp = TempDir::new("new-dry-run");
+        let project = tmp.0.join("abc001");
+        let steps = plan(&project, &files(), false).unwrap();
+        let lines: Vec<String> = steps.iter().map(ToString::to_string).collect();
+        let p = project.display();
+        assert_eq!(
+            lines,
+            vec![
+                format!("$ cargo new {p}"),
+                format!("$ cargo add proconio (in {p})"),
+                format!("remove {p}/src/main.rs"),
+                format!("create {p}/src/bin"),
+                format!("write {p}/src/bin/a.rs"),
+                format!("write {p}/src/bin/b.rs"),
+                format!("create {p}/testcase"),
+            ]
+        );
+        assert!(!project.exists());
+    }
 }
@@ -0,0 +1,258 @@
+use crate::cases::{self, Case, Layout, Manifest, Shape, CASES_FILE_NAME};
+use crate::cmd::Run;
+use crate::config::TESTCASE_DIR_NAME;
+use crate::project;
+
+use clap::{Parser, Subcommand};
+
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::Path;
+
+/// Copies sample cases between the doc comment of a solution and its testcase directory, and between layouts.
+#[derive(Parser, Debug)]
+pub struct Samples {
+    #[command(subcommand)]
+    action: Action,
+}
+
+#[derive(Subcommand, Debug)]
+enum Action {
+    /// Writes the samples in the doc comment of `src/bin/<TASK>.rs` to `testcase/<TASK>/{in,out}/N.txt`.
+    Extract { task: String },
+    /// Replaces the samples in the doc comment of `src/bin/<TASK>.rs` with the cases of `testcase/<TASK>`.
+    Embed { task: String },
+    /// Converts `testcase/<TASK>/{in,out}` into `testcase/<TASK>/cases.txt`.
+    Pack { task: String },
+    /// Converts `testcase/<TASK>/cases.txt` into `testcase/<TASK>/{in,out}/<NAME>.txt`.
+    Unpack { task: String },
+    /// Writes the INDEX-th case of the multi-testcase input CASE to `testcase/<TASK>/{in,out}/<CASE>-<INDEX>.txt`.
+    Isolate {
+        task: String,
+        case: String,
+        /// 1-based.
+        index: usize,
+        /// Shape of each case, overriding `multi` in `cases.toml`.
+        #[arg(long, value_name = "SHAPE")]
+        multi: Option<Shape>,
+        /// Shape of the output of each case, overriding `multi-output` in `cases.toml`.
+        #[arg(long, value_name = "SHAPE")]
+        multi_output: Option<Shape>,
+    },
+}
+
+impl Run for Samples {
+    fn run(&self) -> Result<(), Box<dyn Error>> {
+        let root = project::find_root(&env::current_dir()?)?;
+        match &self.action {
+            Action::Extract { task } => {
+                let written = extract(&root, task)?;
+                println!("{written} samples written to {TESTCASE_DIR_NAME}/{task}");
+            }
+            Action::Embed { task } => {
+                let embedded = embed(&root, task)?;
+                println!("{embedded} samples embedded in src/bin/{task}.rs");
+            }
+            Action::Pack { task } => {
+                let packed = pack(&root.join(TESTCASE_DIR_NAME).join(task))?;
+                println!("{packed} cases written to {TESTCASE_DIR_NAME}/{task}/{CASES_FILE_NAME}");
+            }
+            Action::Unpack { task } => {
+                let unpacked = unpack(&root.join(TESTCASE_DIR_NAME).join(task))?;
+                println!("{unpacked} cases written to {TESTCASE_DIR_NAME}/{task}/{{in,out}}");
+            }
+            Action::Isolate {
+                task,
+                case,
+                index,
+                multi,
+                multi_output,
+            } => {
+                let dir = root.join(TESTCASE_DIR_NAME).join(task);
+                let name = isolate(&dir, case, *index, multi.as_ref(), multi_output.as_ref())?;
+                println!("Case {index} of `{case}` written to {TESTCASE_DIR_NAME}/{task}/{{in,out}}/{name}");
+            }
+        }
+        Ok(())
+    }
+}
+
+fn extract(root: &Path, task: &str) -> Result<usize, Box<dyn Error>> {
+    let src_path = root.join(format!("src/bin/{task}.rs"));
+    let source = fs::read_to_string(&src_path)?;
+    let samples: Vec<Case> = cases::parse_inline(&source, &src_path.display().to_string())?
+        .into_iter()
+        .enumerate()
+        .map(|(i, case)| Case {
+            name: format!("{}.txt", i + 1),
+            ..case
+        })
+        .collect();
+    cases::write_dir(&root.join(TESTCASE_DIR_NAME).join(task), &samples)?;
+    Ok(samples.len())
+}
+
+fn embed(root: &Path, task: &str) -> Result<usize, Box<dyn Error>> {
+    let src_path = root.join(format!("src/bin/{task}.rs"));
+    let source = fs::read_to_string(&src_path)?;
+    let testcase_dir = root.join(TESTCASE_DIR_NAME).join(task);
+    let samples = cases::scan_dir(&testcase_dir, Layout::from_dir(&testcase_dir)?)?.cases;
+    fs::write(&src_path, cases::embed_inline(&source, &samples))?;
+    Ok(samples.len())
+}
+
+fn pack(testcase_dir: &Path) -> Result<usize, Box<dyn Error>> {
+    let cases_file = testcase_dir.join(CASES_FILE_NAME);
+    if cases_file.try_exists()? {
+        return Err(Box::new(io::Error::new(
+            ErrorKind::AlreadyExists,
+            format!("`{}` already exists.", cases_file.display()),
+        )));
+    }
+    let cases = Layout::InOut.scan(testcase_dir)?.cases;
+    fs::write(cases_file, cases::format_cases_file(&cases))?;
+    Ok(cases.len())
+}
+
+fn unpack(testcase_dir: &Path) -> Result<usize, Box<dyn Error>> {
+    let cases_file = testcase_dir.join(CASES_FILE_NAME);
+    let text = fs::read_to_string(&cases_file)?;
+    let cases: Vec<Case> = cases::parse_cases_file(&text, &cases_file.display().to_string())?
+        .into_iter()
+        .map(|case| Case {
+            name: format!("{}.txt", case.name),
+            ..case
+        })
+        .collect();
+    cases::write_dir(testcase_dir, &cases)?;
+    Ok(cases.len())
+}
+
+fn isolate(
+    testcase_dir: &Path,
+    name: &str,
+    index: usize,
+    multi: Option<&Shape>,
+    multi_output: Option<&Shape>,
+) -> Result<String, Box<dyn Error>> {
+    let cases = cases::scan_dir(testcase_dir, Layout::from_dir(testcase_dir)?)?.cases;
+    let case = cases
+        .iter()
+        .find(|case| case.name == name || case.stem() == name)
+        .ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::NotFound,
+                format!("No case `{name}` in `{}`.", testcase_dir.display()),
+            )
+        })?;
+    let options = Manifest::from_dir(testcase_dir)?
+        .unwrap_or_default()
+        .options(case);
+    let multi = options.multi(multi, multi_output).ok_or_else(|| {
+        io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("How `{name}` holds several cases is unknown. Please pass `--multi` or set `multi` in cases.toml."),
+        )
+    })?;
+    let isolated_name = format!("{}-{index}.txt", case.stem());
+    let isolated = index
+        .checked_sub(1)
+        .ok_or_else(|| "Cases are numbered from 1.".to_string())
+        .and_then(|i| multi.extract(case, i, &isolated_name))
+        .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+    cases::write_dir(testcase_dir, &[isolated])?;
+    Ok(isolated_name)
+}
+
+#[cfg(test)]
+mod samples_test {
+    use super::{embed, extract, isolate, pack, unpack};
+    use crate::project::project_test::TempDir;
+    use std::fs;
+
+    const SOURCE: &str = "\
+//! ```input
+//! 3
+//! ```
+//! ```output
+//! 6
+//! ```
+fn main() {}
+";
+
+    #[test]
+    fn extract_and_embed() {
+        let tmp = TempDir::new("samples");
+        fs::create_dir_all(tmp.0.join("src/bin")).unwrap();
+        fs::write(tmp.0.join("src/bin/a.rs"), SOURCE).unwrap();
+
+        assert_eq!(extract(&tmp.0, "a").unwrap(), 1);
+        assert_eq!(
+            fs::read_to_string(tmp.0.join("testcase/a/in/1.txt")).unwrap(),
+            "3\n"
+        );
+        assert_eq!(
+            fs::read_to_string(tmp.0.join("testcase/a/out/1.txt")).unwrap(),
+            "6\n"
+        );
+
+        fs::write(tmp.0.join("testcase/a/in/2.txt"), "1\n").unwrap();
+        fs::write(tmp.0.join("testcase/a/out/2.txt"), "2\n").unwrap();
+        assert_eq!(embed(&tmp.0, "a").unwrap(), 2);
+        let source = fs::read_to_string(tmp.0.join("src/bin/a.rs")).unwrap();
+        assert!(source.starts_with(SOURCE.strip_suffix("fn main() {}\n").unwrap()));
+        assert!(source.ends_with(
+            "//! ```input\n//! 1\n//! ```\n//! ```output\n//! 2\n//! ```\nfn main() {}\n"
+        ));
+    }
+
+    #[test]
+    fn pack_and_unpack() {
+        let tmp = TempDir::new("samples-pack");
+        let dir = tmp.0.join("testcase/a");
+        fs::create_dir_all(dir.join("in")).unwrap();
+        fs::create_dir_all(dir.join("out")).unwrap();
+        fs::write(dir.join("in/1.txt"), "3\n").unwrap();
+        fs::write(dir.join("out/1.txt"), "6\n").unwrap();
+
+        assert_eq!(pack(&dir).unwrap(), 1);
+        assert_eq!(
+            fs::read_to_string(dir.join("cases.txt")).unwrap(),
+            "=== input 1\n3\n=== output 1\n6\n"
+        );
+        assert!(pack(&dir).is_err());
+
+        fs::remove_dir_all(dir.join("in")).unwrap();
+        fs::remove_dir_all(dir.join("out")).unwrap();
+        assert_eq!(unpack(&dir).unwrap(), 1);
+        assert_eq!(fs::read_to_string(dir.join("in/1.txt")).unwrap(), "3\n");
+        assert_eq!(fs::read_to_string(dir.join("out/1.txt")).unwrap(), "6\n");
+    }
+
+    #[test]
+    fn isolate_case() {
+        let tmp = TempDir::new("samples-isolate");
+        let dir = tmp.0.join("testcase/a");
+        fs::create_dir_all(dir.join("in")).unwrap();
+        fs::create_dir_all(dir.join("out")).unwrap();
+        fs::write(dir.join("in/1.txt"), "2\n1\n5\n2\n3 4\n").unwrap();
+        fs::write(dir.join("out/1.txt"), "5\n7\n").unwrap();
+
+        assert!(isolate(&dir, "1", 2, None, None).is_err());
+        fs::write(dir.join("cases.toml"), "multi = \"n; [1]\"\n").unwrap();
+        assert_eq!(isolate(&dir, "1", 2, None, None).unwrap(), "1-2.txt");
+        assert_eq!(
+            fs::read_to_string(dir.join("in/1-2.txt")).unwrap(),
+            "1\n2\n3 4\n"
+        );
+        assert_eq!(fs::read_to_string(dir.join("out/1-2.txt")).unwrap(), "7\n");
+
+        let one_line: super::Shape = "_".parse().unwrap();
+        assert!(isolate(&dir, "1", 2, Some(&one_line), None).is_err());
+        assert!(isolate(&dir, "1", 3, None, None).is_err());
+        assert!(isolate(&dir, "1", 0, None, None).is_err());
+        assert!(isolate(&dir, "2", 1, None, None).is_err());
+    }
+}
@@ -0,0 +1,342 @@
+use crate::cmd::Run;
+use crate::color::{ColorChoice, Palette, Style};
+use crate::project;
+use crate::runner::{self, Execution, Profile};
+use crate::sandbox::{Sandbox, DEFAULT_CPU_LIMIT};
+use crate::scale::Fit;
+use crate::syscommand::{SysChild, SysCommand};
+
+use clap::Parser;
+
+use std::env;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter, Write as _};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+#[derive(Parser, Debug)]
+/// Time a solution on generated inputs of growing size and estimate its complexity.
+pub struct Scale {
+    /// The source file name to execute.
+    src_name: String,
+    /// The generator: a source file name of the project, or the path of a program.
+    /// It gets N as its argument and prints an input of that size.
+    #[arg(long, value_name = "GEN")]
+    gen: String,
+    /// Seconds after which the generator is killed.
+    #[arg(long, default_value_t = 10.0)]
+    gen_time_limit: f64,
+    /// The maximum N of the problem, at which the time is projected.
+    #[arg(long)]
+    max_n: u64,
+    /// The smallest N to measure.
+    #[arg(long, default_value_t = 1000, value_parser = clap::value_parser!(u64).range(1..))]
+    from: u64,
+    /// The largest N to measure, the maximum N by default.
+    #[arg(long)]
+    to: Option<u64>,
+    /// How much N grows from one size to the next.
+    #[arg(long, default_value_t = 2.0)]
+    factor: f64,
+    /// Runs per size, of which the median time is taken.
+    #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(u32).range(1..))]
+    runs: u32,
+    /// The time limit of the problem in seconds; sizes stop growing once a run exceeds it.
+    #[arg(long, default_value_t = 2.0)]
+    time_limit: f64,
+    /// Build with the dev profile instead of the release profile used by the judge.
+    #[arg(long)]
+    debug: bool,
+    /// When to use colors.
+    #[arg(long, value_enum, default_value_t = ColorChoice::default())]
+    color: ColorChoice,
+    /// Stack size of the main thread in MiB, for deep recursion; the inherited limit by default.
+    #[arg(long, value_name = "MIB")]
+    stack_size: Option<u64>,
+    /// Run the solution and the generator without network, writing only to a temp dir, with limited processes and CPU time.
+    #[arg(long)]
+    sandbox: bool,
+}
+
+#[derive(Debug)]
+enum ScaleError {
+    Factor(f64),
+    Generator { n: u64, execution: Execution },
+    Solution { n: u64, execution: Execution },
+    TooFewSizes(usize),
+}
+
+impl Display for ScaleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let ending = |execution: &Execution| match execution.exit_code {
+            _ if execution.timed_out => format!("was killed after {:.1?}", execution.time),
+            _ if execution.output_limit_exceeded => "exceeded the output limit".to_string(),
+            Some(code) => format!("exited with {code}"),
+            None => "was killed".to_string(),
+        };
+        match self {
+            ScaleError::Factor(factor) => {
+                write!(f, "The growth factor must be greater than 1, not {factor}")
+            }
+            ScaleError::Generator { n, execution } => write!(
+                f,
+                "The generator {} for N = {n}\n{}",
+                ending(execution),
+                execution.stderr
+            ),
+            ScaleError::Solution { n, execution } => write!(
+                f,
+                "The solution {} on the input of N = {n}\n{}",
+                ending(execution),
+                execution.stderr
+            ),
+            ScaleError::TooFewSizes(count) => write!(
+                f,
+                "Only {count} sizes could be measured, at least 3 are needed; lower `--from` or `--factor`"
+            ),
+        }
+    }
+}
+
+impl Error for ScaleError {}
+
+impl Run for Scale {
+    fn run(&self) -> Result<(), Box<dyn Error>> {
+        if self.factor.is_nan() || self.factor <= 1.0 {
+            return Err(Box::new(ScaleError::Factor(self.factor)));
+        }
+        let palette = self.color.palette();
+        let root = project::find_root(&env::current_dir()?)?;
+        let profile = if self.debug {
+            Profile::Dev
+        } else {
+            Profile::Release
+        };
+        let gen_is_bin = root.join(format!("src/bin/{}.rs", self.gen)).is_file();
+        let mut bins = vec![self.src_name.clone()];
+        if gen_is_bin {
+            bins.push(self.gen.clone());
+        }
+        runner::build(&bins, profile)?;
+        let binary = runner::binary_path(&root, &self.src_name, profile);
+        let generator = if gen_is_bin {
+            runner::binary_path(&root, &self.gen, profile)
+        } else {
+            Path::new(&self.gen).to_path_buf()
+        };
+
+        let sandbox = self.sandbox.then(|| Sandbox::new(palette)).transpose()?;
+
+        let time_limit = Duration::from_secs_f64(self.time_limit);
+        let gen_time_limit = Duration::from_secs_f64(self.gen_time_limit);
+        let mut samples = Vec::new();
+        // The size whose median run was killed, and the time it was killed after.
+        let mut killed = None;
+        for n in sizes(self.from, self.to.unwrap_or(self.max_n), self.factor) {
+            eprintln!("{} N = {n}", palette.paint(Style::Dim, "Running"));
+            let input = generate(&generator, n, gen_time_limit, sandbox.as_ref())?;
+            let mut times = Vec::with_capacity(self.runs as usize);
+            for _ in 0..self.runs {
+                let mut command = runner::binary(&binary);
+                command.stderr(Stdio::null());
+                if let Some(sandbox) = &sandbox {
+                    sandbox.apply(&mut command, Some(time_limit * 2));
+                }
+                if let Some(mib) = self.stack_size {
+                    runner::set_stack_size(&mut command, mib);
+                }
+                let start = Instant::now();
+                let mut child = SysCommand::spawn(&mut command)?;
+                // Well past the limit, the size is known to be too slow.
+                child.set_time_limit(time_limit * 2);
+                let execution =
+                    runner::execute(&mut child, input.as_bytes(), runner::OUTPUT_LIMIT, start);
+                if execution.is_err() {
+                    let _ = child.kill();
+                }
+                let execution = execution?;
+                if !execution.timed_out && execution.exit_code != Some(0) {
+                    return Err(Box::new(ScaleError::Solution { n, execution }));
+                }
+                times.push((execution.time, execution.timed_out));
+            }
+            times.sort();
+            let (median, timed_out) = times[times.len() / 2];
+            let ms = median.as_secs_f64() * 1000.0;
+            if timed_out {
+                // Only a lower bound of the time, which would flatten the fit.
+                killed = Some((n, ms));
+                break;
+            }
+            samples.push((n, ms));
+            if median > time_limit {
+                break;
+            }
+        }
+        if samples.len() < 3 {
+            return Err(Box::new(ScaleError::TooFewSizes(samples.len())));
+        }
+
+        print!(
+            "{}",
+            render(
+                &samples,
+                killed,
+                self.max_n,
+                self.time_limit * 1000.0,
+                palette
+            )
+        );
+        Ok(())
+    }
+}
+
+/// `from`, growing geometrically by `factor` up to `to`, which is always the last size.
+fn sizes(from: u64, to: u64, factor: f64) -> Vec<u64> {
+    let mut sizes = Vec::new();
+    let mut n = from;
+    while n < to {
+        sizes.push(n);
+        n = ((n as f64 * factor).round() as u64).max(n + 1);
+    }
+    sizes.push(to);
+    sizes
+}
+
+fn generate(
+    generator: &Path,
+    n: u64,
+    time_limit: Duration,
+    sandbox: Option<&Sandbox>,
+) -> Result<String, Box<dyn Error>> {
+    let mut command = Command::new(generator);
+    command
+        .arg(n.to_string())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(sandbox) = sandbox {
+        sandbox.apply(&mut command, Some(DEFAULT_CPU_LIMIT));
+    }
+    let mut child = SysCommand::spawn(&mut command)?;
+    child.set_time_limit(time_limit);
+    let execution = runner::execute(&mut child, b"", runner::OUTPUT_LIMIT, Instant::now());
+    if execution.is_err() {
+        let _ = child.kill();
+    }
+    let execution = execution?;
+    if !execution.succeeded() {
+        return Err(Box::new(ScaleError::Generator { n, execution }));
+    }
+    Ok(execution.stdout)
+}
+
+/// The measured times, the fits from best to worst and the projection of the best one.
+///
+/// A size whose runs were `killed` is shown with the time they were killed after, as a lower
+/// bound, and left out of the fits.
+fn render(
+    samples: &[(u64, f64)],
+    killed: Option<(u64, f64)>,
+    max_n: u64,
+    time_limit_ms: f64,
+    palette: Palette,
+) -> String {
+    let mut rendered = String::new();
+    let _ = writeln!(
+        rendered,
+        "{}",
+        palette.paint(Style::Header, format!("  {:>10}  {:>10}", "N", "time ms"))
+    );
+    for (n, ms) in samples {
+        let _ = writeln!(rendered, "  {n:>10}  {ms:>10.1}");
+    }
+    if let Some((n, ms)) = killed {
+        let _ = writeln!(
+            rendered,
+            "  {n:>10}  {:>10}  {}",
+            format!(">{ms:.1}"),
+            palette.paint(Style::Dim, "killed, left out of the fit")
+        );
+    }
+    let points: Vec<(f64, f64)> = samples.iter().map(|&(n, ms)| (n as f64, ms)).collect();
+    let fits = Fit::all(&points);
+    let _ = writeln!(
+        rendered,
+        "{}",
+        palette.paint(Style::Header, format!("  {:<10}  {:>10}", "fit", "error"))
+    );
+    for (i, fit) in fits.iter().enumerate() {
+        let line = format!("  {:<10}  {:>9.1}%", fit.complexity, fit.error * 100.0);
+        if i == 0 {
+            let _ = writeln!(rendered, "{}", palette.paint(Style::Highlight, line));
+        } else {
+            let _ = writeln!(rendered, "{line}");
+        }
+    }
+    let Some(best) = fits.first() else {
+        return rendered;
+    };
+    let projected = best.project(max_n as f64);
+    let verdict = if projected <= time_limit_ms {
+        palette.paint(Style::Ok, "within")
+    } else {
+        palette.paint(Style::Error, "over")
+    };
+    let _ = writeln!(
+        rendered,
+        "Best fit {}: {:.1} ms projected at N = {max_n}, {verdict} the time limit of {:.0} ms",
+        best.complexity, projected, time_limit_ms
+    );
+    rendered
+}
+
+#[cfg(test)]
+mod scale_cmd_test {
+    use super::{render, sizes};
+    use crate::color::Palette;
+
+    #[test]
+    fn grows_geometrically() {
+        assert_eq!(sizes(1000, 10000, 2.0), vec![1000, 2000, 4000, 8000, 10000]);
+        assert_eq!(sizes(1, 3, 1.1), vec![1, 2, 3]);
+        assert_eq!(sizes(5000, 1000, 2.0), vec![1000]);
+    }
+
+    #[test]
+    fn renders() {
+        let samples = [(1000, 2.0), (2000, 5.0), (4000, 17.0), (8000, 65.0)];
+        let rendered = render(&samples, None, 200_000, 2000.0, Palette::PLAIN);
+        let mut lines = rendered.lines();
+        assert_eq!(lines.next(), Some("           N     time ms"));
+        assert_eq!(lines.next(), Some("        1000         2.0"));
+        assert_eq!(lines.nth(3), Some("  fit              error"));
+        assert!(lines.next().unwrap().starts_with("  O(N²)        "));
+        assert_eq!(
+            rendered.lines().last(),
+            Some("Best fit O(N²): 40001.0 ms projected at N = 200000, over the time limit of 2000 ms")
+        );
+    }
+
+    #[test]
+    fn leaves_killed_sizes_out() {
+        let samples = [(1000, 2.0), (2000, 5.0), (4000, 17.0), (8000, 65.0)];
+        let rendered = render(
+            &samples,
+            Some((16000, 4000.2)),
+            200_000,
+            2000.0,
+            Palette::PLAIN,
+        );
+        assert!(rendered.contains(
+            "        8000        65.0\n       16000     >4000.2  killed, left out of the fit\n"
+        ));
+        assert_eq!(
+            rendered.lines().last(),
+            render(&samples, None, 200_000, 2000.0, Palette::PLAIN)
+                .lines()
+                .last()
+        );
+    }
+}
@@ -1,17 +1,27 @@
+use crate::calibrate::Calibration;
+use crate::cases::{
+    self, Case, LastFailed, Layout, Manifest, Multi, Pattern, Scan, Shape, MANIFEST_FILE_NAME,
+};
+use crate::checker::{Checker, CompareMode};
 use crate::cmd::Run;
+use crate::color::{ColorChoice, Palette, Style};
+use crate::compile::{self, CompileError};
 use crate::config::TESTCASE_DIR_NAME;
-use crate::data::CircularBuffer;
-use crate::syscommand::{SysChild, SysCommand};
+use crate::diff::{DiffOptions, Mismatch};
+use crate::project;
+use crate::report::{CaseReport, DiffLocation, Format, Report, SubCase, Verdict};
+use crate::runner::{self, Execution};
+use crate::sandbox::{self, Sandbox};
+use crate::syscommand::{SysChild, SysCommand, Usage};
 
 use clap::Parser;
 
+use std::env;
 use std::error::Error;
-use std::fmt::{Debug, Display};
-use std::fs::File;
+use std::fmt::{Debug, Display, Write as _};
 use std::io::prelude::*;
-use std::io::{self, BufReader, ErrorKind};
-use std::path::{Path, PathBuf};
-use std::process::{Command, Stdio};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
 #[derive(Parser, Debug)]
 /// Run testcase.
@@ -21,128 +31,632 @@ pub struct Testcase {
     /// If you want to execute only specific test cases.
     #[arg(short, long)]
     in_files: Option<Vec<String>>,
+    /// Run only the cases whose name matches a glob, or a regex prefixed with `re:`.
+    #[arg(long, value_name = "PATTERN", num_args = 1..)]
+    filter: Vec<Pattern>,
+    /// Skip the cases whose name matches a glob, or a regex prefixed with `re:`.
+    #[arg(long, value_name = "PATTERN", num_args = 1..)]
+    exclude: Vec<Pattern>,
+    /// Run only the cases that failed last time, or every case if none did.
+    #[arg(long)]
+    last_failed: bool,
+    /// Stop at the first case that is not accepted.
+    #[arg(long)]
+    fail_fast: bool,
     /// If the directory containing the test cases differs from the source file name.
     #[arg(short, long)]
     dir_name: Option<String>,
+    #[command(flatten)]
+    diff: DiffOptions,
+    /// When to use colors.
+    #[arg(long, value_enum, default_value_t = ColorChoice::default())]
+    color: ColorChoice,
+    /// Output format of the results.
+    #[arg(long, value_enum, default_value_t = Format::default())]
+    format: Format,
+    /// How to compare the output, overriding the `compare` file of the testcase directory.
+    #[arg(long, value_enum)]
+    compare: Option<CompareMode>,
+    /// How the testcase directory is laid out, overriding its `layout` file and detection.
+    #[arg(long, value_enum)]
+    layout: Option<Layout>,
+    /// List the cases found and the files skipped, without running them.
+    #[arg(long)]
+    list: bool,
+    /// Shape of each case of an input that starts with the number of cases, such as `n m; [m]`.
+    #[arg(long, value_name = "SHAPE")]
+    multi: Option<Shape>,
+    /// Shape of the output of each case of a multi-testcase input; one line by default.
+    #[arg(long, value_name = "SHAPE")]
+    multi_output: Option<Shape>,
+    /// Output in MiB after which a program is killed, overriding `output-limit` in `cases.toml`.
+    #[arg(long, value_name = "MIB")]
+    output_limit: Option<f64>,
+    /// Run the solution without network, writing only to a temp dir, with limited processes and CPU time.
+    #[arg(long)]
+    sandbox: bool,
+    /// Stack size of the main thread in MiB, for deep recursion; the inherited limit by default.
+    #[arg(long, value_name = "MIB")]
+    stack_size: Option<u64>,
+    /// Judge the time limit on user plus system CPU time instead of wall-clock time.
+    /// The program is still killed once it has run for twice the limit.
+    #[arg(long)]
+    cpu_time: bool,
+    /// Scale the time limits and the reported times to the judge with the speed factor saved by
+    /// `atcoder calibrate`.
+    #[arg(long)]
+    calibrated: bool,
+    /// When a case fails, report whether and where it overflows; if the dev profile turns overflow
+    /// checks off, the case is run again on a rebuild with overflow checks and debug assertions.
+    #[arg(long)]
+    check_overflow: bool,
 }
 
-fn check_file_existance(path: &Path) -> Result<(), Box<dyn Error>> {
-    match path.try_exists() {
-        Ok(true) => Ok(()),
-        Ok(false) => Err(Box::new(io::Error::new(
-            ErrorKind::NotFound,
-            format!("Please create `{:?}`", path),
-        ))),
-        Err(e) => Err(Box::new(e)),
+#[derive(Debug)]
+struct NotCalibrated;
+
+impl Display for NotCalibrated {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "This machine has not been calibrated. Please run `atcoder calibrate` first."
+        )
     }
 }
 
-fn diff_position(lhs: &str, rhs: &str) -> isize {
-    let (mut s1, mut s2, size) = {
-        if lhs.len() >= rhs.len() {
-            (lhs.chars(), rhs.chars(), lhs.len())
-        } else {
-            (rhs.chars(), lhs.chars(), rhs.len())
-        }
-    };
+impl Error for NotCalibrated {}
 
-    let mut pos = -1;
+/// How times are measured and compared with the time limit.
+#[derive(Clone, Copy, Debug, Default)]
+struct Clock {
+    cpu_time: bool,
+    calibration: Option<Calibration>,
+}
 
-    for i in 0..size {
-        let c1 = s1.next();
-        let c2 = s2.next();
-        if c1 != c2 {
-            pos = i as isize;
-            break;
+impl Clock {
+    /// The time on this machine that matches `judge` on the judge.
+    fn local(&self, judge: Duration) -> Duration {
+        self.calibration.map_or(judge, |c| c.local_time(judge))
+    }
+
+    /// The time on the judge that matches `local` on this machine.
+    fn judge(&self, local: Duration) -> Duration {
+        self.calibration.map_or(local, |c| c.judge_time(local))
+    }
+
+    /// When to kill a program with the time limit `limit`; a program waiting for nothing uses
+    /// no CPU time, so it must be killed after some wall-clock time as well.
+    fn kill_after(&self, limit: Duration) -> Duration {
+        let local = self.local(limit);
+        if self.cpu_time {
+            local * 2
+        } else {
+            local
         }
     }
 
-    pos
+    /// Whether a program used more CPU time than `limit`, when judging on CPU time.
+    fn cpu_exceeded(&self, limit: Option<Duration>, usage: Option<Usage>) -> bool {
+        match (limit, usage) {
+            (Some(limit), Some(usage)) if self.cpu_time => usage.cpu_time() > self.local(limit),
+            _ => false,
+        }
+    }
 }
 
-#[derive(Debug)]
-struct CargoError {
-    #[allow(dead_code)]
+/// The program exited with a nonzero status.
+struct NonZeroExit {
     exit_code: i32,
 }
 
-impl Display for CargoError {
+impl Display for NonZeroExit {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        Debug::fmt(self, f)
+        write!(f, "RuntimeError: exited with {}", self.exit_code)
     }
 }
 
-impl Error for CargoError {}
+impl Debug for NonZeroExit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+impl Error for NonZeroExit {}
 
 impl Run for Testcase {
     fn run(&self) -> Result<(), Box<dyn Error>> {
         let Testcase {
             src_name,
             in_files,
+            filter,
+            exclude,
+            last_failed,
+            fail_fast,
             dir_name,
+            diff,
+            color,
+            format,
+            compare,
+            layout,
+            list,
+            multi,
+            multi_output,
+            output_limit,
+            sandbox,
+            stack_size,
+            cpu_time,
+            calibrated,
+            check_overflow,
         } = self;
+        let human = *format == Format::Human;
+        let palette = if human {
+            color.palette()
+        } else {
+            Palette::PLAIN
+        };
         let testcase_dir = if let Some(dir_name) = dir_name {
             PathBuf::from(format!("{}/{}", TESTCASE_DIR_NAME, dir_name))
         } else {
             PathBuf::from(format!("{}/{}", TESTCASE_DIR_NAME, src_name))
         };
-        check_file_existance(&testcase_dir)?;
-        let testcase_in = testcase_dir.join("in");
-        check_file_existance(&testcase_in)?;
-        let testcase_out = testcase_dir.join("out");
-        check_file_existance(&testcase_out)?;
+        let src_path = PathBuf::from(format!("src/bin/{src_name}.rs"));
+        let task = cases::task_cases(&testcase_dir, &src_path, *layout)?;
+        let mut scan = task.scan;
+        scan.cases.extend(task.inline);
+        if *list {
+            print!("{}", list_cases(&scan, palette));
+            return Ok(());
+        }
+        for skipped in &scan.skipped {
+            eprintln!(
+                "{} {}: {}",
+                palette.paint(Style::Error, "Skipped:"),
+                skipped.path.display(),
+                skipped.reason
+            );
+        }
+        let dir_mode = CompareMode::from_dir(&testcase_dir)?;
+        let manifest = Manifest::from_dir(&testcase_dir)?.unwrap_or_default();
+        for name in manifest.unknown_cases(&scan.cases) {
+            eprintln!(
+                "{} `{name}` in {} matches no case",
+                palette.paint(Style::Error, "Warning:"),
+                MANIFEST_FILE_NAME
+            );
+        }
+
+        let project_root =
+            project::find_root(&env::current_dir()?).unwrap_or_else(|_| PathBuf::from("."));
+        let last_failed_path = LastFailed::path(&project_root, src_name);
+        let mut last = LastFailed::load(&last_failed_path)?.unwrap_or_default();
+        let selection = Selection {
+            in_files: in_files.as_deref(),
+            filter,
+            exclude,
+            last_failed: last_failed.then_some(&last),
+        };
+        let selected = selection.select(&scan.cases);
+        let clock = Clock {
+            cpu_time: *cpu_time,
+            calibration: if *calibrated {
+                Some(Calibration::load(&Calibration::path(&project_root))?.ok_or(NotCalibrated)?)
+            } else {
+                None
+            },
+        };
+        // Compiled once, so that a compile error stops before any case runs.
+        let binary = match compile::compile(src_name) {
+            Ok(binary) => binary,
+            Err(e) => {
+                let error = e.downcast::<CompileError>()?;
+                eprintln!("{}", error.render(palette));
+                let mut report = Report::new(src_name);
+                report.compile_error = Some(*error);
+                if let Some(rendered) = report.render(*format) {
+                    println!("{rendered}");
+                }
+                return Ok(());
+            }
+        };
+        let sandbox = if *sandbox {
+            Some(Sandbox::new(palette)?)
+        } else {
+            None
+        };
+
+        // Built at the first failure with `--check-overflow`, or why it could not be; `None` when
+        // the dev build already checks overflow, so that a rebuild would be the same program.
+        let mut checked_binary: Option<Result<Option<PathBuf>, String>> = None;
 
         // Run source programming using test cases.
-        for in_entry in testcase_in.read_dir()? {
-            let Ok(in_entry) = in_entry else { continue };
-            let in_path = &in_entry.path();
-            let Some(in_file_name_with_ext) = in_path.file_name().and_then(std::ffi::OsStr::to_str)
-            else {
-                continue;
-            };
-            if in_path.extension().and_then(std::ffi::OsStr::to_str) != Some("txt") {
+        let mut report = Report::new(src_name);
+        for case in selected {
+            let options = manifest.options(case);
+            if options.skip() {
+                if human {
+                    println!(
+                        "{} {:?}\n",
+                        palette.paint(Style::Dim, "Skipped:"),
+                        case.name
+                    );
+                }
                 continue;
             }
+            // `--compare` wins over `cases.toml`, which wins over the `compare` file.
+            let checker = Checker::new(
+                compare.or(options.checker).or(dir_mode),
+                options.float_tolerance,
+            );
+            let case_multi = options.multi(multi.as_ref(), multi_output.as_ref());
+            if human {
+                println!(
+                    "{}",
+                    palette.paint(Style::Header, format!("{} runs. 🏃‍➡️", case.origin))
+                );
+            }
 
-            if let Some(in_files) = in_files {
-                if let Some((in_file_name, _)) = in_file_name_with_ext.split_once('.') {
-                    if !in_files.contains(&in_file_name.to_string()) {
-                        continue;
-                    }
-                } else {
-                    continue;
-                }
+            let mut cargo = runner::binary(&binary);
+            if *check_overflow {
+                // The panic of an overflow must stay within the kept end of stderr.
+                cargo.env("RUST_BACKTRACE", "0");
+            }
+            if let Some(sandbox) = &sandbox {
+                let cpu_limit = options
+                    .time_limit()
+                    .map_or(sandbox::DEFAULT_CPU_LIMIT, |limit| clock.local(limit));
+                sandbox.apply(&mut cargo, Some(cpu_limit));
+            }
+            if let Some(mib) = stack_size {
+                runner::set_stack_size(&mut cargo, *mib);
+            }
+            let start = Instant::now();
+            let mut child = SysCommand::spawn(&mut cargo)?;
+            if let Some(time_limit) = options.time_limit() {
+                child.set_time_limit(clock.kill_after(time_limit));
+            }
+            let judging = Judging {
+                checker,
+                multi: case_multi.as_ref(),
+                output_limit: options.output_limit(*output_limit),
+                stack_size: stack_size
+                    .map_or_else(runner::default_stack_size, |mib| Some(mib << 20)),
             };
-
-            let out_file_name = testcase_out.join(in_entry.file_name());
-            check_file_existance(&out_file_name)?;
-            let in_file = File::open(in_path)?;
-            let out_file = File::open(&out_file_name)?;
-
-            println!("testcase/{src_name}/{in_file_name_with_ext} runs. 🏃‍➡️",);
-
-            let mut cargo = Command::new("cargo");
-            cargo
-                .arg("run")
-                .arg("--bin")
-                .arg(src_name)
-                .stdin(Stdio::piped())
-                .stdout(Stdio::piped());
-            match cargo_run(cargo, BufReader::new(in_file), BufReader::new(out_file)) {
-                Ok(_) => {
-                    println!("OK: {:?}", in_file_name_with_ext);
-                }
-                Err(e) => {
-                    println!("Err: {:?}, {:?}", in_file_name_with_ext, e);
+            let result = judge(
+                &mut child,
+                case.input.as_bytes(),
+                case.expect.as_bytes(),
+                &judging,
+                diff,
+                palette,
+            );
+            if result.is_err() {
+                // Judging may have stopped before the program finished.
+                let _ = child.kill();
+            }
+            let case_input = &case.input;
+            let mut case = case_report(
+                &case.name,
+                result,
+                start.elapsed(),
+                &mut child,
+                clock,
+                options.time_limit(),
+            );
+            case.expect_verdict = options.expect_verdict;
+            if *check_overflow && !case.passed() {
+                let binary = checked_binary.get_or_insert_with(|| {
+                    match runner::dev_checks_overflow(&project_root) {
+                        Ok(true) => Ok(None),
+                        Ok(false) => runner::build_checked(&project_root, src_name).map(Some),
+                        Err(e) => Err(e),
+                    }
+                    .map_err(|e| e.to_string())
+                });
+                let line = match binary {
+                    Ok(None) => already_checked(&case.stderr),
+                    Ok(Some(binary)) => {
+                        let mut command = runner::binary(binary);
+                        // A backtrace would push the panic out of the kept end of stderr.
+                        command.env("RUST_BACKTRACE", "0");
+                        if let Some(sandbox) = &sandbox {
+                            sandbox.apply(&mut command, Some(sandbox::DEFAULT_CPU_LIMIT));
+                        }
+                        if let Some(mib) = stack_size {
+                            runner::set_stack_size(&mut command, *mib);
+                        }
+                        // The checks and the lack of optimization may slow the program down a lot.
+                        let time_limit = options.time_limit().unwrap_or(DEFAULT_TIME_LIMIT);
+                        match run_checked(
+                            command,
+                            case_input.as_bytes(),
+                            clock.kill_after(time_limit) * CHECKED_SLOWDOWN,
+                            options.output_limit(*output_limit),
+                        ) {
+                            Ok(execution) => overflow_check(&execution),
+                            Err(e) => {
+                                format!("OverflowCheck: the debug build could not be run: {e}")
+                            }
+                        }
+                    }
+                    Err(e) => format!("OverflowCheck: the debug build failed: {e}"),
+                };
+                case.message = Some(match case.message {
+                    Some(message) => format!("{message}\n{line}"),
+                    None => line,
+                });
+            }
+            case.tags = options.tags;
+            if human {
+                println!("{}", case_line(&case, palette));
+                if let Some(SubCase { index, .. }) = case.diff.and_then(|d| d.sub_case) {
+                    let mut command = format!(
+                        "atcoder samples isolate {} {} {index}",
+                        dir_name.as_ref().unwrap_or(src_name),
+                        case.name
+                    );
+                    if let Some(multi) = multi {
+                        let _ = write!(command, " --multi '{multi}'");
+                    }
+                    if let Some(multi_output) = multi_output {
+                        let _ = write!(command, " --multi-output '{multi_output}'");
+                    }
+                    println!("To extract the case: {command}");
                 }
+                println!();
+            }
+            let passed = case.passed();
+            report.cases.push(case);
+            if *fail_fast && !passed {
+                break;
+            }
+        }
+        last.update(report.cases.iter().map(|c| (c.name.as_str(), c.passed())));
+        last.save(&last_failed_path)?;
+        match report.render(*format) {
+            Some(rendered) => println!("{rendered}"),
+            None => {
+                let num_ok = report.num_passed();
+                let num_err = report.cases.len() - num_ok;
+                println!("{}", summary(num_ok, num_err, palette));
             }
-            println!("");
         }
         Ok(())
     }
 }
 
+fn case_report<C: SysChild>(
+    name: &str,
+    result: Result<(), Box<dyn Error>>,
+    time: Duration,
+    child: &mut C,
+    clock: Clock,
+    time_limit: Option<Duration>,
+) -> CaseReport {
+    let usage = child.usage();
+    let time = clock.judge(time);
+    let (verdict, diff, message) = match result {
+        _ if child.timed_out() => (
+            Verdict::TimeLimitExceeded,
+            None,
+            Some(format!("TimeLimitExceeded: killed after {time:.1?}")),
+        ),
+        _ if clock.cpu_exceeded(time_limit, usage) => {
+            let usage = usage.unwrap_or_default();
+            let message = format!(
+                "TimeLimitExceeded: used {:.1?} of CPU time (user {:.1?}, sys {:.1?})",
+                clock.judge(usage.cpu_time()),
+                clock.judge(usage.user_time),
+                clock.judge(usage.sys_time)
+            );
+            (Verdict::TimeLimitExceeded, None, Some(message))
+        }
+        Ok(()) => (Verdict::Accepted, None, None),
+        Err(e) => match e.downcast_ref::<WrongAnswer>() {
+            Some(wa) => (
+                Verdict::WrongAnswer,
+                Some(wa.location),
+                Some(format!("{e:?}")),
+            ),
+            None if e.is::<OutputLimitExceeded>() => {
+                (Verdict::OutputLimitExceeded, None, Some(format!("{e:?}")))
+            }
+            None => (Verdict::RuntimeError, None, Some(format!("{e:?}"))),
+        },
+    };
+    CaseReport {
+        name: name.to_string(),
+        verdict,
+        time_ms: time.as_millis() as u64,
+        user_ms: usage.map(|u| clock.judge(u.user_time).as_millis() as u64),
+        sys_ms: usage.map(|u| clock.judge(u.sys_time).as_millis() as u64),
+        memory_kb: usage.map(|u| u.max_rss_kb),
+        diff,
+        stderr: child.stderr_excerpt(),
+        message,
+        tags: Vec::new(),
+        expect_verdict: None,
+    }
+}
+
+/// How many times longer than the time limit the checked build of the solution may run.
+const CHECKED_SLOWDOWN: u32 = 10;
+
+/// The time limit the checked build is held to on a case without one, the usual one on AtCoder.
+const DEFAULT_TIME_LIMIT: Duration = Duration::from_secs(2);
+
+/// Runs the checked build of the solution on `input`, killing it after `kill_after`.
+fn run_checked<P, C>(
+    mut command: P,
+    input: &[u8],
+    kill_after: Duration,
+    output_limit: usize,
+) -> Result<Execution, Box<dyn Error>>
+where
+    P: SysCommand<C>,
+    C: SysChild,
+{
+    let start = Instant::now();
+    let mut child = command.spawn()?;
+    child.set_time_limit(kill_after);
+    let execution = runner::execute(&mut child, input, output_limit, start);
+    if execution.is_err() {
+        let _ = child.kill();
+    }
+    Ok(execution?)
+}
+
+/// What the build with overflow checks and debug assertions did on the input of a failed case.
+fn overflow_check(execution: &Execution) -> String {
+    match runner::find_panic(&execution.stderr) {
+        Some(panic) if panic.is_overflow() => format!(
+            "OverflowCheck: the debug build panics with `{}` at {}",
+            panic.message, panic.location
+        ),
+        Some(panic) => format!(
+            "OverflowCheck: no overflow, but the debug build panics with `{}` at {}",
+            panic.message, panic.location
+        ),
+        None if execution.timed_out => format!(
+            "OverflowCheck: the debug build did not overflow before it was killed after {:.1?}",
+            execution.time
+        ),
+        None => "OverflowCheck: the debug build does not overflow".to_string(),
+    }
+}
+
+/// Whether a failed case overflowed, from the `stderr` of a build that already checks overflow.
+fn already_checked(stderr: &str) -> String {
+    match runner::find_panic(stderr) {
+        Some(panic) if panic.is_overflow() => format!(
+            "OverflowCheck: panics with `{}` at {}",
+            panic.message, panic.location
+        ),
+        _ => "OverflowCheck: the dev profile already checks overflow, and it did not overflow"
+            .to_string(),
+    }
+}
+
+/// The outcome of a case in the human format.
+fn case_line(case: &CaseReport, palette: Palette) -> String {
+    let tags = if case.tags.is_empty() {
+        String::new()
+    } else {
+        format!(" [{}]", case.tags.join(", "))
+    };
+    match (&case.message, case.expect_verdict) {
+        _ if case.passed() && !case.verdict.is_accepted() => format!(
+            "{} {:?}{tags} ({} as expected)",
+            palette.paint(Style::Ok, "OK:"),
+            case.name,
+            case.verdict.code()
+        ),
+        (None, None) => format!("{} {:?}{tags}", palette.paint(Style::Ok, "OK:"), case.name),
+        (None, Some(expect_verdict)) => format!(
+            "{} {:?}{tags}, {}, expected {}",
+            palette.paint(Style::Error, "Err:"),
+            case.name,
+            case.verdict.code(),
+            expect_verdict.code()
+        ),
+        (Some(message), _) => format!(
+            "{} {:?}{tags}, {}",
+            palette.paint(Style::Error, "Err:"),
+            case.name,
+            message
+        ),
+    }
+}
+
+/// Which cases to run.
+#[derive(Clone, Copy)]
+struct Selection<'a> {
+    in_files: Option<&'a [String]>,
+    filter: &'a [Pattern],
+    exclude: &'a [Pattern],
+    last_failed: Option<&'a LastFailed>,
+}
+
+impl Selection<'_> {
+    fn select<'c>(&self, cases: &'c [Case]) -> Vec<&'c Case> {
+        // With no recorded failures, `--last-failed` runs everything.
+        let last_failed = self
+            .last_failed
+            .filter(|last| cases.iter().any(|case| last.failed.contains(&case.name)));
+        cases
+            .iter()
+            .filter(|case| {
+                self.in_files
+                    .is_none_or(|in_files| in_files.iter().any(|name| name == case.stem()))
+            })
+            .filter(|case| cases::is_selected(case, self.filter, self.exclude))
+            .filter(|case| last_failed.is_none_or(|last| last.failed.contains(&case.name)))
+            .collect()
+    }
+}
+
+/// The cases found, with where their input and output come from, and the files skipped.
+fn list_cases(scan: &Scan, palette: Palette) -> String {
+    let mut list = String::new();
+    let layouts: Vec<&str> = scan.layouts.iter().map(|layout| layout.name()).collect();
+    let _ = writeln!(
+        list,
+        "{} {}",
+        palette.paint(Style::Header, "Layouts:"),
+        layouts.join(", ")
+    );
+    let _ = writeln!(
+        list,
+        "{} {}",
+        palette.paint(Style::Header, "Cases:"),
+        scan.cases.len()
+    );
+    let width = scan.cases.iter().map(|c| c.name.len()).max().unwrap_or(0);
+    for case in &scan.cases {
+        if case.origin == case.expect_origin {
+            let _ = writeln!(list, "  {:width$}  {}", case.name, case.origin);
+        } else {
+            let _ = writeln!(
+                list,
+                "  {:width$}  {} -> {}",
+                case.name, case.origin, case.expect_origin
+            );
+        }
+    }
+    if !scan.skipped.is_empty() {
+        let _ = writeln!(
+            list,
+            "{} {}",
+            palette.paint(Style::Error, "Skipped:"),
+            scan.skipped.len()
+        );
+        for skipped in &scan.skipped {
+            let _ = writeln!(list, "  {}: {}", skipped.path.display(), skipped.reason);
+        }
+    }
+    list
+}
+
+fn summary(num_ok: usize, num_err: usize, palette: Palette) -> String {
+    let ok = format!("OK: {num_ok}");
+    let err = format!("Err: {num_err}");
+    format!(
+        "{} {}, {}",
+        palette.paint(Style::Header, "Summary:"),
+        if num_ok > 0 {
+            palette.paint(Style::Ok, ok)
+        } else {
+            ok
+        },
+        if num_err > 0 {
+            palette.paint(Style::Error, err)
+        } else {
+            err
+        },
+    )
+}
+
 struct WrongAnswer {
+    location: DiffLocation,
     message: String,
 }
 
@@ -160,120 +674,254 @@ impl std::fmt::Debug for WrongAnswer {
 
 impl Error for WrongAnswer {}
 
-fn cargo_run<P, C>(
-    mut cargo_cmd: P,
-    mut in_reader: impl Read,
-    mut expect_reader: impl BufRead,
-) -> Result<C, Box<dyn Error>>
-where
-    P: SysCommand<C>,
-    C: SysChild,
-{
-    // Command excution
-    let mut cargo = cargo_cmd.spawn()?;
-
-    // Write in_file to stdin
-    let mut in_buf = [0u8; 1024];
-    loop {
-        let in_bytes_read = in_reader.read(&mut in_buf)?;
-        let _ = cargo.stdin_write(&in_buf[..in_bytes_read])?;
-        if in_bytes_read == 0 {
-            break;
+/// Lines of the output shown when it exceeds the output limit.
+const OUTPUT_HEAD_LINES: usize = 10;
+
+struct OutputLimitExceeded {
+    limit: usize,
+    /// The first lines of the output.
+    head: String,
+}
+
+impl std::fmt::Display for OutputLimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "OutputLimitExceeded: killed after printing {:.1} MiB, starting with:\n{}",
+            self.limit as f64 / (1 << 20) as f64,
+            self.head
+        )
+    }
+}
+
+impl std::fmt::Debug for OutputLimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(self, f)
+    }
+}
+
+impl Error for OutputLimitExceeded {}
+
+/// A thread of the program overflowed its stack.
+struct StackOverflow {
+    /// Rust's report of the overflow, naming the thread.
+    line: String,
+    stack_size: Option<u64>,
+}
+
+impl std::fmt::Display for StackOverflow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "StackOverflow: {}", self.line)?;
+        // Other threads have the stack size they were spawned with.
+        if self.line.contains("'main'") {
+            match self.stack_size {
+                Some(bytes) => write!(f, " of {} MiB", bytes >> 20)?,
+                None => write!(f, " of unlimited size")?,
+            }
+            write!(
+                f,
+                "; raise it with `--stack-size`, or run `main` on a large-stack thread as `atcoder new --template large-stack` does"
+            )?;
         }
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for StackOverflow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(self, f)
+    }
+}
+
+impl Error for StackOverflow {}
+
+/// How the output of a case is judged.
+struct Judging<'a> {
+    checker: Checker,
+    /// How a multi-testcase input is split, to tell which case is wrong.
+    multi: Option<&'a Multi>,
+    /// Output in bytes after which the program is killed.
+    output_limit: usize,
+    /// Stack size of the main thread in bytes, `None` when unlimited.
+    stack_size: Option<u64>,
+}
+
+/// Feeds `in_reader` to the running `cargo` and compares its output with `expect_reader`.
+fn judge<C: SysChild>(
+    cargo: &mut C,
+    mut in_reader: impl Read,
+    mut expect_reader: impl Read,
+    judging: &Judging,
+    diff: &DiffOptions,
+    palette: Palette,
+) -> Result<(), Box<dyn Error>> {
+    let Judging {
+        checker,
+        multi,
+        output_limit,
+        stack_size,
+    } = *judging;
+    // Write in_file to stdin as `compare` does, keeping it to split a multi-testcase input.
+    let mut input = Vec::new();
+    in_reader.read_to_end(&mut input)?;
+    runner::feed_input(cargo, &input);
+
+    // Read the whole output before waiting, so that cargo never blocks on a full pipe.
+    let (stdout, exceeded) = runner::read_output(cargo, output_limit)?;
+    if exceeded {
+        let _ = cargo.exit_code();
+        let head: Vec<String> = stdout
+            .lines()
+            .take(OUTPUT_HEAD_LINES)
+            .map(|line| line.chars().take(200).collect())
+            .collect();
+        return Err(Box::new(OutputLimitExceeded {
+            limit: output_limit,
+            head: head.join("\n"),
+        }));
     }
 
     // Wait for cargo to finish.
-    let exit_code = cargo.exit_code()?;
+    let exit_code = match cargo.exit_code() {
+        Ok(exit_code) => exit_code,
+        Err(e) => {
+            let stderr = cargo.stderr_excerpt();
+            return Err(match runner::stack_overflow(cargo.signal(), &stderr) {
+                Some(line) => Box::new(StackOverflow {
+                    line: line.to_string(),
+                    stack_size,
+                }),
+                None => Box::new(e),
+            });
+        }
+    };
     if exit_code > 0 {
-        return Err(Box::new(CargoError { exit_code }));
-    }
-
-    // Read cargo's output.
-    const NUM_TO_DISPLAY: usize = 5;
-    type CircularBufferS = CircularBuffer<String, NUM_TO_DISPLAY>;
-    let wrong_answer =
-        |num_col: usize, num_row: usize, cmd_out: CircularBufferS, expect: CircularBufferS| {
-            let mut message = String::new();
-            message.push_str(format!("Program output(Line {}):\n", num_col).as_str());
-            for line in cmd_out.iter() {
-                message.push_str(format!("{:4}{}", " ", line).as_str());
-            }
-            message.push_str("\n");
-            message.push_str(format!("{:4}{:width$}^", " ", " ", width = num_row).as_str());
-            message.push_str("\n");
-            message.push_str("expect:\n");
+        return Err(Box::new(NonZeroExit { exit_code }));
+    }
 
-            for line in expect.iter() {
-                message.push_str(format!("{:4}{}", " ", line).as_str());
-            }
-            message.push_str("\n");
-            message.push_str(format!("{:4}{:width$}^", " ", " ", width = num_row).as_str());
-
-            Box::new(WrongAnswer { message })
-        };
-
-    // Read the stdout of cmd byte by byte and compare it to expect
-    let mut stdout_line_buf = CircularBufferS::new(NUM_TO_DISPLAY);
-    let mut stdout_line = String::new();
-    let mut expect_line_buf = CircularBufferS::new(NUM_TO_DISPLAY);
-    let mut expect_line = String::new();
-    let mut num_col = 0;
-    loop {
-        let stdout_read_num = cargo.stdout_read_line(&mut stdout_line)?;
-        let expect_read_num = expect_reader.read_line(&mut expect_line)?;
-        let diff_position = diff_position(stdout_line.trim(), expect_line.trim());
-
-        stdout_line_buf.push(stdout_line.clone());
-        expect_line_buf.push(expect_line.clone());
-        stdout_line.clear();
-        expect_line.clear();
-
-        num_col += 1;
-
-        if diff_position >= 0 {
-            let _ = cargo.kill();
-            return Err(wrong_answer(
-                num_col,
-                diff_position as usize,
-                stdout_line_buf,
-                expect_line_buf,
-            ));
-        }
+    let mut expect = String::new();
+    expect_reader.read_to_string(&mut expect)?;
+    if let Some(difference) = checker.compare(&stdout, &expect) {
+        let mismatch = Mismatch::new(&stdout, &expect, difference, checker.mode, diff);
+        let (sub_case, mut message) = match multi {
+            Some(multi) => locate_sub_case(
+                multi,
+                &String::from_utf8_lossy(&input),
+                &expect,
+                difference.expect.line,
+            ),
+            None => (None, String::new()),
+        };
+        message.push_str(&mismatch.render(diff, palette));
+        return Err(Box::new(WrongAnswer {
+            location: DiffLocation {
+                line: difference.actual.line,
+                column: difference.actual.column,
+                sub_case,
+            },
+            message,
+        }));
+    }
+    Ok(())
+}
 
-        if stdout_read_num == 0 && expect_read_num == 0 {
-            break;
+/// The case of a multi-testcase input that the 1-based `expect_line` belongs to, and a note on it.
+fn locate_sub_case(
+    multi: &Multi,
+    input: &str,
+    expect: &str,
+    expect_line: usize,
+) -> (Option<SubCase>, String) {
+    let cases = match multi.split(input, expect) {
+        Ok(cases) => cases,
+        Err(e) => return (None, format!("Cannot split the input into cases: {e}\n")),
+    };
+    match Multi::locate(&cases, expect_line) {
+        Some(i) => {
+            let lines = &cases[i].input;
+            let note = format!(
+                "case {} of {} wrong (input lines {}-{})\n",
+                i + 1,
+                cases.len(),
+                lines.start + 1,
+                lines.end
+            );
+            let sub_case = SubCase {
+                index: i + 1,
+                count: cases.len(),
+            };
+            (Some(sub_case), note)
         }
+        None => (
+            None,
+            format!("output after the last of {} cases\n", cases.len()),
+        ),
     }
-    Ok(cargo)
 }
 
 #[cfg(test)]
 mod atcoder_test {
-    use super::super::super::syscommand::syscommand_test::DummyCommand;
-    use super::cargo_run;
+    use super::super::super::syscommand::syscommand_test::{DummyCommand, DummyEffectCommand};
+    use super::{
+        already_checked, case_line, case_report, judge, list_cases, overflow_check, run_checked,
+        summary, Clock, Judging, Selection, StackOverflow, WrongAnswer,
+    };
+    use crate::calibrate::Calibration;
+    use crate::cases::{Case, LastFailed, Layout, Multi, Scan, Skipped};
+    use crate::checker::{Checker, CompareMode};
+    use crate::color::Palette;
+    use crate::diff::{DiffOptions, DiffStyle};
+    use crate::report::{CaseReport, SubCase, Verdict};
+    use crate::runner::{self, Execution};
+    use crate::syscommand::{SysCommand, Usage};
     use std::fs;
-    use std::io::Cursor;
+    use std::io::{self, Cursor};
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+
+    fn judging(checker: Checker, multi: Option<&Multi>) -> Judging<'_> {
+        Judging {
+            checker,
+            multi,
+            output_limit: runner::OUTPUT_LIMIT,
+            stack_size: None,
+        }
+    }
 
     #[test]
     fn test_ok() {
         let expect = String::from("a\nbc\ndef\n");
-        let dummy_cargo = DummyCommand {
+        let mut dummy_cargo = DummyCommand {
             exit_code: 0,
             stdout: expect.clone(),
         };
-        let child = cargo_run(dummy_cargo, "dummy_input".as_bytes(), Cursor::new(expect));
-        assert_eq!(child.is_ok(), true);
+        let child = judge(
+            &mut dummy_cargo.spawn().unwrap(),
+            "dummy_input".as_bytes(),
+            Cursor::new(expect),
+            &judging(CompareMode::Lines.into(), None),
+            &DiffOptions::default(),
+            Palette::PLAIN,
+        );
+        assert!(child.is_ok());
     }
 
     #[test]
     fn test_wronganswer_description() {
         let expect = String::from("abc\ndef\nghi\njklm\nopqr\ns\nt\nuvwz");
-        let dummy_cargo = DummyCommand {
+        let mut dummy_cargo = DummyCommand {
             exit_code: 0,
             stdout: "abc\ndef\nghi\njklm\nopqr\ns\nt\nuvw".to_string(),
         };
-        let child = cargo_run(dummy_cargo, "dummy_input".as_bytes(), Cursor::new(expect));
-        assert_eq!(child.is_ok(), false);
+        let child = judge(
+            &mut dummy_cargo.spawn().unwrap(),
+            "dummy_input".as_bytes(),
+            Cursor::new(expect),
+            &judging(CompareMode::Lines.into(), None),
+            &DiffOptions::default(),
+            Palette::PLAIN,
+        );
+        assert!(child.is_err());
         match &child {
             Err(e) => {
                 dbg!(e);
@@ -297,7 +945,7 @@ expect:
                 );
             }
             _ => {
-                assert!(false);
+                unreachable!();
             }
         }
     }
@@ -312,22 +960,124 @@ expect:
     }
 
     fn run_testcase_ac(expect: String, program_out: String) {
-        let dummy_cargo = DummyCommand {
+        let mut dummy_cargo = DummyCommand {
             exit_code: 0,
             stdout: program_out,
         };
-        let child = cargo_run(dummy_cargo, "dummy_input".as_bytes(), expect.as_bytes());
+        let child = judge(
+            &mut dummy_cargo.spawn().unwrap(),
+            "dummy_input".as_bytes(),
+            expect.as_bytes(),
+            &judging(CompareMode::Lines.into(), None),
+            &DiffOptions::default(),
+            Palette::PLAIN,
+        );
         println!("child = {:?}", child);
-        assert_eq!(child.is_ok(), true);
+        assert!(child.is_ok());
     }
 
     fn run_testcase_wa(expect: String, program_out: String) {
-        let dummy_cargo = DummyCommand {
+        let mut dummy_cargo = DummyCommand {
             exit_code: 0,
             stdout: program_out,
         };
-        let child = cargo_run(dummy_cargo, "dummy_input".as_bytes(), expect.as_bytes());
-        assert_eq!(child.is_ok(), false);
+        let child = judge(
+            &mut dummy_cargo.spawn().unwrap(),
+            "dummy_input".as_bytes(),
+            expect.as_bytes(),
+            &judging(CompareMode::Lines.into(), None),
+            &DiffOptions::default(),
+            Palette::PLAIN,
+        );
+        assert!(child.is_err());
+    }
+
+    #[test]
+    fn testcase_multi_wa() {
+        let multi = Multi::new("n; [n]".parse().unwrap(), None);
+        let run = |program_out: &str| {
+            let mut dummy_cargo = DummyCommand {
+                exit_code: 0,
+                stdout: program_out.to_string(),
+            };
+            judge(
+                &mut dummy_cargo.spawn().unwrap(),
+                "3\n1\na\n2\nb\nc\n1\nd\n".as_bytes(),
+                "1\n2\n1\n".as_bytes(),
+                &judging(CompareMode::Lines.into(), Some(&multi)),
+                &DiffOptions::default(),
+                Palette::PLAIN,
+            )
+            .unwrap_err()
+        };
+        let e = run("1\n3\n1\n");
+        let wa = e.downcast_ref::<WrongAnswer>().unwrap();
+        assert_eq!(wa.location.sub_case, Some(SubCase { index: 2, count: 3 }));
+        assert!(e
+            .to_string()
+            .starts_with("WrongAnswer:\ncase 2 of 3 wrong (input lines 4-6)\n"));
+
+        let e = run("1\n2\n1\n0\n");
+        assert!(e
+            .to_string()
+            .starts_with("WrongAnswer:\noutput after the last of 3 cases\n"));
+    }
+
+    fn accepted(mode: CompareMode, expect: &str, program_out: &str) -> bool {
+        let mut dummy_cargo = DummyCommand {
+            exit_code: 0,
+            stdout: testcase_pgout(program_out),
+        };
+        judge(
+            &mut dummy_cargo.spawn().unwrap(),
+            "dummy_input".as_bytes(),
+            testcase_expect(expect).as_bytes(),
+            &judging(mode.into(), None),
+            &DiffOptions::default(),
+            Palette::PLAIN,
+        )
+        .is_ok()
+    }
+
+    #[test]
+    fn testcase_compare_exact() {
+        let mode = CompareMode::Exact;
+        assert!(accepted(mode, "num1", "num1_ac"));
+        assert!(accepted(mode, "grid", "grid_ac"));
+        assert!(!accepted(mode, "num1", "num1_ac_crlf"));
+        assert!(!accepted(mode, "num1", "num1_ac_blank_lines"));
+        assert!(!accepted(mode, "num1", "num1_ac_leading_whitespace"));
+        assert!(!accepted(mode, "num1", "num1_ac_trailing_whitespace"));
+    }
+
+    #[test]
+    fn testcase_compare_lines() {
+        let mode = CompareMode::Lines;
+        assert!(accepted(mode, "num1", "num1_ac_crlf"));
+        assert!(accepted(mode, "num1", "num1_ac_blank_lines"));
+        assert!(accepted(mode, "num1", "num1_ac_trailing_whitespace"));
+        assert!(!accepted(mode, "num_list", "num_list_ac_split"));
+        assert!(!accepted(mode, "yes_no", "yes_no_ac_case"));
+        assert!(!accepted(mode, "grid", "grid_wa3"));
+    }
+
+    #[test]
+    fn testcase_compare_tokens() {
+        let mode = CompareMode::Tokens;
+        assert!(accepted(mode, "num_list", "num_list_ac"));
+        assert!(accepted(mode, "num_list", "num_list_ac_split"));
+        assert!(accepted(mode, "num1", "num1_ac_crlf"));
+        assert!(!accepted(mode, "num_list", "num_list_wa_extra"));
+        assert!(!accepted(mode, "num_list", "num_list_wa_lack"));
+        assert!(!accepted(mode, "num_list", "num_list_wa_value_wrong"));
+    }
+
+    #[test]
+    fn testcase_compare_case_insensitive() {
+        let mode = CompareMode::CaseInsensitive;
+        assert!(accepted(mode, "yes_no", "yes_no_ac_case"));
+        assert!(accepted(mode, "num1", "num1_ac_crlf"));
+        assert!(!accepted(mode, "yes_no", "yes_no_wa"));
     }
 
     #[test]
@@ -393,6 +1143,243 @@ expect:
         run_testcase_wa(expect, program_out);
     }
 
+    #[test]
+    fn testcase_grid_wa_grid_diff() {
+        let mut dummy_cargo = DummyCommand {
+            exit_code: 0,
+            stdout: testcase_pgout("grid_wa"),
+        };
+        let diff = DiffOptions {
+            style: DiffStyle::Grid,
+            context: 1,
+            ..DiffOptions::default()
+        };
+        let child = judge(
+            &mut dummy_cargo.spawn().unwrap(),
+            "dummy_input".as_bytes(),
+            testcase_expect("grid").as_bytes(),
+            &judging(CompareMode::Lines.into(), None),
+            &diff,
+            Palette::PLAIN,
+        );
+        assert_eq!(
+            child.unwrap_err().to_string(),
+            "WrongAnswer:
+Grid (line 3, column 2): 1 cell(s) differ
+      expect   output
+    2 .#.#.  | .#.#.
+    3 #..#.  | ##.#.
+                ^
+    4 #..#.  | #..#."
+        );
+    }
+
+    #[test]
+    fn testcase_num1_wa_colored() {
+        let mut dummy_cargo = DummyCommand {
+            exit_code: 0,
+            stdout: testcase_pgout("num1_wa"),
+        };
+        let diff = DiffOptions {
+            style: DiffStyle::Unified,
+            ..DiffOptions::default()
+        };
+        let child = judge(
+            &mut dummy_cargo.spawn().unwrap(),
+            "dummy_input".as_bytes(),
+            testcase_expect("num1").as_bytes(),
+            &judging(CompareMode::Lines.into(), None),
+            &diff,
+            Palette::COLORED,
+        );
+        assert_eq!(
+            child.unwrap_err().to_string(),
+            "WrongAnswer:
+\x1b[1m--- expect\x1b[0m
+\x1b[1m+++ output\x1b[0m
+\x1b[2m@@ -1,1 +1,1 @@\x1b[0m
+\x1b[31m-1\x1b[0m
+\x1b[32m+2\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn testcase_list() {
+        let case = |name: &str, origin: &str, expect_origin: &str| Case {
+            name: name.to_string(),
+            origin: origin.to_string(),
+            expect_origin: expect_origin.to_string(),
+            input: String::new(),
+            expect: String::new(),
+        };
+        let scan = Scan {
+            layouts: vec![Layout::InOut],
+            cases: vec![
+                case("1.txt", "testcase/a/in/1.txt", "testcase/a/out/1.txt"),
+                case("inline-1", "src/bin/a.rs#inline-1", "src/bin/a.rs#inline-1"),
+            ],
+            skipped: vec![Skipped {
+                path: PathBuf::from("testcase/a/in/2.txt"),
+                reason: "no expected output `testcase/a/out/2.txt`".to_string(),
+            }],
+            unanswered: vec![case("2.txt", "testcase/a/in/2.txt", "")],
+        };
+        assert_eq!(
+            list_cases(&scan, Palette::PLAIN),
+            "\
+Layouts: in-out
+Cases: 2
+  1.txt     testcase/a/in/1.txt -> testcase/a/out/1.txt
+  inline-1  src/bin/a.rs#inline-1
+Skipped: 1
+  testcase/a/in/2.txt: no expected output `testcase/a/out/2.txt`
+"
+        );
+    }
+
+    #[test]
+    fn testcase_selection() {
+        let cases: Vec<Case> = ["1.txt", "2.txt", "10.txt", "sample-1"]
+            .iter()
+            .map(|name| Case {
+                name: name.to_string(),
+                origin: String::new(),
+                expect_origin: String::new(),
+                input: String::new(),
+                expect: String::new(),
+            })
+            .collect();
+        let names = |selection: Selection| -> Vec<String> {
+            selection
+                .select(&cases)
+                .iter()
+                .map(|c| c.name.clone())
+                .collect()
+        };
+        let all = Selection {
+            in_files: None,
+            filter: &[],
+            exclude: &[],
+            last_failed: None,
+        };
+        assert_eq!(names(all).len(), 4);
+
+        let in_files = ["2".to_string()];
+        let filter = [r"re:^\d+$".parse().unwrap()];
+        let exclude = ["1*".parse().unwrap()];
+        assert_eq!(
+            names(Selection {
+                in_files: Some(&in_files),
+                ..all
+            }),
+            vec!["2.txt"]
+        );
+        assert_eq!(
+            names(Selection {
+                filter: &filter,
+                exclude: &exclude,
+                ..all
+            }),
+            vec!["2.txt"]
+        );
+
+        let mut last = LastFailed::default();
+        assert_eq!(
+            names(Selection {
+                last_failed: Some(&last),
+                ..all
+            })
+            .len(),
+            4
+        );
+        last.update([("10.txt", false), ("gone.txt", false)]);
+        assert_eq!(
+            names(Selection {
+                last_failed: Some(&last),
+                ..all
+            }),
+            vec!["10.txt"]
+        );
+    }
+
+    #[test]
+    fn testcase_summary() {
+        assert_eq!(summary(2, 1, Palette::PLAIN), "Summary: OK: 2, Err: 1");
+        assert_eq!(
+            summary(2, 0, Palette::COLORED),
+            "\x1b[1mSummary:\x1b[0m \x1b[1;32mOK: 2\x1b[0m, Err: 0"
+        );
+    }
+
+    #[test]
+    fn testcase_expected_verdict() {
+        let report = |verdict, message: Option<&str>, expect_verdict| CaseReport {
+            name: "max.txt".to_string(),
+            verdict,
+            time_ms: 2000,
+            user_ms: None,
+            sys_ms: None,
+            memory_kb: None,
+            diff: None,
+            stderr: String::new(),
+            message: message.map(str::to_string),
+            tags: vec!["max-case".to_string()],
+            expect_verdict,
+        };
+        let tle = Some("TimeLimitExceeded: killed after 2.0s");
+        assert_eq!(
+            case_line(
+                &report(
+                    Verdict::TimeLimitExceeded,
+                    tle,
+                    Some(Verdict::TimeLimitExceeded)
+                ),
+                Palette::PLAIN
+            ),
+            "OK: \"max.txt\" [max-case] (TLE as expected)"
+        );
+        assert_eq!(
+            case_line(
+                &report(Verdict::Accepted, None, Some(Verdict::TimeLimitExceeded)),
+                Palette::PLAIN
+            ),
+            "Err: \"max.txt\" [max-case], AC, expected TLE"
+        );
+        assert_eq!(
+            case_line(
+                &report(Verdict::TimeLimitExceeded, tle, None),
+                Palette::PLAIN
+            ),
+            "Err: \"max.txt\" [max-case], TimeLimitExceeded: killed after 2.0s"
+        );
+    }
+
+    #[test]
+    fn testcase_multibyte_output() {
+        let mut dummy_cargo = DummyCommand {
+            exit_code: 0,
+            stdout: "こんばんは\n".to_string(),
+        };
+        let child = judge(
+            &mut dummy_cargo.spawn().unwrap(),
+            "dummy_input".as_bytes(),
+            "こんにちは\n".as_bytes(),
+            &judging(CompareMode::Lines.into(), None),
+            &DiffOptions::default(),
+            Palette::PLAIN,
+        );
+        assert_eq!(
+            child.unwrap_err().to_string(),
+            "WrongAnswer:
+Program output(Line 1):
+    こんばんは
+        ^
+expect:
+    こんにちは
+        ^"
+        );
+    }
+
     #[test]
     fn testcase_num_list_ac() {
         let expect = testcase_expect("num_list");
@@ -420,4 +1407,188 @@ expect:
         let program_out = testcase_pgout("num_list_wa_value_wrong");
         run_testcase_wa(expect, program_out);
     }
+
+    #[test]
+    fn testcase_output_limit() {
+        let mut dummy_cargo = DummyCommand {
+            exit_code: 0,
+            stdout: "y\n".repeat(1000),
+        };
+        let mut child = dummy_cargo.spawn().unwrap();
+        let judging = Judging {
+            checker: CompareMode::Lines.into(),
+            multi: None,
+            output_limit: 25,
+            stack_size: None,
+        };
+        let result = judge(
+            &mut child,
+            "".as_bytes(),
+            "y\n".as_bytes(),
+            &judging,
+            &DiffOptions::default(),
+            Palette::PLAIN,
+        );
+        let case = case_report(
+            "1.txt",
+            result,
+            Duration::ZERO,
+            &mut child,
+            Clock::default(),
+            None,
+        );
+        assert_eq!(case.verdict, Verdict::OutputLimitExceeded);
+        let message = case.message.unwrap();
+        assert!(message.starts_with("OutputLimitExceeded: killed after printing 0.0 MiB"));
+        assert_eq!(message.lines().skip(1).collect::<Vec<_>>(), ["y"; 10]);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn testcase_unread_input() {
+        let mut command = runner::binary(Path::new("sh"));
+        command.arg("-c").arg("read n; echo $n");
+        let mut child = SysCommand::spawn(&mut command).unwrap();
+        let input = format!("3\n{}", "1 ".repeat(1 << 20));
+        let result = judge(
+            &mut child,
+            input.as_bytes(),
+            "3\n".as_bytes(),
+            &judging(Checker::default(), None),
+            &DiffOptions::default(),
+            Palette::PLAIN,
+        );
+        assert!(result.is_ok(), "{result:?}");
+    }
+
+    #[test]
+    fn testcase_stack_overflow() {
+        let main = StackOverflow {
+            line: "thread 'main' (5111) has overflowed its stack".to_string(),
+            stack_size: Some(8 << 20),
+        };
+        assert_eq!(
+            main.to_string(),
+            "StackOverflow: thread 'main' (5111) has overflowed its stack of 8 MiB; raise it with `--stack-size`, or run `main` on a large-stack thread as `atcoder new --template large-stack` does"
+        );
+        let spawned = StackOverflow {
+            line: "thread '<unnamed>' (5112) has overflowed its stack".to_string(),
+            stack_size: Some(8 << 20),
+        };
+        assert_eq!(
+            spawned.to_string(),
+            "StackOverflow: thread '<unnamed>' (5112) has overflowed its stack"
+        );
+    }
+
+    #[test]
+    fn testcase_already_checked() {
+        assert_eq!(
+            already_checked("thread 'main' (7) panicked at src/bin/a.rs:5:13:\nattempt to multiply with overflow"),
+            "OverflowCheck: panics with `attempt to multiply with overflow` at src/bin/a.rs:5:13"
+        );
+        assert_eq!(
+            already_checked(""),
+            "OverflowCheck: the dev profile already checks overflow, and it did not overflow"
+        );
+    }
+
+    #[test]
+    fn testcase_overflow_check() {
+        let panicked = |stderr: &str| Execution {
+            exit_code: Some(101),
+            stderr: stderr.to_string(),
+            ..Execution::default()
+        };
+        assert_eq!(
+            overflow_check(&panicked(
+                "\nthread 'main' (7) panicked at src/bin/a.rs:5:13:\nattempt to add with overflow\nnote: run with `RUST_BACKTRACE=1` environment variable to display a backtrace"
+            )),
+            "OverflowCheck: the debug build panics with `attempt to add with overflow` at src/bin/a.rs:5:13"
+        );
+        assert_eq!(
+            overflow_check(&panicked(
+                "thread 'main' (7) panicked at src/bin/a.rs:9:5:\nassertion failed: n > 0"
+            )),
+            "OverflowCheck: no overflow, but the debug build panics with `assertion failed: n > 0` at src/bin/a.rs:9:5"
+        );
+        assert_eq!(
+            overflow_check(&Execution {
+                exit_code: Some(0),
+                ..Execution::default()
+            }),
+            "OverflowCheck: the debug build does not overflow"
+        );
+        let killed = Execution {
+            timed_out: true,
+            time: Duration::from_secs(20),
+            ..Execution::default()
+        };
+        assert_eq!(
+            overflow_check(&killed),
+            "OverflowCheck: the debug build did not overflow before it was killed after 20.0s"
+        );
+    }
+
+    #[test]
+    fn testcase_run_checked() {
+        let panicking = DummyCommand {
+            exit_code: 101,
+            stdout: String::new(),
+        };
+        let execution = run_checked(
+            panicking,
+            b"3\n",
+            Duration::from_secs(20),
+            runner::OUTPUT_LIMIT,
+        )
+        .unwrap();
+        assert_eq!(execution.exit_code, Some(101));
+        let missing = DummyEffectCommand {
+            exit_code: 0,
+            effect: || Err(io::Error::from(io::ErrorKind::NotFound)),
+        };
+        assert!(run_checked(
+            missing,
+            b"3\n",
+            Duration::from_secs(20),
+            runner::OUTPUT_LIMIT
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn testcase_clock() {
+        let usage = |user_ms, sys_ms| Usage {
+            max_rss_kb: 0,
+            user_time: Duration::from_millis(user_ms),
+            sys_time: Duration::from_millis(sys_ms),
+        };
+        let limit = Some(Duration::from_secs(2));
+        let wall = Clock::default();
+        assert_eq!(
+            wall.kill_after(Duration::from_secs(2)),
+            Duration::from_secs(2)
+        );
+        assert!(!wall.cpu_exceeded(limit, Some(usage(3000, 0))));
+
+        let cpu = Clock {
+            cpu_time: true,
+            calibration: Some(Calibration {
+                local_ms: 1500.0,
+                judge_ms: 1000.0,
+            }),
+        };
+        assert_eq!(
+            cpu.kill_after(Duration::from_secs(2)),
+            Duration::from_secs(6)
+        );
+        assert!(!cpu.cpu_exceeded(limit, Some(usage(2800, 200))));
+        assert!(cpu.cpu_exceeded(limit, Some(usage(2800, 201))));
+        assert!(!cpu.cpu_exceeded(None, Some(usage(9000, 0))));
+        assert_eq!(
+            cpu.judge(Duration::from_millis(1500)),
+            Duration::from_secs(1)
+        );
+    }
 }
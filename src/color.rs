@@ -0,0 +1,101 @@
+use clap::ValueEnum;
+
+use std::env;
+use std::fmt::Display;
+use std::io::{self, IsTerminal};
+
+/// When to use colors in the output.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Use colors when stdout is a terminal and `NO_COLOR` is not set.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn palette(self) -> Palette {
+        let enabled = match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                let no_color = env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+                !no_color && io::stdout().is_terminal()
+            }
+        };
+        Palette { enabled }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Style {
+    /// Accepted verdicts.
+    Ok,
+    /// Rejected verdicts.
+    Error,
+    /// Headers and titles.
+    Header,
+    /// Lines only in the program output.
+    Added,
+    /// Lines only in the expected output.
+    Removed,
+    /// Differing characters and cells.
+    Highlight,
+    /// Line numbers and other secondary information.
+    Dim,
+}
+
+impl Style {
+    fn code(self) -> &'static str {
+        match self {
+            Style::Ok => "1;32",
+            Style::Error => "1;31",
+            Style::Header => "1",
+            Style::Added => "32",
+            Style::Removed => "31",
+            Style::Highlight => "1;7;31",
+            Style::Dim => "2",
+        }
+    }
+}
+
+/// Applies ANSI colors to text, or leaves it untouched when colors are disabled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Palette {
+    enabled: bool,
+}
+
+impl Palette {
+    pub const PLAIN: Palette = Palette { enabled: false };
+    #[cfg(test)]
+    pub const COLORED: Palette = Palette { enabled: true };
+
+    pub fn paint(&self, style: Style, text: impl Display) -> String {
+        if self.enabled {
+            format!("\x1b[{}m{}\x1b[0m", style.code(), text)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod color_test {
+    use super::{ColorChoice, Palette, Style};
+
+    #[test]
+    fn paint() {
+        assert_eq!(
+            Palette::COLORED.paint(Style::Ok, "AC"),
+            "\x1b[1;32mAC\x1b[0m"
+        );
+        assert_eq!(Palette::PLAIN.paint(Style::Ok, "AC"), "AC");
+    }
+
+    #[test]
+    fn forced_choices() {
+        assert_eq!(ColorChoice::Always.palette(), Palette::COLORED);
+        assert_eq!(ColorChoice::Never.palette(), Palette::PLAIN);
+    }
+}
@@ -0,0 +1,284 @@
+use crate::color::{Palette, Style};
+
+use serde::{Deserialize, Serialize};
+
+use std::error::Error;
+use std::fmt::{self, Display, Formatter, Write as _};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// Errors shown of a solution that does not compile; rustc often reports many that follow from
+/// the first.
+pub const MAX_DIAGNOSTICS: usize = 5;
+
+/// Where in the source an error is.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct Location {
+    pub file: String,
+    /// 1-based.
+    pub line: usize,
+    /// 1-based, as rustc counts.
+    pub column: usize,
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+/// An error reported by rustc.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub location: Option<Location>,
+    /// Such as `E0308`.
+    pub code: Option<String>,
+    pub message: String,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let Some(location) = &self.location {
+            write!(f, "{location} ")?;
+        }
+        match &self.code {
+            Some(code) => write!(f, "error[{code}]: {}", self.message),
+            None => write!(f, "error: {}", self.message),
+        }
+    }
+}
+
+/// A solution that does not compile.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct CompileError {
+    /// Of `cargo build`.
+    pub exit_code: i32,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl CompileError {
+    /// The first errors, under a `CE:` header painted with `palette`.
+    pub fn render(&self, palette: Palette) -> String {
+        let header = palette.paint(Style::Error, "CE:");
+        let mut rendered = match self.diagnostics.len() {
+            // Cargo itself failed, and said why on stderr.
+            0 => return format!("{header} `cargo build` exited with {}", self.exit_code),
+            1 => format!("{header} 1 error"),
+            n => format!("{header} {n} errors"),
+        };
+        for diagnostic in self.diagnostics.iter().take(MAX_DIAGNOSTICS) {
+            let _ = write!(rendered, "\n  {diagnostic}");
+        }
+        if self.diagnostics.len() > MAX_DIAGNOSTICS {
+            let _ = write!(
+                rendered,
+                "\n  ... and {} more",
+                self.diagnostics.len() - MAX_DIAGNOSTICS
+            );
+        }
+        rendered
+    }
+}
+
+impl Display for CompileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.render(Palette::PLAIN))
+    }
+}
+
+impl Error for CompileError {}
+
+/// A line of `cargo build --message-format=json`, of which only a few kinds matter.
+#[derive(Deserialize)]
+struct Message {
+    reason: String,
+    /// Of a `compiler-message`.
+    message: Option<CompilerMessage>,
+    /// Of a `compiler-artifact` of a binary.
+    executable: Option<PathBuf>,
+}
+
+#[derive(Deserialize)]
+struct CompilerMessage {
+    level: String,
+    message: String,
+    code: Option<Code>,
+    spans: Vec<Span>,
+}
+
+#[derive(Deserialize)]
+struct Code {
+    code: String,
+}
+
+#[derive(Deserialize)]
+struct Span {
+    file_name: String,
+    line_start: usize,
+    column_start: usize,
+    is_primary: bool,
+}
+
+/// What a build reported: the binary it produced and the errors of rustc.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Compilation {
+    pub executable: Option<PathBuf>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Reads the JSON messages cargo printed, skipping the lines that are not.
+pub fn parse_messages(output: &str) -> Compilation {
+    let mut compilation = Compilation::default();
+    for line in output.lines() {
+        let Ok(message) = serde_json::from_str::<Message>(line) else {
+            continue;
+        };
+        match message.reason.as_str() {
+            "compiler-artifact" => {
+                if let Some(executable) = message.executable {
+                    compilation.executable = Some(executable);
+                }
+            }
+            "compiler-message" => {
+                let Some(message) = message.message else {
+                    continue;
+                };
+                // Such as `aborting due to 2 previous errors`, which only counts the others.
+                if message.level != "error"
+                    || message.spans.is_empty() && message.message.starts_with("aborting due to")
+                {
+                    continue;
+                }
+                let span = message.spans.iter().find(|span| span.is_primary);
+                compilation.diagnostics.push(Diagnostic {
+                    location: span.map(|span| Location {
+                        file: span.file_name.clone(),
+                        line: span.line_start,
+                        column: span.column_start,
+                    }),
+                    code: message.code.map(|code| code.code),
+                    message: message
+                        .message
+                        .lines()
+                        .next()
+                        .unwrap_or_default()
+                        .to_string(),
+                });
+            }
+            _ => {}
+        }
+    }
+    compilation
+}
+
+/// Builds the solution `bin` with the dev profile, which `cargo run` uses, and returns its binary,
+/// or a `CompileError` with the errors of rustc.
+pub fn compile(bin: &str) -> Result<PathBuf, Box<dyn Error>> {
+    let output = Command::new("cargo")
+        .arg("build")
+        .arg("--bin")
+        .arg(bin)
+        .arg("--message-format=json")
+        .stdin(Stdio::null())
+        .stderr(Stdio::inherit())
+        .output()?;
+    let compilation = parse_messages(&String::from_utf8_lossy(&output.stdout));
+    match compilation.executable {
+        Some(executable) if output.status.success() => Ok(executable),
+        _ => Err(Box::new(CompileError {
+            exit_code: output.status.code().unwrap_or(-1),
+            diagnostics: compilation.diagnostics,
+        })),
+    }
+}
+
+#[cfg(test)]
+mod compile_test {
+    use super::{parse_messages, Compilation, CompileError, Diagnostic, Location};
+    use crate::color::Palette;
+    use std::path::PathBuf;
+
+    const OUTPUT: &str = r#"{"reason":"compiler-message","package_id":"path+file:///p#0.1.0","manifest_path":"/p/Cargo.toml","target":{"kind":["bin"],"name":"a"},"message":{"rendered":"warning: unused variable","$message_type":"diagnostic","children":[],"code":{"code":"unused_variables","explanation":null},"level":"warning","message":"unused variable: `m`","spans":[{"file_name":"src/bin/a.rs","line_start":3,"column_start":9,"is_primary":true}]}}
+{"reason":"compiler-message","package_id":"path+file:///p#0.1.0","manifest_path":"/p/Cargo.toml","target":{"kind":["bin"],"name":"a"},"message":{"rendered":"error[E0308]: mismatched types","$message_type":"diagnostic","children":[],"code":{"code":"E0308","explanation":"..."},"level":"error","message":"mismatched types","spans":[{"file_name":"src/bin/a.rs","line_start":4,"column_start":20,"is_primary":false},{"file_name":"src/bin/a.rs","line_start":5,"column_start":13,"is_primary":true}]}}
+{"reason":"compiler-message","package_id":"path+file:///p#0.1.0","manifest_path":"/p/Cargo.toml","target":{"kind":["bin"],"name":"a"},"message":{"rendered":"error: aborting due to 1 previous error","$message_type":"diagnostic","children":[],"code":null,"level":"error","message":"aborting due to 1 previous error","spans":[]}}
+{"reason":"build-finished","success":false}
+"#;
+
+    #[test]
+    fn parses_errors() {
+        assert_eq!(
+            parse_messages(OUTPUT),
+            Compilation {
+                executable: None,
+                diagnostics: vec![Diagnostic {
+                    location: Some(Location {
+                        file: "src/bin/a.rs".to_string(),
+                        line: 5,
+                        column: 13,
+                    }),
+                    code: Some("E0308".to_string()),
+                    message: "mismatched types".to_string(),
+                }],
+            }
+        );
+    }
+
+    #[test]
+    fn parses_the_executable() {
+        let output = r#"{"reason":"compiler-artifact","package_id":"path+file:///p#0.1.0","target":{"kind":["bin"],"name":"a"},"filenames":["/p/target/debug/a"],"executable":"/p/target/debug/a","fresh":true}
+   Compiling p v0.1.0
+{"reason":"build-finished","success":true}"#;
+        assert_eq!(
+            parse_messages(output).executable,
+            Some(PathBuf::from("/p/target/debug/a"))
+        );
+    }
+
+    #[test]
+    fn renders_the_first_errors() {
+        let diagnostic = |line| Diagnostic {
+            location: Some(Location {
+                file: "src/bin/a.rs".to_string(),
+                line,
+                column: 5,
+            }),
+            code: Some("E0425".to_string()),
+            message: "cannot find value `x` in this scope".to_string(),
+        };
+        let error = CompileError {
+            exit_code: 101,
+            diagnostics: (1..=7).map(diagnostic).collect(),
+        };
+        assert_eq!(
+            error.to_string(),
+            "CE: 7 errors
+  src/bin/a.rs:1:5 error[E0425]: cannot find value `x` in this scope
+  src/bin/a.rs:2:5 error[E0425]: cannot find value `x` in this scope
+  src/bin/a.rs:3:5 error[E0425]: cannot find value `x` in this scope
+  src/bin/a.rs:4:5 error[E0425]: cannot find value `x` in this scope
+  src/bin/a.rs:5:5 error[E0425]: cannot find value `x` in this scope
+  ... and 2 more"
+        );
+        let linker = CompileError {
+            exit_code: 101,
+            diagnostics: vec![Diagnostic {
+                location: None,
+                code: None,
+                message: "linking with `cc` failed: exit status: 1".to_string(),
+            }],
+        };
+        assert_eq!(
+            linker.to_string(),
+            "CE: 1 error\n  error: linking with `cc` failed: exit status: 1"
+        );
+        let cargo = CompileError {
+            exit_code: 101,
+            diagnostics: Vec::new(),
+        };
+        assert_eq!(cargo.to_string(), "CE: `cargo build` exited with 101");
+        assert_eq!(
+            cargo.render(Palette::COLORED),
+            "\x1b[1;31mCE:\x1b[0m `cargo build` exited with 101"
+        );
+    }
+}
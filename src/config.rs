@@ -1,3 +1,5 @@
+use clap::ValueEnum;
+
 pub const TEMPLATE: &str = "\
 use proconio::*;
 
@@ -7,4 +9,42 @@ fn main() {
 }
 ";
 
+/// The template that runs the solution on a thread with a 1 GiB stack, for deep recursion.
+pub const LARGE_STACK_TEMPLATE: &str = "\
+use proconio::*;
+
+fn main() {
+    // The main thread only gets the stack size limit, often 8 MiB.
+    std::thread::Builder::new()
+        .stack_size(1 << 30)
+        .spawn(solve)
+        .unwrap()
+        .join()
+        .unwrap();
+}
+
+fn solve() {
+    input! {
+    }
+}
+";
+
+/// The source file a new task starts from.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Template {
+    #[default]
+    Default,
+    /// Runs `main` on a thread with a large stack.
+    LargeStack,
+}
+
+impl Template {
+    pub fn source(self) -> &'static str {
+        match self {
+            Template::Default => TEMPLATE,
+            Template::LargeStack => LARGE_STACK_TEMPLATE,
+        }
+    }
+}
+
 pub const TESTCASE_DIR_NAME: &str = "testcase";
@@ -0,0 +1,713 @@
+use crate::checker::{CompareMode, Difference, Position};
+use crate::color::{Palette, Style};
+
+use clap::{Args, ValueEnum};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use std::fmt::Display;
+
+/// How a wrong answer is displayed.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DiffStyle {
+    /// The last lines up to the mismatch with a caret.
+    #[default]
+    Tail,
+    /// Unified diff with context before and after the mismatch.
+    Unified,
+    /// Expected output and program output in two columns.
+    SideBySide,
+    /// 2-D view highlighting the differing cells of a grid.
+    Grid,
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct DiffOptions {
+    /// How to display a wrong answer.
+    #[arg(long = "diff", value_enum, default_value_t = DiffStyle::default())]
+    pub style: DiffStyle,
+    /// Number of context lines around the mismatch.
+    #[arg(long, default_value_t = 3)]
+    pub context: usize,
+    /// Maximum number of lines of a rendered diff.
+    #[arg(long, default_value_t = 40)]
+    pub diff_max_lines: usize,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            style: DiffStyle::default(),
+            context: 3,
+            diff_max_lines: 40,
+        }
+    }
+}
+
+const TAIL_LINES: usize = 5;
+const MAX_COLUMN_WIDTH: usize = 60;
+
+impl DiffOptions {
+    /// Number of lines to show before the mismatching line.
+    fn lines_before(&self) -> usize {
+        self.context.max(TAIL_LINES - 1)
+    }
+
+    /// Number of lines to show after the mismatching line.
+    fn lines_after(&self) -> usize {
+        match self.style {
+            DiffStyle::Tail => 0,
+            _ => (2 * self.context + 1).min(self.diff_max_lines),
+        }
+    }
+}
+
+/// Lines of one output around the first difference.
+#[derive(Debug)]
+pub struct Excerpt {
+    /// 1-based line number of `lines[0]`.
+    pub first_line: usize,
+    /// Where the outputs first differ.
+    pub position: Position,
+    pub lines: Vec<String>,
+}
+
+impl Excerpt {
+    /// Cuts the lines around `position` out of `text`.
+    pub fn new(text: &str, position: Position, before: usize, after: usize) -> Self {
+        let first_line = position.line.saturating_sub(before).max(1);
+        let lines = text
+            .lines()
+            .skip(first_line - 1)
+            .take(position.line + after + 1 - first_line)
+            .map(str::to_string)
+            .collect();
+        Self {
+            first_line,
+            position,
+            lines,
+        }
+    }
+
+    /// Index of the differing line in `lines`.
+    fn index(&self) -> usize {
+        self.position.line - self.first_line
+    }
+
+    fn line_number(&self, index: usize) -> usize {
+        self.first_line + index
+    }
+
+    /// The trimmed line `offset` lines away from the differing line.
+    fn row(&self, offset: isize) -> Option<&str> {
+        let index = self.index().checked_add_signed(offset)?;
+        self.lines.get(index).map(|l| l.trim())
+    }
+}
+
+/// Lines around the first difference between the program output and the expected output.
+#[derive(Debug)]
+pub struct Mismatch {
+    pub actual: Excerpt,
+    pub expect: Excerpt,
+    /// Decides which lines match when aligning the excerpts.
+    pub mode: CompareMode,
+}
+
+impl Mismatch {
+    pub fn new(
+        actual: &str,
+        expect: &str,
+        difference: Difference,
+        mode: CompareMode,
+        options: &DiffOptions,
+    ) -> Self {
+        let (before, after) = (options.lines_before(), options.lines_after());
+        Self {
+            actual: Excerpt::new(actual, difference.actual, before, after),
+            expect: Excerpt::new(expect, difference.expect, before, after),
+            mode,
+        }
+    }
+
+    pub fn render(&self, options: &DiffOptions, palette: Palette) -> String {
+        let lines = match options.style {
+            DiffStyle::Tail => self.render_tail(palette),
+            DiffStyle::Unified => self.render_unified(options.context, palette),
+            DiffStyle::SideBySide => self.render_side_by_side(options.context, palette),
+            DiffStyle::Grid => self.render_grid(options.context, palette),
+        };
+        cap_lines(lines, options.diff_max_lines).join("\n")
+    }
+
+    fn render_tail(&self, p: Palette) -> Vec<String> {
+        let excerpt = |excerpt: &Excerpt| {
+            let end = excerpt.index() + 1;
+            let start = end.saturating_sub(TAIL_LINES);
+            let mut lines: Vec<String> = excerpt
+                .lines
+                .iter()
+                .take(end)
+                .skip(start)
+                .map(|line| format!("{:4}{}", " ", line))
+                .collect();
+            let width = excerpt
+                .lines
+                .get(excerpt.index())
+                .map_or(0, |line| caret_width(line, excerpt.position.column));
+            lines.push(format!(
+                "{:4}{:width$}{}",
+                " ",
+                "",
+                p.paint(Style::Error, "^")
+            ));
+            lines
+        };
+        let mut lines = vec![p.paint(
+            Style::Header,
+            format!("Program output(Line {}):", self.actual.position.line),
+        )];
+        lines.extend(excerpt(&self.actual));
+        lines.push(p.paint(Style::Header, "expect:"));
+        lines.extend(excerpt(&self.expect));
+        lines
+    }
+
+    /// Edit script of the excerpts, trimmed to `context` equal lines around the first change.
+    fn hunk(&self, context: usize) -> Vec<Edit> {
+        let edits = edit_script(&self.expect.lines, &self.actual.lines, self.mode);
+        let Some(first_change) = edits.iter().position(|e| !matches!(e, Edit::Equal(..))) else {
+            return edits;
+        };
+        let start = first_change.saturating_sub(context);
+        let mut end = edits.len();
+        let mut equal_run = 0;
+        for (i, edit) in edits.iter().enumerate().skip(first_change) {
+            if matches!(edit, Edit::Equal(..)) {
+                if equal_run == context {
+                    end = i;
+                    break;
+                }
+                equal_run += 1;
+            } else {
+                equal_run = 0;
+            }
+        }
+        edits[start..end].to_vec()
+    }
+
+    fn render_unified(&self, context: usize, p: Palette) -> Vec<String> {
+        let hunk = self.hunk(context);
+        let (expect_start, actual_start) = match hunk.first() {
+            Some(Edit::Equal(e, a)) => (*e, *a),
+            Some(Edit::Delete(e)) => (*e, next_actual(&hunk)),
+            Some(Edit::Insert(a)) => (next_expect(&hunk), *a),
+            None => (self.expect.index(), self.actual.index()),
+        };
+        let expect_count = hunk
+            .iter()
+            .filter(|e| !matches!(e, Edit::Insert(_)))
+            .count();
+        let actual_count = hunk
+            .iter()
+            .filter(|e| !matches!(e, Edit::Delete(_)))
+            .count();
+        let mut lines = vec![
+            p.paint(Style::Header, "--- expect"),
+            p.paint(Style::Header, "+++ output"),
+            p.paint(
+                Style::Dim,
+                format!(
+                    "@@ -{},{} +{},{} @@",
+                    self.expect.line_number(expect_start),
+                    expect_count,
+                    self.actual.line_number(actual_start),
+                    actual_count
+                ),
+            ),
+        ];
+        for edit in &hunk {
+            lines.push(match *edit {
+                Edit::Equal(e, _) => format!(" {}", self.expect.lines[e]),
+                Edit::Delete(e) => p.paint(Style::Removed, format!("-{}", self.expect.lines[e])),
+                Edit::Insert(a) => p.paint(Style::Added, format!("+{}", self.actual.lines[a])),
+            });
+        }
+        lines
+    }
+
+    fn render_side_by_side(&self, context: usize, p: Palette) -> Vec<String> {
+        let hunk = self.hunk(context);
+        // (expected line, program output line, marker)
+        let mut rows: Vec<(Option<usize>, Option<usize>, char)> = Vec::new();
+        let mut i = 0;
+        while i < hunk.len() {
+            if let Edit::Equal(e, a) = hunk[i] {
+                rows.push((Some(e), Some(a), ' '));
+                i += 1;
+                continue;
+            }
+            let mut deleted = Vec::new();
+            let mut inserted = Vec::new();
+            while i < hunk.len() {
+                match hunk[i] {
+                    Edit::Delete(e) => deleted.push(e),
+                    Edit::Insert(a) => inserted.push(a),
+                    Edit::Equal(..) => break,
+                }
+                i += 1;
+            }
+            for k in 0..deleted.len().max(inserted.len()) {
+                let (e, a) = (deleted.get(k).copied(), inserted.get(k).copied());
+                let marker = match (e, a) {
+                    (Some(_), Some(_)) => '|',
+                    (Some(_), None) => '<',
+                    _ => '>',
+                };
+                rows.push((e, a, marker));
+            }
+        }
+
+        let text = |excerpt: &Excerpt, i: Option<usize>| {
+            i.map_or(String::new(), |i| truncate(&excerpt.lines[i]))
+        };
+        let width = rows
+            .iter()
+            .map(|&(e, _, _)| text_width(&text(&self.expect, e)))
+            .chain(std::iter::once("expect".len()))
+            .max()
+            .unwrap_or(0);
+        let number = |excerpt: &Excerpt, i: Option<usize>| {
+            let n = i.map_or(String::new(), |i| excerpt.line_number(i).to_string());
+            p.paint(Style::Dim, format!("{:>5}", n))
+        };
+
+        let mut lines = vec![format!(
+            "{:>5} {}   {:>5} {}",
+            "",
+            pad(p, Style::Header, "expect", width),
+            "",
+            p.paint(Style::Header, "output")
+        )];
+        for (e, a, marker) in rows {
+            let (left_style, right_style) = if marker == ' ' {
+                (None, None)
+            } else {
+                (Some(Style::Removed), Some(Style::Added))
+            };
+            let line = format!(
+                "{} {} {} {} {}",
+                number(&self.expect, e),
+                pad_opt(p, left_style, &text(&self.expect, e), width),
+                paint_opt(p, left_style.map(|_| Style::Error), marker),
+                number(&self.actual, a),
+                paint_opt(p, right_style, text(&self.actual, a))
+            );
+            lines.push(line.trim_end().to_string());
+        }
+        lines
+    }
+
+    fn render_grid(&self, context: usize, p: Palette) -> Vec<String> {
+        // Rows are paired by their offset from the differing line of each output.
+        let offsets: Vec<isize> = (-(context as isize)..=context as isize)
+            .filter(|&k| self.expect.row(k).is_some() || self.actual.row(k).is_some())
+            .collect();
+        // Split rows into whitespace separated tokens, or characters for dense grids.
+        let tokenized = offsets.iter().any(|&k| {
+            self.expect
+                .row(k)
+                .unwrap_or("")
+                .contains(char::is_whitespace)
+        });
+        let cells = |line: &str| -> Vec<String> {
+            if tokenized {
+                line.split_whitespace().map(str::to_string).collect()
+            } else {
+                line.graphemes(true).map(String::from).collect()
+            }
+        };
+        let rows: Vec<(isize, Vec<String>, Vec<String>)> = offsets
+            .iter()
+            .map(|&k| {
+                (
+                    k,
+                    cells(self.expect.row(k).unwrap_or("")),
+                    cells(self.actual.row(k).unwrap_or("")),
+                )
+            })
+            .collect();
+
+        let cell_width = rows
+            .iter()
+            .flat_map(|(_, e, a)| e.iter().chain(a.iter()))
+            .map(|c| text_width(c))
+            .max()
+            .unwrap_or(1);
+        let separator = if tokenized { " " } else { "" };
+        // Cells padded to `cell_width`, the differing ones painted with `style`.
+        let join = |cells: &[String], differs: &[bool], style: Style| -> String {
+            cells
+                .iter()
+                .enumerate()
+                .map(|(i, c)| {
+                    let style = differs[i].then_some(style);
+                    if i + 1 == cells.len() {
+                        paint_opt(p, style, c)
+                    } else {
+                        pad_opt(p, style, c, cell_width)
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(separator)
+        };
+        // Width of a joined row; the last cell is not padded.
+        let row_width = |cells: &[String]| -> usize {
+            cells.last().map_or(0, |last| {
+                (cells.len() - 1) * (cell_width + separator.len()) + text_width(last)
+            })
+        };
+        let grid_width = rows
+            .iter()
+            .map(|(_, e, _)| row_width(e))
+            .chain(std::iter::once("expect".len()))
+            .max()
+            .unwrap_or(0);
+
+        let mut lines = Vec::new();
+        let mut num_diff_cells = 0;
+        for (k, expect, actual) in &rows {
+            let differs: Vec<bool> = (0..expect.len().max(actual.len()))
+                .map(|c| expect.get(c) != actual.get(c))
+                .collect();
+            let line_number = self.expect.position.line as isize + k;
+            lines.push(
+                format!(
+                    "{} {}{} | {}",
+                    p.paint(Style::Dim, format!("{:>5}", line_number)),
+                    join(expect, &differs, Style::Removed),
+                    " ".repeat(grid_width - row_width(expect)),
+                    join(actual, &differs, Style::Highlight)
+                )
+                .trim_end()
+                .to_string(),
+            );
+            if differs.iter().any(|&d| d) {
+                num_diff_cells += differs.iter().filter(|&&d| d).count();
+                let last = differs.iter().rposition(|&d| d).unwrap_or(0);
+                let markers: Vec<String> = differs[..=last]
+                    .iter()
+                    .map(|&d| format!("{:<cell_width$}", if d { "^" } else { "" }))
+                    .collect();
+                lines.push(format!(
+                    "{:>5} {:<grid_width$}   {}",
+                    "",
+                    "",
+                    p.paint(Style::Error, markers.join(separator).trim_end())
+                ));
+            }
+        }
+        lines.insert(
+            0,
+            format!(
+                "{:>5} {}   {}",
+                "",
+                pad(p, Style::Header, "expect", grid_width),
+                p.paint(Style::Header, "output")
+            ),
+        );
+        lines.insert(
+            0,
+            p.paint(
+                Style::Header,
+                format!(
+                    "Grid (line {}, column {}): {} cell(s) differ",
+                    self.actual.position.line,
+                    self.actual.position.column + 1,
+                    num_diff_cells
+                ),
+            ),
+        );
+        lines
+    }
+}
+
+/// Display width of `text` in terminal columns.
+fn text_width(text: &str) -> usize {
+    text.width()
+}
+
+/// Display width of `line` before its `column`-th grapheme.
+fn caret_width(line: &str, column: usize) -> usize {
+    line.graphemes(true).take(column).map(text_width).sum()
+}
+
+/// Paints `text` and pads it with spaces to `width` columns.
+fn pad(p: Palette, style: Style, text: &str, width: usize) -> String {
+    pad_opt(p, Some(style), text, width)
+}
+
+fn pad_opt(p: Palette, style: Option<Style>, text: &str, width: usize) -> String {
+    let padding = " ".repeat(width.saturating_sub(text_width(text)));
+    format!("{}{}", paint_opt(p, style, text), padding)
+}
+
+fn paint_opt(p: Palette, style: Option<Style>, text: impl Display) -> String {
+    match style {
+        Some(style) => p.paint(style, text),
+        None => text.to_string(),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Edit {
+    /// Line of the expected output and line of the program output that match.
+    Equal(usize, usize),
+    /// Line of the expected output missing from the program output.
+    Delete(usize),
+    /// Line of the program output not in the expected output.
+    Insert(usize),
+}
+
+fn next_actual(hunk: &[Edit]) -> usize {
+    hunk.iter()
+        .find_map(|e| match e {
+            Edit::Equal(_, a) | Edit::Insert(a) => Some(*a),
+            Edit::Delete(_) => None,
+        })
+        .unwrap_or(0)
+}
+
+fn next_expect(hunk: &[Edit]) -> usize {
+    hunk.iter()
+        .find_map(|e| match e {
+            Edit::Equal(e, _) | Edit::Delete(e) => Some(*e),
+            Edit::Insert(_) => None,
+        })
+        .unwrap_or(0)
+}
+
+/// Longest common subsequence based edit script from `expect` to `actual`.
+fn edit_script(expect: &[String], actual: &[String], mode: CompareMode) -> Vec<Edit> {
+    let (n, m) = (expect.len(), actual.len());
+    let same = |i: usize, j: usize| mode.lines_equal(&expect[i], &actual[j]);
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if same(i, j) {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let mut edits = Vec::with_capacity(n.max(m));
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && same(i, j) {
+            edits.push(Edit::Equal(i, j));
+            i += 1;
+            j += 1;
+        } else if j == m || (i < n && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            edits.push(Edit::Delete(i));
+            i += 1;
+        } else {
+            edits.push(Edit::Insert(j));
+            j += 1;
+        }
+    }
+    edits
+}
+
+fn truncate(line: &str) -> String {
+    if text_width(line) <= MAX_COLUMN_WIDTH {
+        return line.to_string();
+    }
+    let mut s = String::new();
+    let mut width = 0;
+    for g in line.graphemes(true) {
+        width += text_width(g);
+        if width > MAX_COLUMN_WIDTH - 1 {
+            break;
+        }
+        s.push_str(g);
+    }
+    s.push('…');
+    s
+}
+
+fn cap_lines(mut lines: Vec<String>, max_lines: usize) -> Vec<String> {
+    if lines.len() > max_lines {
+        let omitted = lines.len() - max_lines;
+        lines.truncate(max_lines);
+        lines.push(format!("... ({omitted} more lines)"));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod diff_test {
+    use super::{DiffOptions, DiffStyle, Mismatch};
+    use crate::checker::{Checker, CompareMode};
+    use crate::color::Palette;
+
+    fn render(actual: &str, expect: &str, mode: CompareMode, options: &DiffOptions) -> String {
+        let difference = Checker::from(mode).compare(actual, expect).unwrap();
+        Mismatch::new(actual, expect, difference, mode, options).render(options, Palette::PLAIN)
+    }
+
+    fn render_style(actual: &str, expect: &str, style: DiffStyle) -> String {
+        let options = DiffOptions {
+            style,
+            ..DiffOptions::default()
+        };
+        render(actual, expect, CompareMode::Lines, &options)
+    }
+
+    #[test]
+    fn unified_missing_line() {
+        assert_eq!(
+            render_style(
+                "1\n3\n4\n5\n6\n7\n",
+                "1\n2\n3\n4\n5\n6\n7\n",
+                DiffStyle::Unified
+            ),
+            "\
+--- expect
++++ output
+@@ -1,5 +1,4 @@
+ 1
+-2
+ 3
+ 4
+ 5"
+        );
+    }
+
+    #[test]
+    fn unified_changed_line() {
+        assert_eq!(
+            render_style(
+                "a\nb\nc\nd\neX\nf\n",
+                "a\nb\nc\nd\nee\nf\n",
+                DiffStyle::Unified
+            ),
+            "\
+--- expect
++++ output
+@@ -2,5 +2,5 @@
+ b
+ c
+ d
+-ee
++eX
+ f"
+        );
+    }
+
+    #[test]
+    fn unified_shifted_tokens() {
+        let options = DiffOptions {
+            style: DiffStyle::Unified,
+            context: 1,
+            ..DiffOptions::default()
+        };
+        assert_eq!(
+            render(
+                "1 2\n3\n4\n5 X\n",
+                "1\n2 3\n4 5\n6\n",
+                CompareMode::Tokens,
+                &options
+            ),
+            "\
+--- expect
++++ output
+@@ -1,4 +1,4 @@
+-1
+-2 3
+-4 5
+-6
++1 2
++3
++4
++5 X"
+        );
+    }
+
+    #[test]
+    fn side_by_side() {
+        assert_eq!(
+            render_style("Yes\nYes\n", "Yes\nNo\nYes\n", DiffStyle::SideBySide),
+            "      expect         output
+    1 Yes          1 Yes
+    2 No     <
+    3 Yes          2 Yes"
+        );
+    }
+
+    #[test]
+    fn tail_full_width_caret() {
+        assert_eq!(
+            render_style(" こんばんは\n", "こんにちは\n", DiffStyle::Tail),
+            "Program output(Line 1):
+     こんばんは
+         ^
+expect:
+    こんにちは
+        ^"
+        );
+    }
+
+    #[test]
+    fn grid_highlights_cells() {
+        assert_eq!(
+            render_style("#.#\n..#\n#.#\n", "#.#\n.#.\n#.#\n", DiffStyle::Grid),
+            "Grid (line 2, column 2): 2 cell(s) differ
+      expect   output
+    1 #.#    | #.#
+    2 .#.    | ..#
+                ^^
+    3 #.#    | #.#"
+        );
+    }
+
+    #[test]
+    fn grid_tokens() {
+        assert_eq!(
+            render_style("1 11 100\n", "1 10 100\n", DiffStyle::Grid),
+            "Grid (line 1, column 4): 1 cell(s) differ
+      expect        output
+    1 1   10  100 | 1   11  100
+                        ^"
+        );
+    }
+
+    #[test]
+    fn grid_full_width_cells() {
+        assert_eq!(
+            render_style("＃＃\n", "＃．\n", DiffStyle::Grid),
+            "Grid (line 1, column 2): 1 cell(s) differ
+      expect   output
+    1 ＃．   | ＃＃
+                 ^"
+        );
+    }
+
+    #[test]
+    fn max_lines_cap() {
+        let options = DiffOptions {
+            style: DiffStyle::Unified,
+            context: 3,
+            diff_max_lines: 4,
+        };
+        assert_eq!(
+            render("w\nx\ny\nz\n", "a\nb\nc\nd\n", CompareMode::Lines, &options),
+            "\
+--- expect
++++ output
+@@ -1,4 +1,4 @@
+-a
+... (7 more lines)"
+        );
+    }
+}
@@ -0,0 +1,233 @@
+use crate::syscommand::{SysChild, SysCommand};
+
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::process::{Command, Stdio};
+
+/// An input and its expected output taken from a problem statement.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Sample {
+    pub input: String,
+    pub output: String,
+}
+
+#[derive(Debug)]
+struct FetchError {
+    url: String,
+    exit_code: i32,
+}
+
+impl Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "failed to fetch {} (curl exited with {})",
+            self.url, self.exit_code
+        )
+    }
+}
+
+impl Error for FetchError {}
+
+/// A contest or task name that no AtCoder URL can be made of.
+#[derive(Debug)]
+struct InvalidName {
+    kind: &'static str,
+    name: String,
+}
+
+impl Display for InvalidName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}` is not a {} name of AtCoder", self.name, self.kind)
+    }
+}
+
+impl Error for InvalidName {}
+
+/// URL of the statement of `task` in `contest`, e.g. `https://atcoder.jp/contests/abc326/tasks/abc326_a`.
+///
+/// A task named with trailing digits, such as `a2` for a second attempt at `a`, is that of its
+/// letters. The Ex problems of ABC are served as `h`.
+pub fn task_url(contest: &str, task: &str) -> Result<String, Box<dyn Error>> {
+    let invalid = |kind, name: &str| {
+        Box::new(InvalidName {
+            kind,
+            name: name.to_string(),
+        })
+    };
+    let contest_valid = !contest.is_empty()
+        && contest
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !contest_valid {
+        return Err(invalid("contest", contest));
+    }
+    let problem = task.trim_end_matches(|c: char| c.is_ascii_digit());
+    if problem.is_empty() || !problem.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(invalid("task", task));
+    }
+    let contest = contest.to_lowercase();
+    let problem = match problem.to_lowercase().as_str() {
+        "ex" => "h".to_string(),
+        problem => problem.to_string(),
+    };
+    Ok(format!(
+        "https://atcoder.jp/contests/{contest}/tasks/{contest}_{problem}"
+    ))
+}
+
+/// `curl` command downloading `url` to stdout.
+pub fn curl(url: &str) -> Command {
+    let mut curl = Command::new("curl");
+    curl.args(["--silent", "--show-error", "--fail", "--location", url])
+        .stdout(Stdio::piped());
+    curl
+}
+
+/// Downloads a problem statement with `download` and extracts its samples.
+pub fn fetch_samples<S, C>(url: &str, mut download: S) -> Result<Vec<Sample>, Box<dyn Error>>
+where
+    S: SysCommand<C>,
+    C: SysChild,
+{
+    let mut child = download.spawn()?;
+    let mut html = Vec::new();
+    let mut buf = [0; 8192];
+    loop {
+        match child.stdout_read(&mut buf)? {
+            0 => break,
+            n => html.extend_from_slice(&buf[..n]),
+        }
+    }
+    let exit_code = child.exit_code()?;
+    if exit_code != 0 {
+        return Err(Box::new(FetchError {
+            url: url.to_string(),
+            exit_code,
+        }));
+    }
+    Ok(parse_samples(&String::from_utf8_lossy(&html)))
+}
+
+/// Extracts the samples of an AtCoder problem statement.
+///
+/// The English statement is preferred; pages without one fall back to the Japanese headings.
+pub fn parse_samples(html: &str) -> Vec<Sample> {
+    let english = parse_numbered(html, "Sample Input", "Sample Output");
+    if english.is_empty() {
+        parse_numbered(html, "入力例", "出力例")
+    } else {
+        english
+    }
+}
+
+fn parse_numbered(html: &str, input_heading: &str, output_heading: &str) -> Vec<Sample> {
+    let mut samples = Vec::new();
+    for n in 1.. {
+        let input = pre_after(html, &format!("{input_heading} {n}<"));
+        let output = pre_after(html, &format!("{output_heading} {n}<"));
+        match (input, output) {
+            (Some(input), Some(output)) => samples.push(Sample { input, output }),
+            _ => break,
+        }
+    }
+    samples
+}
+
+/// Contents of the first `<pre>` element after `heading`.
+fn pre_after(html: &str, heading: &str) -> Option<String> {
+    let rest = &html[html.find(heading)?..];
+    let rest = &rest[rest.find("<pre")?..];
+    let rest = &rest[rest.find('>')? + 1..];
+    let contents = &rest[..rest.find("</pre>")?];
+    let mut text = unescape(contents).replace("\r\n", "\n");
+    if !text.ends_with('\n') {
+        text.push('\n');
+    }
+    Some(text)
+}
+
+fn unescape(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod fetch_test {
+    use super::{fetch_samples, parse_samples, task_url, Sample};
+    use crate::syscommand::syscommand_test::DummyCommand;
+
+    const HTML: &str = r#"
+<span class="lang-ja">
+<div class="part"><section><h3>入力例 1</h3><pre>3
+</pre></section></div>
+<div class="part"><section><h3>出力例 1</h3><pre>Yes
+</pre></section></div>
+</span>
+<span class="lang-en">
+<div class="part"><section><h3>Sample Input 1</h3><pre>3
+</pre></section></div>
+<div class="part"><section><h3>Sample Output 1</h3><pre>Yes
+</pre></section></div>
+<div class="part"><section><h3>Sample Input 2</h3><pre id="pre-sample2">1 &lt; 2</pre></section></div>
+<div class="part"><section><h3>Sample Output 2</h3><pre>A&amp;B
+</pre></section></div>
+</span>
+"#;
+
+    fn sample(input: &str, output: &str) -> Sample {
+        Sample {
+            input: input.to_string(),
+            output: output.to_string(),
+        }
+    }
+
+    #[test]
+    fn english_samples() {
+        assert_eq!(
+            parse_samples(HTML),
+            vec![sample("3\n", "Yes\n"), sample("1 < 2\n", "A&B\n")]
+        );
+    }
+
+    #[test]
+    fn japanese_samples() {
+        let html = HTML.split("<span class=\"lang-en\">").next().unwrap();
+        assert_eq!(parse_samples(html), vec![sample("3\n", "Yes\n")]);
+    }
+
+    #[test]
+    fn url() {
+        assert_eq!(
+            task_url("ABC326", "A").unwrap(),
+            "https://atcoder.jp/contests/abc326/tasks/abc326_a"
+        );
+        assert_eq!(
+            task_url("abc326", "ex2").unwrap(),
+            "https://atcoder.jp/contests/abc326/tasks/abc326_h"
+        );
+        assert_eq!(
+            task_url("abc326", "Ex").unwrap(),
+            "https://atcoder.jp/contests/abc326/tasks/abc326_h"
+        );
+        assert!(task_url("abc326", "a_fast").is_err());
+        assert!(task_url("abc326", "2").is_err());
+        assert!(task_url("my contest", "a").is_err());
+    }
+
+    #[test]
+    fn download_failure() {
+        let curl = DummyCommand {
+            exit_code: 22,
+            stdout: String::new(),
+        };
+        let err = fetch_samples("https://atcoder.jp/", curl).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "failed to fetch https://atcoder.jp/ (curl exited with 22)"
+        );
+    }
+}
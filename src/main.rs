@@ -1,6 +1,20 @@
+mod ahc;
+mod bench;
+mod calibrate;
+mod cases;
+mod checker;
 mod cmd;
+mod color;
+mod compile;
 mod config;
 mod data;
+mod diff;
+mod fetch;
+mod project;
+mod report;
+mod runner;
+mod sandbox;
+mod scale;
 mod syscommand;
 
 use std::process::ExitCode;
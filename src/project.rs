@@ -0,0 +1,54 @@
+use std::error::Error;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+/// Finds the root of the cargo project containing `start`, the nearest directory with a `Cargo.toml`.
+pub fn find_root(start: &Path) -> Result<PathBuf, Box<dyn Error>> {
+    for dir in start.ancestors() {
+        if dir.join("Cargo.toml").try_exists()? {
+            return Ok(dir.to_path_buf());
+        }
+    }
+    Err(Box::new(io::Error::new(
+        ErrorKind::NotFound,
+        format!(
+            "`{}` is not inside a cargo project. Create one with `atcoder new`.",
+            start.display()
+        ),
+    )))
+}
+
+#[cfg(test)]
+pub(crate) mod project_test {
+    use super::find_root;
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// An empty directory for a test, removed when dropped.
+    pub(crate) struct TempDir(pub(crate) PathBuf);
+
+    impl TempDir {
+        pub(crate) fn new(name: &str) -> Self {
+            let dir = env::temp_dir().join(format!("atcoder-rs-{}-{name}", std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn root_from_subdirectory() {
+        let tmp = TempDir::new("find-root");
+        fs::create_dir_all(tmp.0.join("src/bin")).unwrap();
+        fs::write(tmp.0.join("Cargo.toml"), "").unwrap();
+        assert_eq!(find_root(&tmp.0.join("src/bin")).unwrap(), tmp.0);
+        assert_eq!(find_root(&tmp.0).unwrap(), tmp.0);
+    }
+}
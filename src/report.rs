@@ -0,0 +1,652 @@
+use crate::compile::CompileError;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use std::fmt::Write;
+use std::time::Duration;
+
+/// Version of the machine-readable report layout.
+///
+/// Bump it whenever a field is renamed or removed, or its meaning changes.
+pub const REPORT_VERSION: u32 = 1;
+
+/// Output format of the test results.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Human,
+    Json,
+    Junit,
+    Tap,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    #[serde(rename = "AC")]
+    Accepted,
+    #[serde(rename = "WA")]
+    WrongAnswer,
+    #[serde(rename = "RE")]
+    RuntimeError,
+    #[serde(rename = "TLE")]
+    TimeLimitExceeded,
+    #[serde(rename = "OLE")]
+    OutputLimitExceeded,
+}
+
+impl Verdict {
+    pub fn code(self) -> &'static str {
+        match self {
+            Verdict::Accepted => "AC",
+            Verdict::WrongAnswer => "WA",
+            Verdict::RuntimeError => "RE",
+            Verdict::TimeLimitExceeded => "TLE",
+            Verdict::OutputLimitExceeded => "OLE",
+        }
+    }
+
+    pub fn is_accepted(self) -> bool {
+        self == Verdict::Accepted
+    }
+}
+
+/// Where the program output first differs from the expected output.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DiffLocation {
+    /// 1-based line number.
+    pub line: usize,
+    /// 0-based column.
+    pub column: usize,
+    /// The case of a multi-testcase input that the line belongs to.
+    pub sub_case: Option<SubCase>,
+}
+
+/// A case within an input that holds several.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SubCase {
+    /// 1-based.
+    pub index: usize,
+    pub count: usize,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct CaseReport {
+    pub name: String,
+    pub verdict: Verdict,
+    /// Wall-clock time.
+    pub time_ms: u64,
+    /// CPU time spent in the program itself.
+    pub user_ms: Option<u64>,
+    /// CPU time spent in the kernel on behalf of the program.
+    pub sys_ms: Option<u64>,
+    pub memory_kb: Option<u64>,
+    pub diff: Option<DiffLocation>,
+    pub stderr: String,
+    pub message: Option<String>,
+    pub tags: Vec<String>,
+    /// The verdict the case is meant to get, when it is not AC.
+    pub expect_verdict: Option<Verdict>,
+}
+
+impl CaseReport {
+    /// Whether the case got the verdict it is meant to get.
+    pub fn passed(&self) -> bool {
+        self.verdict == self.expect_verdict.unwrap_or(Verdict::Accepted)
+    }
+
+    /// JUnit element for a case that did not pass: `error` for RE, `failure` otherwise.
+    fn junit_tag(&self) -> Option<&'static str> {
+        match self.verdict {
+            _ if self.passed() => None,
+            Verdict::RuntimeError => Some("error"),
+            _ => Some("failure"),
+        }
+    }
+
+    pub fn time(&self) -> Duration {
+        Duration::from_millis(self.time_ms)
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Report {
+    pub version: u32,
+    pub task: String,
+    pub cases: Vec<CaseReport>,
+    /// Why the solution did not compile, in which case no case ran.
+    pub compile_error: Option<CompileError>,
+}
+
+impl Report {
+    pub fn new(task: &str) -> Self {
+        Self {
+            version: REPORT_VERSION,
+            task: task.to_string(),
+            cases: Vec::new(),
+            compile_error: None,
+        }
+    }
+
+    pub fn num_passed(&self) -> usize {
+        self.cases.iter().filter(|c| c.passed()).count()
+    }
+
+    /// Renders the report in a machine-readable `format`; `None` for `Format::Human`.
+    pub fn render(&self, format: Format) -> Option<String> {
+        match format {
+            Format::Human => None,
+            Format::Json => Some(self.to_json()),
+            Format::Junit => Some(self.to_junit()),
+            Format::Tap => Some(self.to_tap()),
+        }
+    }
+
+    fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("a report is always serializable")
+    }
+
+    fn to_junit(&self) -> String {
+        let count = |tag| {
+            self.cases
+                .iter()
+                .filter(|c| c.junit_tag() == Some(tag))
+                .count()
+        };
+        // A compile error is one more test, which errored.
+        let compiled = usize::from(self.compile_error.is_some());
+        let total: Duration = self.cases.iter().map(CaseReport::time).sum();
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let _ = writeln!(
+            xml,
+            "<testsuites name=\"atcoder-rs\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{:.3}\">",
+            self.cases.len() + compiled,
+            count("failure"),
+            count("error") + compiled,
+            total.as_secs_f64()
+        );
+        let _ = writeln!(
+            xml,
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{:.3}\">",
+            xml_escape(&self.task),
+            self.cases.len() + compiled,
+            count("failure"),
+            count("error") + compiled,
+            total.as_secs_f64()
+        );
+        xml.push_str("    <properties>\n");
+        let _ = writeln!(
+            xml,
+            "      <property name=\"report-version\" value=\"{}\"/>",
+            self.version
+        );
+        xml.push_str("    </properties>\n");
+        if let Some(error) = &self.compile_error {
+            let _ = writeln!(
+                xml,
+                "    <testcase name=\"compile\" classname=\"{}\" time=\"0.000\">",
+                xml_escape(&self.task)
+            );
+            let _ = writeln!(
+                xml,
+                "      <error type=\"CE\" message=\"{}\">{}</error>",
+                xml_escape(error.to_string().lines().next().unwrap_or("CE")),
+                xml_escape(&compile_error_lines(error).join("\n"))
+            );
+            xml.push_str("    </testcase>\n");
+        }
+        for case in &self.cases {
+            let _ = writeln!(
+                xml,
+                "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\">",
+                xml_escape(&case.name),
+                xml_escape(&self.task),
+                case.time().as_secs_f64()
+            );
+            let _ = writeln!(
+                xml,
+                "      <properties>\n        <property name=\"verdict\" value=\"{}\"/>",
+                case.verdict.code()
+            );
+            if let (Some(user_ms), Some(sys_ms)) = (case.user_ms, case.sys_ms) {
+                let _ = writeln!(
+                    xml,
+                    "        <property name=\"user_ms\" value=\"{user_ms}\"/>\n        <property name=\"sys_ms\" value=\"{sys_ms}\"/>"
+                );
+            }
+            if let Some(memory_kb) = case.memory_kb {
+                let _ = writeln!(
+                    xml,
+                    "        <property name=\"memory_kb\" value=\"{memory_kb}\"/>"
+                );
+            }
+            if let Some(expect_verdict) = case.expect_verdict {
+                let _ = writeln!(
+                    xml,
+                    "        <property name=\"expect_verdict\" value=\"{}\"/>",
+                    expect_verdict.code()
+                );
+            }
+            if !case.tags.is_empty() {
+                let _ = writeln!(
+                    xml,
+                    "        <property name=\"tags\" value=\"{}\"/>",
+                    xml_escape(&case.tags.join(","))
+                );
+            }
+            xml.push_str("      </properties>\n");
+            if let Some(tag) = case.junit_tag() {
+                let mut message = match case.diff {
+                    Some(DiffLocation { line, column, .. }) => {
+                        format!("{} at line {line}, column {column}", case.verdict.code())
+                    }
+                    None => case.verdict.code().to_string(),
+                };
+                if let Some(SubCase { index, count }) = case.diff.and_then(|d| d.sub_case) {
+                    let _ = write!(message, " in case {index} of {count}");
+                }
+                if let Some(expect_verdict) = case.expect_verdict {
+                    let _ = write!(message, ", expected {}", expect_verdict.code());
+                }
+                let _ = writeln!(
+                    xml,
+                    "      <{tag} type=\"{}\" message=\"{}\">{}</{tag}>",
+                    case.verdict.code(),
+                    xml_escape(&message),
+                    xml_escape(case.message.as_deref().unwrap_or(""))
+                );
+            }
+            if !case.stderr.is_empty() {
+                let _ = writeln!(
+                    xml,
+                    "      <system-err>{}</system-err>",
+                    xml_escape(&case.stderr)
+                );
+            }
+            xml.push_str("    </testcase>\n");
+        }
+        xml.push_str("  </testsuite>\n</testsuites>");
+        xml
+    }
+
+    fn to_tap(&self) -> String {
+        let mut tap = String::from("TAP version 13\n");
+        let _ = writeln!(tap, "# atcoder-rs report version {}", self.version);
+        let compiled = usize::from(self.compile_error.is_some());
+        let _ = writeln!(tap, "1..{}", self.cases.len() + compiled);
+        if let Some(error) = &self.compile_error {
+            tap.push_str("not ok 1 - compile\n  ---\n  verdict: CE\n");
+            let _ = writeln!(tap, "  exit_code: {}", error.exit_code);
+            if !error.diagnostics.is_empty() {
+                tap.push_str("  diagnostics:\n");
+                for line in compile_error_lines(error) {
+                    let _ = writeln!(tap, "    - {}", json_string(&line));
+                }
+            }
+            tap.push_str("  ...\n");
+        }
+        for (i, case) in self.cases.iter().enumerate() {
+            let ok = if case.passed() { "ok" } else { "not ok" };
+            let _ = writeln!(tap, "{ok} {} - {}", i + 1 + compiled, case.name);
+            // YAML diagnostics; JSON strings are valid YAML scalars.
+            tap.push_str("  ---\n");
+            let _ = writeln!(tap, "  verdict: {}", case.verdict.code());
+            let _ = writeln!(tap, "  time_ms: {}", case.time_ms);
+            if let (Some(user_ms), Some(sys_ms)) = (case.user_ms, case.sys_ms) {
+                let _ = writeln!(tap, "  user_ms: {user_ms}\n  sys_ms: {sys_ms}");
+            }
+            if let Some(memory_kb) = case.memory_kb {
+                let _ = writeln!(tap, "  memory_kb: {memory_kb}");
+            }
+            if let Some(expect_verdict) = case.expect_verdict {
+                let _ = writeln!(tap, "  expect_verdict: {}", expect_verdict.code());
+            }
+            if !case.tags.is_empty() {
+                let tags: Vec<String> = case.tags.iter().map(|t| json_string(t)).collect();
+                let _ = writeln!(tap, "  tags: [{}]", tags.join(", "));
+            }
+            if let Some(DiffLocation {
+                line,
+                column,
+                sub_case,
+            }) = case.diff
+            {
+                let _ = writeln!(tap, "  diff:\n    line: {line}\n    column: {column}");
+                if let Some(SubCase { index, count }) = sub_case {
+                    let _ = writeln!(tap, "    case: {index}\n    cases: {count}");
+                }
+            }
+            if !case.stderr.is_empty() {
+                let _ = writeln!(tap, "  stderr: {}", json_string(&case.stderr));
+            }
+            if let Some(message) = &case.message {
+                let _ = writeln!(tap, "  message: {}", json_string(message));
+            }
+            tap.push_str("  ...\n");
+        }
+        tap.pop();
+        tap
+    }
+}
+
+/// Every error of rustc, or how cargo exited when it reported none.
+fn compile_error_lines(error: &CompileError) -> Vec<String> {
+    if error.diagnostics.is_empty() {
+        return vec![format!("`cargo build` exited with {}", error.exit_code)];
+    }
+    error.diagnostics.iter().map(ToString::to_string).collect()
+}
+
+fn json_string(s: &str) -> String {
+    serde_json::to_string(s).expect("a string is always serializable")
+}
+
+fn xml_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Control characters other than tab and newlines are not allowed in XML 1.0.
+            c if c.is_control() && !matches!(c, '\t' | '\n' | '\r') => {
+                let _ = write!(escaped, "\\u{{{:x}}}", c as u32);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod report_test {
+    use super::{CaseReport, DiffLocation, Format, Report, SubCase, Verdict};
+    use crate::compile::{CompileError, Diagnostic, Location};
+
+    fn report() -> Report {
+        let mut report = Report::new("a");
+        report.cases.push(CaseReport {
+            name: "1.txt".to_string(),
+            verdict: Verdict::Accepted,
+            time_ms: 12,
+            user_ms: Some(10),
+            sys_ms: Some(1),
+            memory_kb: Some(2048),
+            diff: None,
+            stderr: String::new(),
+            message: None,
+            tags: Vec::new(),
+            expect_verdict: None,
+        });
+        report.cases.push(CaseReport {
+            name: "2.txt".to_string(),
+            verdict: Verdict::WrongAnswer,
+            time_ms: 1500,
+            user_ms: None,
+            sys_ms: None,
+            memory_kb: None,
+            diff: Some(DiffLocation {
+                line: 3,
+                column: 1,
+                sub_case: None,
+            }),
+            stderr: "debug <x>".to_string(),
+            message: Some("-1\n+2".to_string()),
+            tags: Vec::new(),
+            expect_verdict: None,
+        });
+        report
+    }
+
+    #[test]
+    fn json() {
+        assert_eq!(
+            report().render(Format::Json).unwrap(),
+            r#"{
+  "version": 1,
+  "task": "a",
+  "cases": [
+    {
+      "name": "1.txt",
+      "verdict": "AC",
+      "time_ms": 12,
+      "user_ms": 10,
+      "sys_ms": 1,
+      "memory_kb": 2048,
+      "diff": null,
+      "stderr": "",
+      "message": null,
+      "tags": [],
+      "expect_verdict": null
+    },
+    {
+      "name": "2.txt",
+      "verdict": "WA",
+      "time_ms": 1500,
+      "user_ms": null,
+      "sys_ms": null,
+      "memory_kb": null,
+      "diff": {
+        "line": 3,
+        "column": 1,
+        "sub_case": null
+      },
+      "stderr": "debug <x>",
+      "message": "-1\n+2",
+      "tags": [],
+      "expect_verdict": null
+    }
+  ],
+  "compile_error": null
+}"#
+        );
+    }
+
+    #[test]
+    fn junit() {
+        assert_eq!(
+            report().render(Format::Junit).unwrap(),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="atcoder-rs" tests="2" failures="1" errors="0" time="1.512">
+  <testsuite name="a" tests="2" failures="1" errors="0" time="1.512">
+    <properties>
+      <property name="report-version" value="1"/>
+    </properties>
+    <testcase name="1.txt" classname="a" time="0.012">
+      <properties>
+        <property name="verdict" value="AC"/>
+        <property name="user_ms" value="10"/>
+        <property name="sys_ms" value="1"/>
+        <property name="memory_kb" value="2048"/>
+      </properties>
+    </testcase>
+    <testcase name="2.txt" classname="a" time="1.500">
+      <properties>
+        <property name="verdict" value="WA"/>
+      </properties>
+      <failure type="WA" message="WA at line 3, column 1">-1
++2</failure>
+      <system-err>debug &lt;x&gt;</system-err>
+    </testcase>
+  </testsuite>
+</testsuites>"#
+        );
+    }
+
+    #[test]
+    fn tap() {
+        assert_eq!(
+            report().render(Format::Tap).unwrap(),
+            r#"TAP version 13
+# atcoder-rs report version 1
+1..2
+ok 1 - 1.txt
+  ---
+  verdict: AC
+  time_ms: 12
+  user_ms: 10
+  sys_ms: 1
+  memory_kb: 2048
+  ...
+not ok 2 - 2.txt
+  ---
+  verdict: WA
+  time_ms: 1500
+  diff:
+    line: 3
+    column: 1
+  stderr: "debug <x>"
+  message: "-1\n+2"
+  ..."#
+        );
+    }
+
+    #[test]
+    fn expected_verdict() {
+        let mut report = report();
+        report.cases[1].verdict = Verdict::TimeLimitExceeded;
+        report.cases[1].diff = None;
+        report.cases[1].expect_verdict = Some(Verdict::TimeLimitExceeded);
+        report.cases[1].tags = vec!["max-case".to_string()];
+        assert!(report.cases[1].passed());
+        assert_eq!(report.num_passed(), 2);
+        let tap = report.render(Format::Tap).unwrap();
+        assert!(tap.contains("ok 2 - 2.txt\n  ---\n  verdict: TLE\n  time_ms: 1500\n  expect_verdict: TLE\n  tags: [\"max-case\"]\n"));
+
+        report.cases[1].verdict = Verdict::Accepted;
+        assert!(!report.cases[1].passed());
+        let junit = report.render(Format::Junit).unwrap();
+        assert!(junit.contains("tests=\"2\" failures=\"1\" errors=\"0\""));
+        assert!(junit.contains("<failure type=\"AC\" message=\"AC, expected TLE\">"));
+    }
+
+    #[test]
+    fn sub_case() {
+        let mut report = report();
+        report.cases[1].diff = Some(DiffLocation {
+            line: 57,
+            column: 0,
+            sub_case: Some(SubCase {
+                index: 57,
+                count: 100,
+            }),
+        });
+        let tap = report.render(Format::Tap).unwrap();
+        assert!(
+            tap.contains("  diff:\n    line: 57\n    column: 0\n    case: 57\n    cases: 100\n")
+        );
+        let junit = report.render(Format::Junit).unwrap();
+        assert!(junit.contains("message=\"WA at line 57, column 0 in case 57 of 100\""));
+        let json = report.render(Format::Json).unwrap();
+        assert!(json.contains(
+            "\"sub_case\": {\n          \"index\": 57,\n          \"count\": 100\n        }"
+        ));
+    }
+
+    fn compile_error() -> Report {
+        let mut report = Report::new("a");
+        report.compile_error = Some(CompileError {
+            exit_code: 101,
+            diagnostics: vec![
+                Diagnostic {
+                    location: Some(Location {
+                        file: "src/bin/a.rs".to_string(),
+                        line: 3,
+                        column: 20,
+                    }),
+                    code: Some("E0425".to_string()),
+                    message: "cannot find value `m` in this scope".to_string(),
+                },
+                Diagnostic {
+                    location: None,
+                    code: None,
+                    message: "linking with `cc` failed".to_string(),
+                },
+            ],
+        });
+        report
+    }
+
+    #[test]
+    fn compile_error_json() {
+        assert_eq!(
+            compile_error().render(Format::Json).unwrap(),
+            r#"{
+  "version": 1,
+  "task": "a",
+  "cases": [],
+  "compile_error": {
+    "exit_code": 101,
+    "diagnostics": [
+      {
+        "location": {
+          "file": "src/bin/a.rs",
+          "line": 3,
+          "column": 20
+        },
+        "code": "E0425",
+        "message": "cannot find value `m` in this scope"
+      },
+      {
+        "location": null,
+        "code": null,
+        "message": "linking with `cc` failed"
+      }
+    ]
+  }
+}"#
+        );
+    }
+
+    #[test]
+    fn compile_error_junit() {
+        assert_eq!(
+            compile_error().render(Format::Junit).unwrap(),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="atcoder-rs" tests="1" failures="0" errors="1" time="0.000">
+  <testsuite name="a" tests="1" failures="0" errors="1" time="0.000">
+    <properties>
+      <property name="report-version" value="1"/>
+    </properties>
+    <testcase name="compile" classname="a" time="0.000">
+      <error type="CE" message="CE: 2 errors">src/bin/a.rs:3:20 error[E0425]: cannot find value `m` in this scope
+error: linking with `cc` failed</error>
+    </testcase>
+  </testsuite>
+</testsuites>"#
+        );
+    }
+
+    #[test]
+    fn compile_error_tap() {
+        assert_eq!(
+            compile_error().render(Format::Tap).unwrap(),
+            r#"TAP version 13
+# atcoder-rs report version 1
+1..1
+not ok 1 - compile
+  ---
+  verdict: CE
+  exit_code: 101
+  diagnostics:
+    - "src/bin/a.rs:3:20 error[E0425]: cannot find value `m` in this scope"
+    - "error: linking with `cc` failed"
+  ..."#
+        );
+        let mut cargo = compile_error();
+        cargo.compile_error.as_mut().unwrap().diagnostics.clear();
+        let tap = cargo.render(Format::Tap).unwrap();
+        assert!(tap.ends_with("  verdict: CE\n  exit_code: 101\n  ..."));
+        let junit = cargo.render(Format::Junit).unwrap();
+        assert!(junit.contains(
+            "<error type=\"CE\" message=\"CE: `cargo build` exited with 101\">`cargo build` exited with 101</error>"
+        ));
+    }
+
+    #[test]
+    fn human_is_not_rendered() {
+        assert!(report().render(Format::Human).is_none());
+    }
+}
@@ -0,0 +1,416 @@
+use crate::syscommand::{SysChild, SysCommand, Usage};
+
+use serde::Deserialize;
+
+use std::env;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io::Result;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// The cargo profile solutions are built with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Profile {
+    /// What `cargo run` uses.
+    Dev,
+    /// What the judge uses.
+    Release,
+}
+
+impl Profile {
+    /// Directory of the built binaries under the target directory.
+    fn dir_name(self) -> &'static str {
+        match self {
+            Profile::Dev => "debug",
+            Profile::Release => "release",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct BuildError {
+    exit_code: i32,
+}
+
+impl Display for BuildError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "`cargo build` exited with {}", self.exit_code)
+    }
+}
+
+impl Error for BuildError {}
+
+/// Builds the solutions `bins` with `profile`.
+pub fn build(bins: &[String], profile: Profile) -> std::result::Result<(), Box<dyn Error>> {
+    let mut cargo = Command::new("cargo");
+    cargo.arg("build");
+    if profile == Profile::Release {
+        cargo.arg("--release");
+    }
+    for bin in bins {
+        cargo.arg("--bin").arg(bin);
+    }
+    match SysCommand::status(&mut cargo)? {
+        0 => Ok(()),
+        exit_code => Err(Box::new(BuildError { exit_code })),
+    }
+}
+
+/// Builds `bin` with overflow checks and debug assertions, whatever the profiles of the project
+/// say, and returns its binary.
+///
+/// It goes to a target directory of its own, so that the usual builds stay cached.
+pub fn build_checked(
+    project_root: &Path,
+    bin: &str,
+) -> std::result::Result<PathBuf, Box<dyn Error>> {
+    let target_dir = project_root.join("target/atcoder-rs/checked");
+    let mut cargo = Command::new("cargo");
+    cargo
+        .arg("build")
+        .arg("--bin")
+        .arg(bin)
+        .arg("--target-dir")
+        .arg(&target_dir)
+        .env("CARGO_PROFILE_DEV_OVERFLOW_CHECKS", "true")
+        .env("CARGO_PROFILE_DEV_DEBUG_ASSERTIONS", "true");
+    match SysCommand::status(&mut cargo)? {
+        0 => Ok(target_dir
+            .join(Profile::Dev.dir_name())
+            .join(format!("{bin}{}", env::consts::EXE_SUFFIX))),
+        exit_code => Err(Box::new(BuildError { exit_code })),
+    }
+}
+
+/// The parts of `Cargo.toml` that say whether the dev profile checks overflow.
+#[derive(Deserialize, Default)]
+struct CargoManifest {
+    #[serde(default)]
+    profile: Profiles,
+}
+
+#[derive(Deserialize, Default)]
+struct Profiles {
+    #[serde(default)]
+    dev: DevProfile,
+}
+
+#[derive(Deserialize, Default)]
+struct DevProfile {
+    #[serde(rename = "overflow-checks")]
+    overflow_checks: Option<bool>,
+}
+
+/// Whether the dev profile of the project at `project_root` checks arithmetic overflow, which it
+/// does unless `CARGO_PROFILE_DEV_OVERFLOW_CHECKS` or `Cargo.toml` turns it off.
+pub fn dev_checks_overflow(project_root: &Path) -> std::result::Result<bool, Box<dyn Error>> {
+    let manifest = fs::read_to_string(project_root.join("Cargo.toml"))?;
+    let env = env::var("CARGO_PROFILE_DEV_OVERFLOW_CHECKS").ok();
+    checks_overflow(&manifest, env.as_deref())
+}
+
+fn checks_overflow(manifest: &str, env: Option<&str>) -> std::result::Result<bool, Box<dyn Error>> {
+    if let Some(env) = env {
+        return Ok(env.parse()?);
+    }
+    let manifest: CargoManifest = toml::from_str(manifest)?;
+    Ok(manifest.profile.dev.overflow_checks.unwrap_or(true))
+}
+
+/// Where cargo puts the binary of `bin`, honoring `CARGO_TARGET_DIR`.
+pub fn binary_path(project_root: &Path, bin: &str, profile: Profile) -> PathBuf {
+    let target_dir = env::var_os("CARGO_TARGET_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| project_root.join("target"));
+    target_dir
+        .join(profile.dir_name())
+        .join(format!("{bin}{}", env::consts::EXE_SUFFIX))
+}
+
+/// A built binary with piped stdio.
+pub fn binary(path: &Path) -> Command {
+    let mut command = Command::new(path);
+    command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    command
+}
+
+/// Raises the stack size limit of `command` to `mib` MiB, or as far as the hard limit allows.
+///
+/// The main thread of the program gets a stack of that size.
+#[cfg(unix)]
+pub fn set_stack_size(command: &mut Command, mib: u64) {
+    use std::os::unix::process::CommandExt;
+
+    let bytes = mib.saturating_mul(1 << 20) as libc::rlim_t;
+    // SAFETY: `getrlimit` and `setrlimit` are async-signal-safe and only touch `limit`.
+    unsafe {
+        command.pre_exec(move || {
+            let mut limit: libc::rlimit = std::mem::zeroed();
+            if libc::getrlimit(libc::RLIMIT_STACK, &mut limit) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            limit.rlim_cur = bytes.min(limit.rlim_max);
+            if libc::setrlimit(libc::RLIMIT_STACK, &limit) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+}
+
+#[cfg(not(unix))]
+pub fn set_stack_size(_command: &mut Command, _mib: u64) {}
+
+/// The stack size in bytes that programs get unless it is raised, `None` when unlimited.
+#[cfg(unix)]
+pub fn default_stack_size() -> Option<u64> {
+    // SAFETY: `rlimit` is plain old data, and `getrlimit` only writes to it.
+    let mut limit: libc::rlimit = unsafe { std::mem::zeroed() };
+    if unsafe { libc::getrlimit(libc::RLIMIT_STACK, &mut limit) } != 0
+        || limit.rlim_cur == libc::RLIM_INFINITY
+    {
+        return None;
+    }
+    Some(limit.rlim_cur as u64)
+}
+
+/// The 1 MiB main thread stack Windows gives programs by default.
+#[cfg(not(unix))]
+pub fn default_stack_size() -> Option<u64> {
+    Some(1 << 20)
+}
+
+/// The line where Rust reports that a thread overflowed its stack, if the program died of it.
+///
+/// Rust catches the overflow on the guard page, which raises SIGSEGV, and aborts after the message.
+pub fn stack_overflow(signal: Option<i32>, stderr: &str) -> Option<&str> {
+    #[cfg(unix)]
+    let fatal = matches!(signal, Some(libc::SIGSEGV | libc::SIGABRT));
+    #[cfg(not(unix))]
+    let fatal = signal.is_none();
+    if !fatal {
+        return None;
+    }
+    stderr
+        .lines()
+        .find(|line| line.contains("has overflowed its stack"))
+}
+
+/// Where and why a Rust program panicked.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Panic {
+    /// Such as `src/bin/a.rs:5:13`.
+    pub location: String,
+    /// The first line of the message.
+    pub message: String,
+}
+
+impl Panic {
+    /// Whether an arithmetic overflow check failed, such as `attempt to add with overflow`.
+    pub fn is_overflow(&self) -> bool {
+        self.message.starts_with("attempt to ") && self.message.ends_with(" with overflow")
+    }
+}
+
+/// The first panic reported in `stderr`, as `thread 'main' (42) panicked at src/bin/a.rs:5:13:`
+/// followed by the message.
+pub fn find_panic(stderr: &str) -> Option<Panic> {
+    let mut lines = stderr.lines();
+    while let Some(line) = lines.next() {
+        if let Some((_, location)) = line.split_once(" panicked at ") {
+            return Some(Panic {
+                location: location.strip_suffix(':').unwrap_or(location).to_string(),
+                message: lines.next().unwrap_or_default().to_string(),
+            });
+        }
+    }
+    None
+}
+
+/// Output beyond which a program is killed, as a runaway loop can print without end.
+pub const OUTPUT_LIMIT: usize = 64 << 20;
+
+/// What a program printed and how it ended.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Execution {
+    pub stdout: String,
+    /// `None` when the program was killed by a signal.
+    pub exit_code: Option<i32>,
+    pub timed_out: bool,
+    /// Whether the program was killed for printing more than its output limit.
+    pub output_limit_exceeded: bool,
+    pub time: Duration,
+    pub usage: Option<Usage>,
+    pub stderr: String,
+    /// Why the program could not be started or waited for, when it could not.
+    pub error: Option<String>,
+}
+
+impl Execution {
+    /// A program that could not be started or waited for because of `error`.
+    pub fn not_run(error: impl Display) -> Self {
+        Self {
+            error: Some(error.to_string()),
+            ..Self::default()
+        }
+    }
+
+    /// Whether the program exited normally with status 0.
+    pub fn succeeded(&self) -> bool {
+        !self.timed_out && !self.output_limit_exceeded && self.exit_code == Some(0)
+    }
+}
+
+/// Reads the output of `child` to its end, or kills it once it printed more than `limit` bytes,
+/// keeping only the first `limit`. The flag tells whether it was killed.
+pub fn read_output<C: SysChild>(child: &mut C, limit: usize) -> Result<(String, bool)> {
+    let mut output = Vec::new();
+    let mut buf = [0u8; 8192];
+    let exceeded = loop {
+        let n = child.stdout_read(&mut buf)?;
+        if n == 0 {
+            break false;
+        }
+        if output.len() + n > limit {
+            output.extend_from_slice(&buf[..limit - output.len()]);
+            let _ = child.kill();
+            break true;
+        }
+        output.extend_from_slice(&buf[..n]);
+    };
+    Ok((String::from_utf8_lossy(&output).into_owned(), exceeded))
+}
+
+/// Writes the whole of `input` to the stdin of `child` and closes it.
+///
+/// A program may exit without reading all its input, which closes the pipe; the rest is dropped.
+pub fn feed_input<C: SysChild>(child: &mut C, input: &[u8]) {
+    let mut written = 0;
+    while written < input.len() {
+        match child.stdin_write(&input[written..]) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => written += n,
+        }
+    }
+    child.close_stdin();
+}
+
+/// Feeds `input` to a spawned program, then reads at most `output_limit` bytes of its output
+/// and waits for it.
+pub fn execute<C: SysChild>(
+    child: &mut C,
+    input: &[u8],
+    output_limit: usize,
+    start: Instant,
+) -> Result<Execution> {
+    feed_input(child, input);
+    let (stdout, output_limit_exceeded) = read_output(child, output_limit)?;
+    let exit_code = child.exit_code().ok();
+    Ok(Execution {
+        stdout,
+        exit_code,
+        timed_out: child.timed_out(),
+        output_limit_exceeded,
+        time: start.elapsed(),
+        usage: child.usage(),
+        stderr: child.stderr_excerpt(),
+        error: None,
+    })
+}
+
+#[cfg(test)]
+mod runner_test {
+    use super::{
+        checks_overflow, execute, find_panic, read_output, stack_overflow, Panic, OUTPUT_LIMIT,
+    };
+    use crate::syscommand::syscommand_test::DummyCommand;
+    use crate::syscommand::SysCommand;
+    use std::time::Instant;
+
+    #[test]
+    fn dev_overflow_checks() {
+        let manifest = "[package]\nname = \"p\"\n\n[dependencies]\nproconio = \"0.4\"\n";
+        assert!(checks_overflow(manifest, None).unwrap());
+        let unchecked = "[package]\nname = \"p\"\n\n[profile.dev]\noverflow-checks = false\n";
+        assert!(!checks_overflow(unchecked, None).unwrap());
+        assert!(checks_overflow(unchecked, Some("true")).unwrap());
+        assert!(!checks_overflow(manifest, Some("false")).unwrap());
+        assert!(checks_overflow(manifest, Some("yes")).is_err());
+    }
+
+    #[test]
+    fn executes() {
+        let mut command = DummyCommand {
+            exit_code: 1,
+            stdout: "a\nb\n".to_string(),
+        };
+        let mut child = command.spawn().unwrap();
+        let execution = execute(&mut child, b"3\n", OUTPUT_LIMIT, Instant::now()).unwrap();
+        assert_eq!(child.stdin, b"3\n");
+        assert_eq!(execution.stdout, "a\nb\n");
+        assert_eq!(execution.exit_code, Some(1));
+        assert!(!execution.succeeded());
+    }
+
+    #[test]
+    fn limits_output() {
+        let mut command = DummyCommand {
+            exit_code: 0,
+            stdout: "é".repeat(10_000),
+        };
+        let mut child = command.spawn().unwrap();
+        let (output, exceeded) = read_output(&mut child, 20_001).unwrap();
+        assert_eq!(output, "é".repeat(10_000));
+        assert!(!exceeded);
+
+        let mut child = command.spawn().unwrap();
+        let (output, exceeded) = read_output(&mut child, 9).unwrap();
+        assert_eq!(output, "éééé\u{FFFD}");
+        assert!(exceeded);
+        let execution = execute(&mut command.spawn().unwrap(), b"", 9, Instant::now()).unwrap();
+        assert!(execution.output_limit_exceeded);
+        assert!(!execution.succeeded());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn detects_stack_overflow() {
+        let stderr = "\nthread 'main' (5111) has overflowed its stack\nfatal runtime error: stack overflow, aborting";
+        assert_eq!(
+            stack_overflow(Some(libc::SIGABRT), stderr),
+            Some("thread 'main' (5111) has overflowed its stack")
+        );
+        assert!(stack_overflow(Some(libc::SIGSEGV), stderr).is_some());
+        assert_eq!(stack_overflow(Some(libc::SIGKILL), stderr), None);
+        assert_eq!(
+            stack_overflow(Some(libc::SIGSEGV), "index out of bounds"),
+            None
+        );
+        assert_eq!(stack_overflow(None, stderr), None);
+    }
+
+    #[test]
+    fn finds_panics() {
+        let stderr = "\nthread 'main' (5111) panicked at src/bin/a.rs:5:13:\nattempt to multiply with overflow\nnote: run with `RUST_BACKTRACE=1` environment variable to display a backtrace";
+        let panic = find_panic(stderr).unwrap();
+        assert_eq!(
+            panic,
+            Panic {
+                location: "src/bin/a.rs:5:13".to_string(),
+                message: "attempt to multiply with overflow".to_string(),
+            }
+        );
+        assert!(panic.is_overflow());
+        let divide =
+            find_panic("thread 'main' (7) panicked at src/main.rs:1:1:\nattempt to divide by zero")
+                .unwrap();
+        assert!(!divide.is_overflow());
+        assert_eq!(find_panic("Killed"), None);
+    }
+}
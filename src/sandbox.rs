@@ -0,0 +1,140 @@
+#[cfg(target_os = "linux")]
+mod linux;
+
+use crate::color::{Palette, Style};
+
+use std::env;
+use std::fs;
+use std::io::Result;
+use std::path::PathBuf;
+use std::process::{self, Command};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// CPU time of a sandboxed program without a time limit of its own, such as a generator.
+pub const DEFAULT_CPU_LIMIT: Duration = Duration::from_secs(60);
+
+static NEXT_DIR: AtomicUsize = AtomicUsize::new(0);
+
+/// Isolation for untrusted solutions and generators.
+///
+/// On Linux a sandboxed program has no network, may write only to a temp dir, and runs a limited
+/// number of processes for a limited CPU time. Without unprivileged user namespaces only the
+/// network and the CPU time are limited.
+pub struct Sandbox {
+    /// The only directory sandboxed programs may write to, also their `TMPDIR`.
+    dir: PathBuf,
+    namespaces: bool,
+}
+
+impl Sandbox {
+    /// Creates the temp dir of the sandboxed programs, warning about what cannot be isolated here.
+    pub fn new(palette: Palette) -> Result<Self> {
+        let dir = env::temp_dir().join(format!(
+            "atcoder-rs-sandbox-{}-{}",
+            process::id(),
+            NEXT_DIR.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&dir)?;
+        let dir = dir.canonicalize()?;
+        #[cfg(target_os = "linux")]
+        let namespaces = linux::namespaces_available(&dir);
+        #[cfg(not(target_os = "linux"))]
+        let namespaces = false;
+        let warning = if !cfg!(target_os = "linux") {
+            Some("the sandbox is only available on Linux; programs run unrestricted")
+        } else if !namespaces {
+            Some("user namespaces are unavailable; sandboxed programs can still write files anywhere and start any number of processes")
+        } else {
+            None
+        };
+        if let Some(warning) = warning {
+            eprintln!("{} {warning}", palette.paint(Style::Error, "Warning:"));
+        }
+        Ok(Self { dir, namespaces })
+    }
+
+    /// Makes `command` run in the sandbox, killed once it has used `cpu_limit` of CPU time.
+    pub fn apply(&self, command: &mut Command, cpu_limit: Option<Duration>) {
+        command.env("TMPDIR", &self.dir);
+        #[cfg(target_os = "linux")]
+        linux::apply(command, &self.dir, self.namespaces, cpu_limit);
+        #[cfg(not(target_os = "linux"))]
+        let _ = (cpu_limit, self.namespaces);
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod sandbox_test {
+    use super::Sandbox;
+    use crate::color::Palette;
+    use crate::syscommand::{SysChild, SysCommand};
+    use std::process::{Command, Stdio};
+    use std::time::Duration;
+
+    fn run(sandbox: &Sandbox, script: &str) -> (i32, String) {
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg(script)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null());
+        sandbox.apply(&mut command, Some(Duration::from_millis(1500)));
+        let mut child = SysCommand::spawn(&mut command).unwrap();
+        let mut output = Vec::new();
+        let mut buf = [0u8; 256];
+        loop {
+            let n = child.stdout_read(&mut buf).unwrap();
+            if n == 0 {
+                break;
+            }
+            output.extend_from_slice(&buf[..n]);
+        }
+        let code = child.exit_code().unwrap();
+        (code, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn limits() {
+        let sandbox = Sandbox::new(Palette::PLAIN).unwrap();
+        let (code, output) = run(
+            &sandbox,
+            "ulimit -t; grep '^Seccomp:' /proc/self/status; echo ok > \"$TMPDIR/file\" && cat \"$TMPDIR/file\"",
+        );
+        assert_eq!(code, 0);
+        assert_eq!(output, "2\nSeccomp:\t2\nok\n");
+        assert_eq!(
+            std::fs::read_to_string(sandbox.dir.join("file")).unwrap(),
+            "ok\n"
+        );
+    }
+
+    #[test]
+    fn isolates() {
+        let sandbox = Sandbox::new(Palette::PLAIN).unwrap();
+        if !sandbox.namespaces {
+            return;
+        }
+        let outside = std::env::current_dir()
+            .unwrap()
+            .join("target/sandbox-escape");
+        let (code, output) = run(
+            &sandbox,
+            &format!(
+                "grep -c : /proc/self/net/dev; grep -o 'processes *[0-9][0-9]*' /proc/self/limits; echo escaped > {}",
+                outside.display()
+            ),
+        );
+        assert_ne!(code, 0);
+        // Only the loopback interface, and 64 processes at most.
+        assert_eq!(output, "1\nprocesses             64\n");
+        assert!(!outside.exists());
+    }
+}
@@ -0,0 +1,274 @@
+use std::ffi::CString;
+use std::io::{Error, Result};
+use std::mem::{self, offset_of};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+
+/// Processes and threads a sandboxed program may run at once.
+const MAX_PROCESSES: u64 = 64;
+
+#[cfg(target_arch = "x86_64")]
+const AUDIT_ARCH: Option<u32> = Some(0xc000_003e);
+#[cfg(target_arch = "aarch64")]
+const AUDIT_ARCH: Option<u32> = Some(0xc000_00b7);
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+const AUDIT_ARCH: Option<u32> = None;
+
+/// Syscalls that could undo the sandbox or reach outside it; they fail with `EPERM`.
+const DENIED_SYSCALLS: [libc::c_long; 22] = [
+    libc::SYS_mount,
+    libc::SYS_umount2,
+    libc::SYS_pivot_root,
+    libc::SYS_mount_setattr,
+    libc::SYS_open_tree,
+    libc::SYS_move_mount,
+    libc::SYS_fsopen,
+    libc::SYS_fsmount,
+    libc::SYS_unshare,
+    libc::SYS_setns,
+    libc::SYS_ptrace,
+    libc::SYS_process_vm_readv,
+    libc::SYS_process_vm_writev,
+    libc::SYS_bpf,
+    libc::SYS_perf_event_open,
+    libc::SYS_io_uring_setup,
+    libc::SYS_keyctl,
+    libc::SYS_add_key,
+    libc::SYS_request_key,
+    libc::SYS_init_module,
+    libc::SYS_finit_module,
+    libc::SYS_kexec_load,
+];
+
+/// Everything the child needs, prepared beforehand as it must not allocate between fork and exec.
+struct Setup {
+    writable_dir: CString,
+    uid_map: CString,
+    gid_map: CString,
+    namespaces: bool,
+    cpu_limit: Option<u64>,
+    filter: Vec<libc::sock_filter>,
+}
+
+impl Setup {
+    fn new(writable_dir: &Path, namespaces: bool, cpu_limit: Option<Duration>) -> Self {
+        // SAFETY: `getuid` and `getgid` cannot fail.
+        let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+        Self {
+            writable_dir: CString::new(writable_dir.as_os_str().as_bytes()).unwrap_or_default(),
+            uid_map: CString::new(format!("{uid} {uid} 1")).unwrap(),
+            gid_map: CString::new(format!("{gid} {gid} 1")).unwrap(),
+            namespaces,
+            cpu_limit: cpu_limit.map(|limit| (limit.as_secs_f64().ceil() as u64).max(1)),
+            filter: filter(),
+        }
+    }
+
+    /// Applies the limits to the calling process, which is about to exec the program.
+    fn enter(&self) -> Result<()> {
+        set_limit(libc::RLIMIT_CORE, 0, 0)?;
+        if let Some(seconds) = self.cpu_limit {
+            // Past the soft limit the program gets SIGXCPU, past the hard one SIGKILL.
+            set_limit(libc::RLIMIT_CPU, seconds, seconds + 1)?;
+        }
+        if self.namespaces {
+            self.enter_namespaces()?;
+            // Processes are counted per user namespace, so only the program's own are limited.
+            set_limit(libc::RLIMIT_NPROC, MAX_PROCESSES, MAX_PROCESSES)?;
+        }
+        self.install_filter()
+    }
+
+    /// Moves to new namespaces without network, where everything but the writable dir is read-only.
+    fn enter_namespaces(&self) -> Result<()> {
+        // SAFETY: the calls below only affect the calling process, and their strings outlive them.
+        check(unsafe {
+            libc::unshare(
+                libc::CLONE_NEWUSER | libc::CLONE_NEWNS | libc::CLONE_NEWNET | libc::CLONE_NEWIPC,
+            )
+        })?;
+        write_file(c"/proc/self/setgroups", c"deny")?;
+        write_file(c"/proc/self/uid_map", &self.uid_map)?;
+        write_file(c"/proc/self/gid_map", &self.gid_map)?;
+        // Keep the mounts below from propagating back to the parent namespace.
+        check(unsafe {
+            libc::mount(
+                c"none".as_ptr(),
+                c"/".as_ptr(),
+                std::ptr::null(),
+                libc::MS_REC | libc::MS_PRIVATE,
+                std::ptr::null(),
+            )
+        })?;
+        let dir = self.writable_dir.as_ptr();
+        check(unsafe { libc::mount(dir, dir, std::ptr::null(), libc::MS_BIND, std::ptr::null()) })?;
+        set_mount_attr(c"/", libc::AT_RECURSIVE, libc::MOUNT_ATTR_RDONLY, 0)?;
+        set_mount_attr(&self.writable_dir, 0, 0, libc::MOUNT_ATTR_RDONLY)
+    }
+
+    fn install_filter(&self) -> Result<()> {
+        let program = libc::sock_fprog {
+            len: self.filter.len() as libc::c_ushort,
+            filter: self.filter.as_ptr() as *mut libc::sock_filter,
+        };
+        // SAFETY: the filter outlives the call, which copies it into the kernel.
+        check(unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) })?;
+        check(unsafe {
+            libc::prctl(
+                libc::PR_SET_SECCOMP,
+                libc::SECCOMP_MODE_FILTER,
+                &program as *const libc::sock_fprog,
+            )
+        })
+    }
+}
+
+fn check(result: libc::c_int) -> Result<()> {
+    if result < 0 {
+        Err(Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+fn set_limit(resource: libc::__rlimit_resource_t, soft: u64, hard: u64) -> Result<()> {
+    let limit = libc::rlimit {
+        rlim_cur: soft as libc::rlim_t,
+        rlim_max: hard as libc::rlim_t,
+    };
+    // SAFETY: `setrlimit` only reads `limit`.
+    check(unsafe { libc::setrlimit(resource, &limit) })
+}
+
+fn write_file(path: &std::ffi::CStr, contents: &std::ffi::CStr) -> Result<()> {
+    // SAFETY: the file descriptor is closed before returning, and the buffers outlive the calls.
+    let fd = unsafe { libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC) };
+    check(fd)?;
+    let bytes = contents.to_bytes();
+    let written = unsafe { libc::write(fd, bytes.as_ptr().cast(), bytes.len()) };
+    let error = Error::last_os_error();
+    unsafe {
+        libc::close(fd);
+    }
+    if written != bytes.len() as isize {
+        return Err(error);
+    }
+    Ok(())
+}
+
+fn set_mount_attr(path: &std::ffi::CStr, flags: libc::c_int, set: u64, clear: u64) -> Result<()> {
+    let attr = libc::mount_attr {
+        attr_set: set,
+        attr_clr: clear,
+        propagation: 0,
+        userns_fd: 0,
+    };
+    // SAFETY: `mount_setattr` only reads `path` and `attr`, whose size it is given.
+    let result = unsafe {
+        libc::syscall(
+            libc::SYS_mount_setattr,
+            libc::AT_FDCWD,
+            path.as_ptr(),
+            flags,
+            &attr as *const libc::mount_attr,
+            mem::size_of::<libc::mount_attr>(),
+        )
+    };
+    check(result as libc::c_int)
+}
+
+fn statement(code: u32, k: u32) -> libc::sock_filter {
+    libc::sock_filter {
+        code: code as u16,
+        jt: 0,
+        jf: 0,
+        k,
+    }
+}
+
+fn jump_if_equal(k: u32, jt: u8, jf: u8) -> libc::sock_filter {
+    libc::sock_filter {
+        code: (libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K) as u16,
+        jt,
+        jf,
+        k,
+    }
+}
+
+/// A seccomp filter that denies sockets other than Unix ones and the syscalls of `DENIED_SYSCALLS`.
+fn filter() -> Vec<libc::sock_filter> {
+    let load = |offset: usize| statement(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, offset as u32);
+    let ret = |action: u32| statement(libc::BPF_RET | libc::BPF_K, action);
+    let mut filter = Vec::new();
+    let Some(arch) = AUDIT_ARCH else {
+        filter.push(ret(libc::SECCOMP_RET_ALLOW));
+        return filter;
+    };
+    // Syscall numbers differ between architectures, so a foreign one cannot be judged.
+    filter.push(load(offset_of!(libc::seccomp_data, arch)));
+    filter.push(jump_if_equal(arch, 1, 0));
+    filter.push(ret(libc::SECCOMP_RET_KILL_PROCESS));
+    filter.push(load(offset_of!(libc::seccomp_data, nr)));
+    if cfg!(target_arch = "x86_64") {
+        // The x32 ABI numbers the same syscalls differently.
+        filter.push(libc::sock_filter {
+            code: (libc::BPF_JMP | libc::BPF_JGE | libc::BPF_K) as u16,
+            jt: 0,
+            jf: 1,
+            k: 0x4000_0000,
+        });
+        filter.push(ret(libc::SECCOMP_RET_ERRNO | libc::EPERM as u32));
+    }
+    for syscall in DENIED_SYSCALLS {
+        filter.push(jump_if_equal(syscall as u32, 0, 1));
+        filter.push(ret(libc::SECCOMP_RET_ERRNO | libc::EPERM as u32));
+    }
+    filter.push(jump_if_equal(libc::SYS_socket as u32, 0, 3));
+    // The low half of the first argument, the address family, on little-endian machines.
+    filter.push(load(offset_of!(libc::seccomp_data, args)));
+    filter.push(jump_if_equal(libc::AF_UNIX as u32, 1, 0));
+    filter.push(ret(libc::SECCOMP_RET_ERRNO | libc::EACCES as u32));
+    filter.push(ret(libc::SECCOMP_RET_ALLOW));
+    filter
+}
+
+/// Whether unprivileged user namespaces and read-only remounts work, tried in a forked child.
+pub fn namespaces_available(writable_dir: &Path) -> bool {
+    let setup = Setup::new(writable_dir, true, None);
+    // SAFETY: the child only makes syscalls before exiting, without allocating or locking.
+    match unsafe { libc::fork() } {
+        -1 => false,
+        0 => {
+            let code = i32::from(setup.enter_namespaces().is_err());
+            // SAFETY: `_exit` skips the destructors and handlers the child must not run.
+            unsafe { libc::_exit(code) }
+        }
+        pid => {
+            let mut status = 0;
+            // SAFETY: `waitpid` only writes to `status`.
+            while unsafe { libc::waitpid(pid, &mut status, 0) } < 0 {
+                if Error::last_os_error().kind() != std::io::ErrorKind::Interrupted {
+                    return false;
+                }
+            }
+            libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 0
+        }
+    }
+}
+
+/// Makes `command` enter the sandbox before it execs.
+pub fn apply(
+    command: &mut Command,
+    writable_dir: &Path,
+    namespaces: bool,
+    cpu_limit: Option<Duration>,
+) {
+    let setup = Setup::new(writable_dir, namespaces, cpu_limit);
+    // SAFETY: `enter` only makes syscalls on data prepared beforehand.
+    unsafe {
+        command.pre_exec(move || setup.enter());
+    }
+}
@@ -0,0 +1,178 @@
+use std::fmt::{self, Display, Formatter};
+
+/// A complexity class a running time is fitted against.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Complexity {
+    Constant,
+    Log,
+    Linear,
+    Linearithmic,
+    Quadratic,
+    Cubic,
+}
+
+impl Complexity {
+    pub const ALL: [Complexity; 6] = [
+        Complexity::Constant,
+        Complexity::Log,
+        Complexity::Linear,
+        Complexity::Linearithmic,
+        Complexity::Quadratic,
+        Complexity::Cubic,
+    ];
+
+    /// The growth of the class at `n`; zero for `O(1)`, whose time is all in the constant term.
+    fn growth(self, n: f64) -> f64 {
+        match self {
+            Complexity::Constant => 0.0,
+            Complexity::Log => n.ln(),
+            Complexity::Linear => n,
+            Complexity::Linearithmic => n * n.ln(),
+            Complexity::Quadratic => n * n,
+            Complexity::Cubic => n * n * n,
+        }
+    }
+}
+
+impl Display for Complexity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Complexity::Constant => "O(1)",
+            Complexity::Log => "O(log N)",
+            Complexity::Linear => "O(N)",
+            Complexity::Linearithmic => "O(N log N)",
+            Complexity::Quadratic => "O(N²)",
+            Complexity::Cubic => "O(N³)",
+        })
+    }
+}
+
+/// `time = constant + factor * growth(n)`, fitted to measurements.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fit {
+    pub complexity: Complexity,
+    /// Fixed cost, such as process startup.
+    pub constant: f64,
+    pub factor: f64,
+    /// Root mean square of the relative errors of the fitted times.
+    pub error: f64,
+}
+
+impl Fit {
+    /// Least squares fit of `(n, time)` samples, weighted so that every sample counts by its relative error.
+    ///
+    /// Both coefficients are kept non-negative. `None` without samples or with a non-positive time.
+    pub fn new(complexity: Complexity, samples: &[(f64, f64)]) -> Option<Fit> {
+        if samples.is_empty() || samples.iter().any(|&(_, t)| t <= 0.0) {
+            return None;
+        }
+        let (mut s_w, mut s_f, mut s_ff, mut s_t, mut s_ft) = (0.0, 0.0, 0.0, 0.0, 0.0);
+        for &(n, t) in samples {
+            let (w, f) = (1.0 / (t * t), complexity.growth(n));
+            s_w += w;
+            s_f += w * f;
+            s_ff += w * f * f;
+            s_t += w * t;
+            s_ft += w * f * t;
+        }
+        let det = s_w * s_ff - s_f * s_f;
+        let (mut constant, mut factor) = if det.abs() > f64::EPSILON * s_w * s_ff {
+            (
+                (s_t * s_ff - s_f * s_ft) / det,
+                (s_w * s_ft - s_f * s_t) / det,
+            )
+        } else {
+            (s_t / s_w, 0.0)
+        };
+        if factor < 0.0 {
+            (constant, factor) = (s_t / s_w, 0.0);
+        } else if constant < 0.0 {
+            (constant, factor) = (0.0, s_ft / s_ff);
+        }
+        let squares: f64 = samples
+            .iter()
+            .map(|&(n, t)| ((constant + factor * complexity.growth(n) - t) / t).powi(2))
+            .sum();
+        Some(Fit {
+            complexity,
+            constant,
+            factor,
+            error: (squares / samples.len() as f64).sqrt(),
+        })
+    }
+
+    /// Fits of every class, the best first.
+    ///
+    /// A class is only preferred to a slower-growing one when it fits clearly better, so that
+    /// noise does not turn `O(N)` into `O(N log N)`.
+    pub fn all(samples: &[(f64, f64)]) -> Vec<Fit> {
+        let mut fits: Vec<Fit> = Complexity::ALL
+            .iter()
+            .filter_map(|&complexity| Fit::new(complexity, samples))
+            .collect();
+        fits.sort_by(|a, b| a.error.total_cmp(&b.error));
+        // Within 10% or half a percentage point of the best error, the slowest-growing class wins.
+        let tolerance = fits.first().map_or(0.0, |fit| fit.error * 1.1 + 0.005);
+        let simplest = fits
+            .iter()
+            .enumerate()
+            .filter(|(_, fit)| fit.error <= tolerance)
+            .min_by_key(|(_, fit)| fit.complexity as usize)
+            .map(|(i, _)| i);
+        if let Some(simplest) = simplest {
+            let simplest = fits.remove(simplest);
+            fits.insert(0, simplest);
+        }
+        fits
+    }
+
+    pub fn project(&self, n: f64) -> f64 {
+        self.constant + self.factor * self.complexity.growth(n)
+    }
+}
+
+#[cfg(test)]
+mod scale_test {
+    use super::{Complexity, Fit};
+
+    fn samples(time: impl Fn(f64) -> f64) -> Vec<(f64, f64)> {
+        (0..8)
+            .map(|i| {
+                let n = 1000.0 * 2f64.powi(i);
+                // A little deterministic noise.
+                let noise = if i % 2 == 0 { 1.02 } else { 0.98 };
+                (n, time(n) * noise)
+            })
+            .collect()
+    }
+
+    fn best(time: impl Fn(f64) -> f64) -> Complexity {
+        Fit::all(&samples(time))[0].complexity
+    }
+
+    #[test]
+    fn finds_the_class() {
+        assert_eq!(best(|_| 5.0), Complexity::Constant);
+        assert_eq!(best(|n| 1.0 + 1e-4 * n), Complexity::Linear);
+        assert_eq!(best(|n| 1.0 + 1e-5 * n * n.ln()), Complexity::Linearithmic);
+        assert_eq!(best(|n| 1.0 + 1e-8 * n * n), Complexity::Quadratic);
+        assert_eq!(best(|n| 1.0 + 1e-12 * n * n * n), Complexity::Cubic);
+    }
+
+    #[test]
+    fn projects() {
+        let fit = Fit::new(
+            Complexity::Quadratic,
+            &[(1000.0, 2.0), (2000.0, 5.0), (4000.0, 17.0)],
+        )
+        .unwrap();
+        assert!((fit.constant - 1.0).abs() < 0.1, "{fit:?}");
+        assert!(
+            (fit.project(200_000.0) / 40_000.0 - 1.0).abs() < 0.05,
+            "{fit:?}"
+        );
+        assert_eq!(Fit::new(Complexity::Linear, &[]), None);
+        assert_eq!(Fit::new(Complexity::Linear, &[(1.0, 0.0)]), None);
+        assert_eq!(Complexity::Linearithmic.to_string(), "O(N log N)");
+    }
+}
@@ -1,46 +1,77 @@
+#[cfg(unix)]
+mod groups;
+
+use crate::data::CircularBuffer;
+
 use std::io::prelude::*;
-use std::io::{Error, ErrorKind, Result};
-use std::process::{Child, Command};
+use std::io::{self, BufReader, Error, Result};
+use std::process::{Child, Command, ExitStatus};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
 pub trait SysCommand<C: SysChild> {
     fn status(&mut self) -> Result<i32>;
     fn spawn(&mut self) -> Result<C>;
 }
 
+/// Resource usage of a finished child process.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Usage {
+    /// Peak resident set size in KiB.
+    pub max_rss_kb: u64,
+    pub user_time: Duration,
+    pub sys_time: Duration,
+}
+
+impl Usage {
+    /// User plus system CPU time.
+    pub fn cpu_time(&self) -> Duration {
+        self.user_time + self.sys_time
+    }
+}
+
 pub trait SysChild {
     fn stdin_write(&mut self, buf: &[u8]) -> Result<usize>;
     fn stdout_read(&mut self, buf: &mut [u8]) -> Result<usize>;
 
-    fn stdout_read_line(&mut self, buf: &mut String) -> Result<usize> {
-        let mut stdout_byte = [0u8; 1];
-        let mut sum = 0;
-        loop {
-            let read_num = self.stdout_read(&mut stdout_byte)?;
-            if read_num <= 0 {
-                break;
-            }
-            sum += read_num;
-            let c = stdout_byte[0] as char;
-            buf.push(c);
-            if c == '\n' {
-                break;
-            }
-        }
-        Ok(sum)
-    }
+    /// Closes stdin so that the child sees the end of its input.
+    fn close_stdin(&mut self) {}
 
     fn exit_code(&mut self) -> Result<i32>;
 
     fn kill(&mut self) -> Result<()>;
+
+    /// Kills the child, with its process group if it leads one, once it has run for `limit`.
+    fn set_time_limit(&mut self, _limit: Duration) {}
+
+    /// Whether the child was killed for exceeding its time limit.
+    fn timed_out(&self) -> bool {
+        false
+    }
+
+    /// Resource usage, available once the child has been waited for.
+    fn usage(&self) -> Option<Usage> {
+        None
+    }
+
+    /// The signal that killed the child, once it has been waited for.
+    fn signal(&self) -> Option<i32> {
+        None
+    }
+
+    /// The last lines the child wrote to stderr, if stderr is piped.
+    fn stderr_excerpt(&mut self) -> String {
+        String::new()
+    }
 }
 
-impl SysCommand<Child> for Command {
+impl SysCommand<Process> for Command {
     fn status(&mut self) -> Result<i32> {
         match self.status() {
             Ok(exit_code) => match exit_code.code() {
                 Some(code) => Ok(code),
-                None => Err(Error::new(
-                    ErrorKind::Other,
+                None => Err(Error::other(
                     "Failed to retrieve the child process's status code.",
                 )),
             },
@@ -48,39 +79,270 @@ impl SysCommand<Child> for Command {
         }
     }
 
-    fn spawn(&mut self) -> Result<Child> {
-        match self.spawn() {
-            Ok(c) => Ok(c),
-            Err(e) => Err(e),
+    /// Spawns the child in its own process group, so that it can be killed with its descendants.
+    fn spawn(&mut self) -> Result<Process> {
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(self, 0);
+        let process = self.spawn().map(Process::new)?;
+        #[cfg(unix)]
+        groups::register(process.child.id() as i32);
+        Ok(process)
+    }
+}
+
+const STDERR_EXCERPT_LINES: usize = 10;
+
+type StderrTail = CircularBuffer<String, STDERR_EXCERPT_LINES>;
+
+#[derive(Default)]
+struct WatchdogState {
+    /// Set once the child has exited, after which its pid must not be signalled.
+    exited: bool,
+    timed_out: bool,
+}
+
+/// Kills a child that runs for too long.
+struct Watchdog {
+    state: Arc<(Mutex<WatchdogState>, Condvar)>,
+}
+
+impl Watchdog {
+    #[cfg(unix)]
+    fn start(pid: u32, limit: Duration) -> Self {
+        let state = Arc::new((Mutex::new(WatchdogState::default()), Condvar::new()));
+        let thread_state = Arc::clone(&state);
+        thread::spawn(move || {
+            let (lock, exited) = &*thread_state;
+            let state = lock.lock().unwrap();
+            let (mut state, _) = exited
+                .wait_timeout_while(state, limit, |state| !state.exited)
+                .unwrap();
+            if !state.exited {
+                let pid = pid as libc::pid_t;
+                // SAFETY: the child has not been reaped, so `pid` and its process group are still ours.
+                unsafe {
+                    libc::kill(-pid, libc::SIGKILL);
+                    libc::kill(pid, libc::SIGKILL);
+                }
+                state.timed_out = true;
+            }
+        });
+        Self { state }
+    }
+
+    /// Stops the watchdog; the child must have exited but not been reaped yet.
+    fn stop(&self) {
+        let (lock, exited) = &*self.state;
+        lock.lock().unwrap().exited = true;
+        exited.notify_all();
+    }
+
+    fn timed_out(&self) -> bool {
+        self.state.0.lock().unwrap().timed_out
+    }
+}
+
+/// A spawned child process.
+///
+/// A piped stderr is copied to our stderr while its tail is kept for reports.
+pub struct Process {
+    child: Child,
+    status: Option<ExitStatus>,
+    usage: Option<Usage>,
+    stderr_tail: Arc<Mutex<StderrTail>>,
+    stderr_thread: Option<JoinHandle<()>>,
+    watchdog: Option<Watchdog>,
+}
+
+impl Process {
+    fn new(mut child: Child) -> Self {
+        let stderr_tail = Arc::new(Mutex::new(StderrTail::new(STDERR_EXCERPT_LINES)));
+        let stderr_thread = child.stderr.take().map(|child_err| {
+            let stderr_tail = Arc::clone(&stderr_tail);
+            thread::spawn(move || {
+                let mut child_err = BufReader::new(child_err);
+                let mut line = Vec::new();
+                while let Ok(n) = child_err.read_until(b'\n', &mut line) {
+                    if n == 0 {
+                        break;
+                    }
+                    let _ = io::stderr().write_all(&line);
+                    let line_str = String::from_utf8_lossy(&line);
+                    stderr_tail
+                        .lock()
+                        .unwrap()
+                        .push(line_str.trim_end_matches(['\n', '\r']).to_string());
+                    line.clear();
+                }
+            })
+        });
+        Self {
+            child,
+            status: None,
+            usage: None,
+            stderr_tail,
+            stderr_thread,
+            watchdog: None,
         }
     }
+
+    #[cfg(unix)]
+    fn wait(&mut self) -> Result<ExitStatus> {
+        use std::os::unix::process::ExitStatusExt;
+
+        let pid = self.child.id() as libc::pid_t;
+        // Wait for the exit without reaping, so that neither the watchdog nor the group kill
+        // below ever signals a reused pid.
+        // SAFETY: `siginfo_t` is plain old data, and `waitid` only writes to it.
+        let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+        while unsafe {
+            libc::waitid(
+                libc::P_PID,
+                pid as libc::id_t,
+                &mut info,
+                libc::WEXITED | libc::WNOWAIT,
+            )
+        } != 0
+        {
+            let e = Error::last_os_error();
+            if e.kind() != io::ErrorKind::Interrupted {
+                return Err(e);
+            }
+        }
+        if let Some(watchdog) = &self.watchdog {
+            watchdog.stop();
+        }
+        // Descendants left running in the background must not outlive the child.
+        // SAFETY: the unreaped leader keeps the process group id ours.
+        unsafe {
+            libc::kill(-pid, libc::SIGKILL);
+        }
+        groups::unregister(pid);
+        let mut status = 0;
+        // SAFETY: `rusage` is plain old data, and `wait4` only writes to the given pointers.
+        let mut rusage: libc::rusage = unsafe { std::mem::zeroed() };
+        loop {
+            if unsafe { libc::wait4(pid, &mut status, 0, &mut rusage) } == pid {
+                break;
+            }
+            let e = Error::last_os_error();
+            if e.kind() != io::ErrorKind::Interrupted {
+                return Err(e);
+            }
+        }
+        let timeval = |t: libc::timeval| {
+            Duration::from_secs(t.tv_sec as u64) + Duration::from_micros(t.tv_usec as u64)
+        };
+        // `ru_maxrss` is in bytes on macOS and in KiB elsewhere.
+        let max_rss_kb = if cfg!(target_os = "macos") {
+            rusage.ru_maxrss as u64 / 1024
+        } else {
+            rusage.ru_maxrss as u64
+        };
+        self.usage = Some(Usage {
+            max_rss_kb,
+            user_time: timeval(rusage.ru_utime),
+            sys_time: timeval(rusage.ru_stime),
+        });
+        Ok(ExitStatus::from_raw(status))
+    }
+
+    #[cfg(not(unix))]
+    fn wait(&mut self) -> Result<ExitStatus> {
+        self.child.wait()
+    }
 }
 
-impl SysChild for Child {
+impl SysChild for Process {
     fn stdin_write(&mut self, buf: &[u8]) -> Result<usize> {
-        self.stdin.as_ref().unwrap().write(buf)
+        self.child.stdin.as_ref().unwrap().write(buf)
     }
 
     fn stdout_read(&mut self, buf: &mut [u8]) -> Result<usize> {
-        let child_out = self.stdout.as_mut().unwrap();
+        let child_out = self.child.stdout.as_mut().unwrap();
         child_out.read(buf)
     }
 
+    fn close_stdin(&mut self) {
+        drop(self.child.stdin.take());
+    }
+
     fn exit_code(&mut self) -> Result<i32> {
-        match self.wait() {
-            Ok(exit_code) => match exit_code.code() {
-                Some(code) => Ok(code),
-                None => Err(Error::new(
-                    ErrorKind::Other,
-                    "Failed to retrieve the child process's status code.",
-                )),
-            },
-            Err(e) => Err(e),
+        let status = match self.status {
+            Some(status) => status,
+            None => {
+                let status = self.wait()?;
+                self.status = Some(status);
+                status
+            }
+        };
+        match status.code() {
+            Some(code) => Ok(code),
+            None => Err(Error::other(
+                "Failed to retrieve the child process's status code.",
+            )),
         }
     }
 
+    /// Kills the child with its process group.
     fn kill(&mut self) -> Result<()> {
-        self.kill()
+        // The pid may have been reused once the child has been waited for.
+        if self.status.is_some() {
+            return Ok(());
+        }
+        #[cfg(unix)]
+        // SAFETY: the child has not been reaped, so its process group is still ours.
+        unsafe {
+            libc::kill(-(self.child.id() as libc::pid_t), libc::SIGKILL);
+        }
+        self.child.kill()
+    }
+
+    #[cfg(unix)]
+    fn set_time_limit(&mut self, limit: Duration) {
+        if self.status.is_none() {
+            self.watchdog = Some(Watchdog::start(self.child.id(), limit));
+        }
+    }
+
+    fn timed_out(&self) -> bool {
+        self.watchdog.as_ref().is_some_and(Watchdog::timed_out)
+    }
+
+    fn usage(&self) -> Option<Usage> {
+        self.usage
+    }
+
+    #[cfg(unix)]
+    fn signal(&self) -> Option<i32> {
+        use std::os::unix::process::ExitStatusExt;
+
+        self.status.and_then(|status| status.signal())
+    }
+
+    fn stderr_excerpt(&mut self) -> String {
+        if let Some(handle) = self.stderr_thread.take() {
+            // A killed child may leave the pipe open in a grandchild, so do not block on it.
+            let deadline = Instant::now() + Duration::from_millis(100);
+            while !handle.is_finished() && Instant::now() < deadline {
+                thread::sleep(Duration::from_millis(5));
+            }
+            if handle.is_finished() {
+                let _ = handle.join();
+            }
+        }
+        let tail = self.stderr_tail.lock().unwrap();
+        tail.iter().cloned().collect::<Vec<_>>().join("\n")
+    }
+}
+
+impl Drop for Process {
+    /// Kills and reaps a child that was not waited for, such as after an error.
+    fn drop(&mut self) {
+        if self.status.is_none() {
+            let _ = SysChild::kill(self);
+            let _ = self.wait();
+        }
     }
 }
 
@@ -130,6 +392,28 @@ pub(crate) mod syscommand_test {
         }
     }
 
+    /// A command that changes the file system when it runs, as `cargo new` does.
+    pub(crate) struct DummyEffectCommand<F: FnMut() -> Result<()>> {
+        pub(crate) exit_code: i32,
+        pub(crate) effect: F,
+    }
+
+    impl<F: FnMut() -> Result<()>> SysCommand<DummyChild> for DummyEffectCommand<F> {
+        fn status(&mut self) -> Result<i32> {
+            (self.effect)()?;
+            Ok(self.exit_code)
+        }
+
+        fn spawn(&mut self) -> Result<DummyChild> {
+            (self.effect)()?;
+            Ok(DummyChild {
+                exit_code: self.exit_code,
+                stdin: Vec::new(),
+                stdout: Box::new(Cursor::new(String::new())),
+            })
+        }
+    }
+
     impl SysChild for DummyChild {
         fn stdin_write(&mut self, buf: &[u8]) -> Result<usize> {
             self.stdin.write(buf)
@@ -147,4 +431,72 @@ pub(crate) mod syscommand_test {
             Ok(())
         }
     }
+
+    /// Whether `pid` is running; a zombie has exited already.
+    #[cfg(target_os = "linux")]
+    fn running(pid: i32) -> bool {
+        std::fs::read_to_string(format!("/proc/{pid}/stat"))
+            .is_ok_and(|stat| !stat.rsplit(')').next().unwrap_or("").starts_with(" Z"))
+    }
+
+    /// Whether `pid` stops running within a second.
+    #[cfg(target_os = "linux")]
+    fn exits(pid: i32) -> bool {
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(1);
+        while running(pid) {
+            if std::time::Instant::now() > deadline {
+                return false;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        true
+    }
+
+    /// Spawns `sh -c script`, where `script` prints the pid of a background process first.
+    #[cfg(target_os = "linux")]
+    fn spawn_with_background(script: &str) -> (super::Process, i32) {
+        let mut command = std::process::Command::new("sh");
+        command
+            .arg("-c")
+            .arg(script)
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::piped());
+        let mut child = SysCommand::spawn(&mut command).unwrap();
+        let mut line = Vec::new();
+        let mut byte = [0u8];
+        while child.stdout_read(&mut byte).unwrap() > 0 && byte[0] != b'\n' {
+            line.push(byte[0]);
+        }
+        let pid = String::from_utf8(line).unwrap().parse().unwrap();
+        assert!(running(pid));
+        (child, pid)
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn no_stray_processes_after_exit() {
+        let (mut child, pid) = spawn_with_background("sleep 30 >/dev/null & echo $!");
+        assert_eq!(child.exit_code().unwrap(), 0);
+        assert!(exits(pid));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn no_stray_processes_after_timeout() {
+        let (mut child, pid) = spawn_with_background("sleep 30 & echo $!; sleep 30");
+        child.set_time_limit(std::time::Duration::from_millis(200));
+        let mut rest = [0u8; 64];
+        while child.stdout_read(&mut rest).unwrap() > 0 {}
+        assert!(child.exit_code().is_err());
+        assert!(child.timed_out());
+        assert!(exits(pid));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn no_stray_processes_after_drop() {
+        let (child, pid) = spawn_with_background("sleep 30 & echo $!; sleep 30");
+        drop(child);
+        assert!(exits(pid));
+    }
 }
@@ -0,0 +1,68 @@
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::Once;
+
+/// More children than this at once are not killed on Ctrl-C, but still when they are dropped.
+const SLOTS: usize = 256;
+
+/// The process groups of the running children, killed when we are interrupted.
+///
+/// A child in its own process group does not get the terminal's Ctrl-C, so it would outlive us.
+/// Lock-free, so that the signal handler can read it.
+static GROUPS: [AtomicI32; SLOTS] = [const { AtomicI32::new(0) }; SLOTS];
+
+static HANDLER: Once = Once::new();
+
+/// Remembers the process group `pgid` until it is unregistered.
+pub fn register(pgid: i32) {
+    HANDLER.call_once(install_handler);
+    for slot in &GROUPS {
+        if slot
+            .compare_exchange(0, pgid, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+        {
+            return;
+        }
+    }
+}
+
+/// Forgets `pgid`; must be called before its leader is reaped, after which the id may be reused.
+pub fn unregister(pgid: i32) {
+    for slot in &GROUPS {
+        if slot
+            .compare_exchange(pgid, 0, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+        {
+            return;
+        }
+    }
+}
+
+fn install_handler() {
+    for signal in [libc::SIGINT, libc::SIGTERM] {
+        // SAFETY: the handler only calls async-signal-safe functions.
+        unsafe {
+            libc::signal(
+                signal,
+                on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t,
+            );
+        }
+    }
+}
+
+extern "C" fn on_signal(signal: libc::c_int) {
+    for slot in &GROUPS {
+        let pgid = slot.load(Ordering::SeqCst);
+        if pgid > 0 {
+            // SAFETY: `kill` is async-signal-safe, and registered groups have not been reaped.
+            unsafe {
+                libc::kill(-pgid, libc::SIGKILL);
+            }
+        }
+    }
+    // Die of the signal as we would have without the handler.
+    // SAFETY: `signal` and `raise` are async-signal-safe.
+    unsafe {
+        libc::signal(signal, libc::SIG_DFL);
+        libc::raise(signal);
+    }
+}